@@ -7,6 +7,8 @@ use bevy::{
 const CAMERA_ZOOM_SENSE: f32 = 0.3;
 const CAMERA_ZOOM_FLOATYNESS: f32 = 10.0;
 const CAMERA_PAN_FLOATYNESS: f32 = 10.0;
+/// Small enough to get down to moons when the length unit is an AU
+const CAMERA_MIN_ZOOM: f32 = 1e-6;
 
 /// Provides a nice 2d camera
 pub struct CameraPlugin;
@@ -14,8 +16,12 @@ pub struct CameraPlugin;
 impl Plugin for CameraPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(Startup, setup_camera);
-        app.add_systems(Update, (zoom_camera, pan_camera, get_world_coords));
+        app.add_systems(
+            Update,
+            (move_camera, zoom_camera, pan_camera, get_world_coords).chain(),
+        );
         app.init_resource::<CursorWorldCoords>();
+        app.add_event::<MoveCamera>();
     }
 }
 
@@ -23,6 +29,13 @@ impl Plugin for CameraPlugin {
 /// Resource that provides the current world coords of the camera
 pub struct CursorWorldCoords(pub Vec2);
 
+/// Send to glide the camera over to `position` with the given zoom, the orthographic scale
+#[derive(Event, Clone, Copy, Debug)]
+pub struct MoveCamera {
+    pub position: Vec2,
+    pub zoom: f32,
+}
+
 #[derive(Component)]
 struct CameraTarget {
     position: Vec3,
@@ -42,6 +55,13 @@ fn setup_camera(mut commands: Commands) {
     commands.spawn((Camera2d, Transform::default(), CameraTarget::default()));
 }
 
+fn move_camera(mut events: EventReader<MoveCamera>, mut camera_target: Single<&mut CameraTarget>) {
+    if let Some(event) = events.read().last() {
+        camera_target.position = event.position.extend(0.0);
+        camera_target.zoom = event.zoom.max(CAMERA_MIN_ZOOM);
+    }
+}
+
 fn zoom_camera(
     mouse_wheel: Res<AccumulatedMouseScroll>,
     camera_query: Single<(&mut Projection, &mut CameraTarget), With<Camera>>,
@@ -56,7 +76,7 @@ fn zoom_camera(
         }
     };
 
-    if (camera_target.zoom - projection.scale).abs() >= 0.01 {
+    if (camera_target.zoom - projection.scale).abs() >= 0.01 * camera_target.zoom {
         projection.scale = projection.scale.lerp(
             camera_target.zoom,
             CAMERA_ZOOM_FLOATYNESS * time.delta_secs(),
@@ -65,7 +85,7 @@ fn zoom_camera(
 
     // -= so taht it goes in correct direction
     camera_target.zoom -= mouse_wheel.delta.y * (camera_target.zoom * CAMERA_ZOOM_SENSE);
-    camera_target.zoom = camera_target.zoom.max(0.01);
+    camera_target.zoom = camera_target.zoom.max(CAMERA_MIN_ZOOM);
 
     Ok(())
 }
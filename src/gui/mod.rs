@@ -2,6 +2,11 @@ use bevy::{diagnostic::DiagnosticsStore, prelude::*};
 use bevy_egui::{egui, EguiGlobalSettings, EguiPlugin, EguiPrimaryContextPass};
 
 use crate::particle::ParticleCount;
+use crate::scenarios::{Scenario, ScenarioRequest};
+use crate::simulation::cosmology::Expansion;
+use crate::simulation::sinks::AccretedMass;
+use crate::simulation::units::UnitSystem;
+use crate::simulation::SimTime;
 
 mod performance;
 mod settings;
@@ -18,31 +23,69 @@ impl Plugin for GuiPlugin {
                 ..Default::default()
             })
             .add_systems(EguiPrimaryContextPass, egui_system)
-            .add_systems(Update, tools::tool_interactions_system);
+            .add_systems(
+                Update,
+                (
+                    tools::tool_interactions_system,
+                    tools::collider_actions_system,
+                ),
+            );
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn egui_system(
     mut contexts: bevy_egui::EguiContexts,
     mut tool_state: ResMut<tools::ToolState>,
     mut sim_settings: ResMut<crate::simulation::SimSettings>,
     diagnostics: Res<DiagnosticsStore>,
     particle_count: Res<ParticleCount>,
+    accreted_mass: Res<AccretedMass>,
+    expansion: Res<Expansion>,
+    sim_time: Res<SimTime>,
+    mut unit_system: ResMut<UnitSystem>,
+    mut scenario_request: ResMut<ScenarioRequest>,
 ) -> Result {
     let ctx = contexts.ctx_mut()?;
 
     egui::TopBottomPanel::top("menu_bar")
         .resizable(false)
-        .show(ctx, |ui| ui.label("n-body"));
+        .show(ctx, |ui| {
+            egui::MenuBar::new().ui(ui, |ui| {
+                ui.label("n-body");
+                ui.menu_button("scenarios", |ui| {
+                    for scenario in Scenario::ALL {
+                        if ui
+                            .button(format!("{scenario}"))
+                            .on_hover_text(scenario.description())
+                            .clicked()
+                        {
+                            scenario_request.0 = Some(scenario);
+                        }
+                    }
+                });
+            });
+        });
 
     // left side panel
     egui::SidePanel::left("left_panel").show(ctx, |ui| {
         egui_box(ui, "performance", true, |ui| {
-            performance::ui(ui, &diagnostics, &particle_count)
+            performance::ui(
+                ui,
+                &diagnostics,
+                &particle_count,
+                &accreted_mass,
+                &sim_time,
+                sim_settings.epoch,
+                &unit_system,
+            )
         });
 
         egui_box(ui, "simulation settings", true, |ui| {
-            sim_settings.ui(ui);
+            // only touch the resource on a real change, it sets G whenever it changes
+            let mut units = *unit_system;
+            sim_settings.ui(ui, &expansion, &mut units);
+            unit_system.set_if_neq(units);
         });
 
         egui_box(ui, "tools", true, |ui| {
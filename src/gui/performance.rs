@@ -3,8 +3,20 @@ use bevy::prelude::*;
 use bevy_egui::egui;
 
 use crate::particle::ParticleCount;
+use crate::scenarios::solar_system::calendar_date;
+use crate::simulation::sinks::AccretedMass;
+use crate::simulation::units::{Quantity, UnitSystem};
+use crate::simulation::SimTime;
 
-pub fn ui(ui: &mut egui::Ui, diagnostics: &DiagnosticsStore, particle_count: &ParticleCount) {
+pub fn ui(
+    ui: &mut egui::Ui,
+    diagnostics: &DiagnosticsStore,
+    particle_count: &ParticleCount,
+    accreted_mass: &AccretedMass,
+    sim_time: &SimTime,
+    epoch: Option<f64>,
+    units: &UnitSystem,
+) {
     egui::Grid::new("perf_stats_grid")
         .num_columns(2)
         .striped(true)
@@ -24,6 +36,22 @@ pub fn ui(ui: &mut egui::Ui, diagnostics: &DiagnosticsStore, particle_count: &Pa
 
             ui.label("particles");
             ui.label(format!("{}", particle_count.0));
+            ui.end_row();
+
+            ui.label("accreted mass")
+                .on_hover_text_at_pointer("total mass swallowed by sinks");
+            ui.label(format!("{:.1}", accreted_mass.0));
+            ui.end_row();
+
+            ui.label("time")
+                .on_hover_text_at_pointer("simulated time since the particles were cleared");
+            ui.label(units.format(sim_time.0, Quantity::Time));
+
+            if let (Some(epoch), Some(seconds)) = (epoch, units.seconds_per_time_unit()) {
+                ui.end_row();
+                ui.label("date");
+                ui.label(calendar_date(epoch + sim_time.0 * seconds / 86400.0));
+            }
         });
 }
 
@@ -1,9 +1,16 @@
+use bevy::prelude::*;
 use bevy_egui::egui;
 
+use crate::render::species_color;
+use crate::simulation::cosmology::{Expansion, FriedmannModel};
+use crate::simulation::gravity::GravitySolver;
+use crate::simulation::particle_life::InteractionMatrix;
+use crate::simulation::units::{Quantity, UnitSystem};
+use crate::simulation::wisdom_holman::Integrator;
 use crate::simulation::SimSettings;
 
 impl SimSettings {
-    pub fn ui(&mut self, ui: &mut egui::Ui) {
+    pub fn ui(&mut self, ui: &mut egui::Ui, expansion: &Expansion, units: &mut UnitSystem) {
         if ui.button("clear all particles").clicked() {
             self.should_clear_all_particles = true;
         }
@@ -28,12 +35,421 @@ impl SimSettings {
                 ui.checkbox(&mut self.enable_collisions, "");
                 ui.end_row();
 
+                ui.label("units").on_hover_text_at_pointer(
+                    "what a unit of mass, length and time means, a second of simulation is one unit of time",
+                );
+                egui::ComboBox::from_id_salt("unit_system")
+                    .selected_text(units.to_string())
+                    .show_ui(ui, |ui| {
+                        for system in UnitSystem::ALL {
+                            ui.selectable_value(units, system, system.to_string());
+                        }
+                    });
+                ui.end_row();
+
                 ui.label("gravity constant");
+                let (mass, length, time) = (
+                    units.unit(Quantity::Mass),
+                    units.unit(Quantity::Length),
+                    units.unit(Quantity::Time),
+                );
+                if *units == UnitSystem::Dimensionless {
+                    ui.label(format!("{}", self.gravitational_constant));
+                } else {
+                    ui.label(format!(
+                        "{:.4e} {length}³ / ({mass} {time}²)",
+                        self.gravitational_constant
+                    ));
+                }
                 ui.end_row();
 
                 ui.label("collision steps");
                 ui.add(egui::DragValue::new(&mut self.collision_steps).speed(0.1));
-                self.collision_steps = self.collision_steps.max(1)
+                self.collision_steps = self.collision_steps.max(1);
+                ui.end_row();
+
+                ui.label("particle life").on_hover_text_at_pointer(
+                    "replace gravity with the species interaction matrix",
+                );
+                ui.checkbox(&mut self.enable_particle_life, "");
+            });
+
+        egui::CollapsingHeader::new("gravity solver").show(ui, |ui| {
+            egui::Grid::new("gravity_solver_grid")
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.label("solver");
+                    egui::ComboBox::from_id_salt("gravity_solver")
+                        .selected_text(self.gravity_solver.to_string())
+                        .show_ui(ui, |ui| {
+                            for solver in [
+                                GravitySolver::Direct,
+                                GravitySolver::ParticleMesh,
+                                GravitySolver::Multipole,
+                            ] {
+                                ui.selectable_value(
+                                    &mut self.gravity_solver,
+                                    solver,
+                                    solver.to_string(),
+                                );
+                            }
+                        });
+                    ui.end_row();
+
+                    if self.gravity_solver == GravitySolver::ParticleMesh {
+                        ui.label("grid size").on_hover_text_at_pointer(
+                            "cells along each side of the mesh, the periodic box is used for the boundaries when enabled",
+                        );
+                        egui::ComboBox::from_id_salt("particle_mesh_grid_size")
+                            .selected_text(self.particle_mesh.grid_size.to_string())
+                            .show_ui(ui, |ui| {
+                                for grid_size in [32, 64, 128, 256, 512] {
+                                    ui.selectable_value(
+                                        &mut self.particle_mesh.grid_size,
+                                        grid_size,
+                                        grid_size.to_string(),
+                                    );
+                                }
+                            });
+                        ui.end_row();
+                    }
+
+                    if self.gravity_solver == GravitySolver::Multipole {
+                        ui.label("expansion order").on_hover_text_at_pointer(
+                            "higher is more accurate and slower, only works with isolated boundaries",
+                        );
+                        ui.add(egui::DragValue::new(&mut self.multipole.order).range(1..=16));
+                        ui.end_row();
+
+                        ui.label("leaf size")
+                            .on_hover_text_at_pointer("particles per box at the bottom of the tree");
+                        ui.add(egui::DragValue::new(&mut self.multipole.leaf_size).range(1..=256));
+                        ui.end_row();
+
+                        if self.periodic.enabled {
+                            ui.label("periodic box");
+                            ui.label("using particle mesh instead");
+                            ui.end_row();
+                        }
+                    }
+                });
+        });
+
+        egui::CollapsingHeader::new("integrator").show(ui, |ui| {
+            egui::Grid::new("integrator_grid").striped(true).show(ui, |ui| {
+                ui.label("integrator").on_hover_text_at_pointer(
+                    "wisdom-holman follows kepler orbits around the heaviest particle and only integrates gravity, \
+                    it hands over to verlet during close encounters or with fixed particles, periodic boxes, cosmology or post-newtonian terms",
+                );
+                egui::ComboBox::from_id_salt("integrator")
+                    .selected_text(self.integrator.to_string())
+                    .show_ui(ui, |ui| {
+                        for integrator in [Integrator::Verlet, Integrator::WisdomHolman] {
+                            ui.selectable_value(
+                                &mut self.integrator,
+                                integrator,
+                                integrator.to_string(),
+                            );
+                        }
+                    });
+                ui.end_row();
+
+                if self.integrator == Integrator::WisdomHolman {
+                    ui.label("encounter distance").on_hover_text_at_pointer(
+                        "mutual hill radii two bodies can come within before verlet takes over",
+                    );
+                    ui.add(
+                        egui::DragValue::new(&mut self.wisdom_holman.encounter_hill_radii)
+                            .speed(0.1)
+                            .range(0.0..=100.0),
+                    );
+                }
+            });
+        });
+
+        egui::CollapsingHeader::new("regularization").show(ui, |ui| {
+            egui::Grid::new("regularization_grid")
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.label("enabled").on_hover_text_at_pointer(
+                        "follow the orbits of close bound pairs with levi-civita regularization, so hard binaries keep their energy",
+                    );
+                    ui.checkbox(&mut self.regularization.enabled, "");
+                    ui.end_row();
+
+                    ui.label("capture distance").on_hover_text_at_pointer(
+                        "bound pairs closer than this are regularized, they are let go at twice this distance",
+                    );
+                    ui.add(
+                        egui::DragValue::new(&mut self.regularization.capture_distance)
+                            .speed(0.1)
+                            .range(0.1..=f32::MAX),
+                    );
+                });
+        });
+
+        egui::CollapsingHeader::new("periodic box").show(ui, |ui| {
+            egui::Grid::new("periodic_grid").striped(true).show(ui, |ui| {
+                ui.label("enabled").on_hover_text_at_pointer(
+                    "particles wrap around the box edges and feel gravity from every periodic image, always on with cosmology",
+                );
+                ui.add_enabled(
+                    !self.cosmology.enabled,
+                    egui::Checkbox::new(&mut self.periodic.enabled, ""),
+                );
+                ui.end_row();
+
+                ui.label("box size");
+                ui.add(egui::DragValue::new(&mut self.periodic.size).speed(1.0));
+                self.periodic.size = self.periodic.size.max(1.0);
+            });
+        });
+
+        egui::CollapsingHeader::new("cosmology").show(ui, |ui| {
+            self.cosmology_ui(ui, expansion);
+        });
+
+        egui::CollapsingHeader::new("post-newtonian").show(ui, |ui| {
+            egui::Grid::new("post_newtonian_grid")
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.label("1PN")
+                        .on_hover_text_at_pointer("first order correction, orbits precess");
+                    ui.checkbox(&mut self.post_newtonian.first_order, "");
+                    ui.end_row();
+
+                    ui.label("2.5PN").on_hover_text_at_pointer(
+                        "radiation reaction, binaries lose energy to gravitational waves",
+                    );
+                    ui.checkbox(&mut self.post_newtonian.radiation_reaction, "");
+                    ui.end_row();
+
+                    ui.label("speed of light");
+                    ui.add(
+                        egui::DragValue::new(&mut self.post_newtonian.speed_of_light).speed(1.0),
+                    );
+                    self.post_newtonian.speed_of_light =
+                        self.post_newtonian.speed_of_light.max(0.01);
+                });
+        });
+
+        egui::CollapsingHeader::new("tidal disruption").show(ui, |ui| {
+            egui::Grid::new("tidal_grid").striped(true).show(ui, |ui| {
+                ui.label("enabled").on_hover_text_at_pointer(
+                    "particles inside the roche limit of a much heavier body break apart",
+                );
+                ui.checkbox(&mut self.tidal.enabled, "");
+                ui.end_row();
+
+                ui.label("fragments");
+                ui.add(egui::DragValue::new(&mut self.tidal.fragments).speed(0.05));
+                self.tidal.fragments = self.tidal.fragments.max(2);
+                ui.end_row();
+
+                ui.label("fragment density")
+                    .on_hover_text_at_pointer("mass per area of fragments, sets their radius");
+                ui.add(egui::DragValue::new(&mut self.tidal.fragment_density).speed(0.01));
+                self.tidal.fragment_density = self.tidal.fragment_density.max(0.001);
+                ui.end_row();
+
+                ui.label("mass ratio").on_hover_text_at_pointer(
+                    "how many times heavier a body has to be to tear a particle apart",
+                );
+                ui.add(egui::DragValue::new(&mut self.tidal.mass_ratio).speed(1.0));
+                self.tidal.mass_ratio = self.tidal.mass_ratio.max(1.0);
+                ui.end_row();
+
+                ui.label("min fragment mass");
+                ui.add(egui::DragValue::new(&mut self.tidal.min_fragment_mass).speed(0.001));
+            });
+        });
+
+        egui::CollapsingHeader::new("gas").show(ui, |ui| {
+            egui::Grid::new("sph_settings_grid")
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.label("smoothing length")
+                        .on_hover_text_at_pointer("gas particles interact out to twice this");
+                    ui.add(egui::DragValue::new(&mut self.sph.smoothing_length).speed(0.1));
+                    self.sph.smoothing_length = self.sph.smoothing_length.max(0.01);
+                    ui.end_row();
+
+                    ui.label("adiabatic index");
+                    ui.add(egui::DragValue::new(&mut self.sph.gamma).speed(0.01));
+                    self.sph.gamma = self.sph.gamma.max(1.0);
+                    ui.end_row();
+
+                    ui.label("viscosity α");
+                    ui.add(egui::DragValue::new(&mut self.sph.viscosity_alpha).speed(0.01));
+                    ui.end_row();
+
+                    ui.label("viscosity β");
+                    ui.add(egui::DragValue::new(&mut self.sph.viscosity_beta).speed(0.01));
+                });
+        });
+
+        if self.enable_particle_life {
+            egui::CollapsingHeader::new("interaction matrix")
+                .default_open(true)
+                .show(ui, |ui| self.interactions.ui(ui));
+        }
+    }
+}
+
+impl SimSettings {
+    fn cosmology_ui(&mut self, ui: &mut egui::Ui, expansion: &Expansion) {
+        let cosmology = &mut self.cosmology;
+        egui::Grid::new("cosmology_grid")
+            .striped(true)
+            .show(ui, |ui| {
+                ui.label("enabled").on_hover_text_at_pointer(
+                    "positions are comoving and the universe expands with the scale factor",
+                );
+                ui.checkbox(&mut cosmology.enabled, "");
+                ui.end_row();
+
+                ui.label("model");
+                egui::ComboBox::from_id_salt("friedmann_model")
+                    .selected_text(format!("{}", cosmology.model))
+                    .show_ui(ui, |ui| {
+                        for model in [FriedmannModel::EinsteinDeSitter, FriedmannModel::LambdaCdm] {
+                            ui.selectable_value(&mut cosmology.model, model, format!("{model}"));
+                        }
+                    });
+                ui.end_row();
+
+                ui.label("hubble constant")
+                    .on_hover_text_at_pointer("expansion rate at a = 1, per second");
+                ui.add(egui::DragValue::new(&mut cosmology.hubble_constant).speed(0.001));
+                cosmology.hubble_constant = cosmology.hubble_constant.max(0.0);
+                ui.end_row();
+
+                if cosmology.model == FriedmannModel::LambdaCdm {
+                    ui.label("Ωm");
+                    ui.add(
+                        egui::DragValue::new(&mut cosmology.omega_matter)
+                            .speed(0.01)
+                            .range(0.0..=2.0),
+                    );
+                    ui.end_row();
+
+                    ui.label("ΩΛ");
+                    ui.add(
+                        egui::DragValue::new(&mut cosmology.omega_lambda)
+                            .speed(0.01)
+                            .range(0.0..=2.0),
+                    );
+                    ui.end_row();
+                }
+
+                ui.label("initial a");
+                ui.add(
+                    egui::DragValue::new(&mut cosmology.initial_scale_factor)
+                        .speed(0.001)
+                        .range(0.001..=1.0),
+                );
+                ui.end_row();
+
+                ui.label("a");
+                ui.label(format!("{:.4}", expansion.scale_factor));
+                ui.end_row();
+
+                ui.label("z");
+                ui.label(format!("{:.2}", expansion.redshift()));
+            });
+
+        if ui.button("reset expansion").clicked() {
+            self.should_reset_expansion = true;
+        }
+    }
+}
+
+impl InteractionMatrix {
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            let mut species = self.species();
+            ui.label("species");
+            ui.add(egui::DragValue::new(&mut species).speed(0.05).range(1..=8));
+            if species != self.species() {
+                self.resize(species);
+            }
+
+            if ui.button("randomize").clicked() {
+                self.randomize();
+            }
+        });
+
+        egui::Grid::new("particle_life_settings_grid")
+            .striped(true)
+            .show(ui, |ui| {
+                ui.label("force scale");
+                ui.add(egui::DragValue::new(&mut self.force_scale).speed(1.0));
+                ui.end_row();
+
+                ui.label("friction");
+                ui.add(egui::DragValue::new(&mut self.friction).speed(0.01));
+                self.friction = self.friction.max(0.0);
             });
+
+        ui.label("strength").on_hover_text_at_pointer(
+            "how the row species reacts to the column species, negative repels",
+        );
+
+        egui::Grid::new("interaction_matrix_grid").show(ui, |ui| {
+            ui.label("");
+            for b in 0..self.species() {
+                ui.colored_label(egui_color(species_color(b)), format!("{b}"));
+            }
+            ui.end_row();
+
+            for a in 0..self.species() {
+                ui.colored_label(egui_color(species_color(a)), format!("{a}"));
+                for b in 0..self.species() {
+                    let rule = self.get_mut(a, b);
+                    ui.add(
+                        egui::DragValue::new(&mut rule.strength)
+                            .speed(0.01)
+                            .range(-1.0..=1.0),
+                    )
+                    .on_hover_text_at_pointer(format!(
+                        "range {:.1} to {:.1}",
+                        rule.min_range, rule.max_range
+                    ));
+                }
+                ui.end_row();
+            }
+        });
+
+        egui::CollapsingHeader::new("ranges").show(ui, |ui| {
+            egui::Grid::new("interaction_range_grid")
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.label("");
+                    ui.label("min");
+                    ui.label("max");
+                    ui.end_row();
+
+                    for a in 0..self.species() {
+                        for b in 0..self.species() {
+                            let rule = self.get_mut(a, b);
+                            ui.label(format!("{a} → {b}"));
+                            ui.add(egui::DragValue::new(&mut rule.min_range).speed(0.1));
+                            ui.add(egui::DragValue::new(&mut rule.max_range).speed(0.1));
+                            rule.min_range = rule.min_range.max(0.01);
+                            rule.max_range = rule.max_range.max(rule.min_range);
+                            ui.end_row();
+                        }
+                    }
+                });
+        });
     }
 }
+
+fn egui_color(color: Color) -> egui::Color32 {
+    let color = color.to_srgba();
+    egui::Color32::from_rgb(
+        (color.red * 255.0) as u8,
+        (color.green * 255.0) as u8,
+        (color.blue * 255.0) as u8,
+    )
+}
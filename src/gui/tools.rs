@@ -1,10 +1,22 @@
 use bevy::prelude::*;
 use bevy_egui::egui;
 use std::fmt::Display;
+use std::sync::Arc;
 
 use crate::camera::CursorWorldCoords;
-use crate::particle::spawners::SpawnRandomParticles;
-use crate::particle::ParticleBundle;
+use crate::particle::spawners::{
+    ClusterModel, DiskProfile, ImageMask, LatticeKind, MassDistribution, RingRotation,
+    SizeDistribution, SpawnGalaxyDisk, SpawnLattice, SpawnRandomParticles, SpawnShape,
+    SpawnStarCluster, VelocityModel,
+};
+use crate::particle::{Mass, Particle, ParticleBundle, Radius};
+use crate::simulation::bonds::Bond;
+use crate::simulation::colliders::{Collider, ColliderShape, ContainerPreset};
+use crate::simulation::cosmology::Expansion;
+use crate::simulation::gravity::orbital_velocity;
+use crate::simulation::motion::{KinematicPath, OldPosition};
+use crate::simulation::units::{Quantity, UnitSystem};
+use crate::simulation::SimSettings;
 
 use super::value_editor_row;
 
@@ -12,6 +24,30 @@ use super::value_editor_row;
 enum Tool {
     SpawnParticle,
     SpawnRandomParticles,
+    ConnectParticles,
+    DrawCollider,
+    SpawnInOrbit,
+    SpawnGalaxy,
+    SpawnCluster,
+    SpawnLattice,
+}
+
+/// Which shape the collider tool draws
+#[derive(PartialEq, Debug, Copy, Clone)]
+enum ColliderKind {
+    Segment,
+    Rectangle,
+    Circle,
+    Polygon,
+}
+
+/// Which [KinematicPath] a pinned particle is spawned with
+#[derive(PartialEq, Debug, Copy, Clone)]
+enum PathKind {
+    /// Stays where it is put
+    Still,
+    Circular,
+    Linear,
 }
 
 #[derive(Resource)]
@@ -25,6 +61,79 @@ pub struct ToolState {
     amount: u32,
     inner_radius: f32,
     outer_radius: f32,
+    species: usize,
+    species_mix: usize,
+    bond_start: Option<Entity>,
+    stiffness: f32,
+    damping: f32,
+    breaking_strain: f32,
+    gas: bool,
+    internal_energy: f32,
+    pinned: bool,
+    path_kind: PathKind,
+    path_center: Vec2,
+    path_angular_speed: f32,
+    path_velocity: Vec2,
+    test_particles: bool,
+    sink: bool,
+    accretion_radius: f32,
+    collider_kind: ColliderKind,
+    restitution: f32,
+    polygon_points: Vec<Vec2>,
+    container_preset: ContainerPreset,
+    container_size: f32,
+    should_spawn_container: bool,
+    should_clear_colliders: bool,
+    orbit_primary: Option<Entity>,
+    eccentricity: f32,
+    retrograde: bool,
+    disk_profile: DiskProfile,
+    disk_mass: f32,
+    scale_length: f32,
+    bulge_mass: f32,
+    bulge_radius: f32,
+    nucleus_mass: f32,
+    toomre_q: f32,
+    clockwise: bool,
+    cluster_model: ClusterModel,
+    king_depth: f32,
+    cluster_mass: f32,
+    virial_radius: f32,
+    mass_distribution: MassDistribution,
+    min_mass: f32,
+    max_mass: f32,
+    mass_exponent: f32,
+    size_distribution: SizeDistribution,
+    min_radius: f32,
+    max_radius: f32,
+    particle_density: f32,
+    velocity_model: VelocityModel,
+    temperature: f32,
+    angular_velocity: f32,
+    central_mass: f32,
+    expansion_rate: f32,
+    virial_scaling: bool,
+    virial_ratio: f32,
+    spawn_shape: SpawnShape,
+    spawn_size: Vec2,
+    spiral_arms: u32,
+    pitch_angle: f32,
+    arm_width: f32,
+    ring_width: f32,
+    image_path: String,
+    image_mask: Option<Arc<ImageMask>>,
+    image_error: Option<String>,
+    color_from_image: bool,
+    lattice_kind: LatticeKind,
+    columns: u32,
+    rows: u32,
+    spacing: f32,
+    jitter: f32,
+    ring_rotation: RingRotation,
+    spectral_index: f32,
+    displacement: f32,
+    /// Copy of the [UnitSystem] resource, for labelling values and for G when spawning
+    units: UnitSystem,
 }
 
 impl Tool {
@@ -36,14 +145,56 @@ impl Tool {
                 Tool::SpawnParticle => {
                     state.mass_ui(ui);
                     state.radius_ui(ui);
+                    state.species_ui(ui);
+                    state.pinned_ui(ui);
+                    state.sink_ui(ui);
+                    state.gas_ui(ui);
                 }
                 Tool::SpawnRandomParticles => {
+                    state.mass_distribution_ui(ui);
+                    state.size_distribution_ui(ui);
+                    state.amount_ui(ui);
+                    state.velocity_model_ui(ui);
+                    state.spawn_shape_ui(ui);
+                    state.species_mix_ui(ui);
+                    state.test_particles_ui(ui);
+                    state.gas_ui(ui);
+                }
+                Tool::ConnectParticles => {
+                    state.stiffness_ui(ui);
+                    state.damping_ui(ui);
+                    state.breaking_strain_ui(ui);
+                }
+                Tool::DrawCollider => {
+                    state.collider_kind_ui(ui);
+                    state.restitution_ui(ui);
+                    state.container_ui(ui);
+                }
+                Tool::SpawnInOrbit => {
                     state.mass_ui(ui);
                     state.radius_ui(ui);
+                    state.species_ui(ui);
+                    state.orbit_ui(ui);
+                    state.test_particles_ui(ui);
+                }
+                Tool::SpawnGalaxy => {
                     state.amount_ui(ui);
-                    state.max_random_velocity_ui(ui);
-                    state.inner_radius_ui(ui);
+                    state.radius_ui(ui);
                     state.outer_radius_ui(ui);
+                    state.galaxy_ui(ui);
+                    state.test_particles_ui(ui);
+                }
+                Tool::SpawnCluster => {
+                    state.amount_ui(ui);
+                    state.radius_ui(ui);
+                    state.cluster_ui(ui);
+                }
+                Tool::SpawnLattice => {
+                    state.mass_ui(ui);
+                    state.radius_ui(ui);
+                    state.species_ui(ui);
+                    state.lattice_ui(ui);
+                    state.test_particles_ui(ui);
                 }
             });
     }
@@ -65,6 +216,42 @@ impl ToolState {
                     &mut self.selected_tool,
                     Tool::SpawnRandomParticles,
                     format!("{}", Tool::SpawnRandomParticles),
+                );
+
+                ui.selectable_value(
+                    &mut self.selected_tool,
+                    Tool::ConnectParticles,
+                    format!("{}", Tool::ConnectParticles),
+                );
+
+                ui.selectable_value(
+                    &mut self.selected_tool,
+                    Tool::DrawCollider,
+                    format!("{}", Tool::DrawCollider),
+                );
+
+                ui.selectable_value(
+                    &mut self.selected_tool,
+                    Tool::SpawnInOrbit,
+                    format!("{}", Tool::SpawnInOrbit),
+                );
+
+                ui.selectable_value(
+                    &mut self.selected_tool,
+                    Tool::SpawnGalaxy,
+                    format!("{}", Tool::SpawnGalaxy),
+                );
+
+                ui.selectable_value(
+                    &mut self.selected_tool,
+                    Tool::SpawnCluster,
+                    format!("{}", Tool::SpawnCluster),
+                );
+
+                ui.selectable_value(
+                    &mut self.selected_tool,
+                    Tool::SpawnLattice,
+                    format!("{}", Tool::SpawnLattice),
                 )
             });
 
@@ -74,12 +261,44 @@ impl ToolState {
 
     /// spawn particle using the config
     fn spawn_particle(&self, commands: &mut Commands) {
-        ParticleBundle::new()
+        let mut particle = ParticleBundle::new()
             .radius(self.radius)
             .mass(self.mass)
             .position(self.position)
-            .velocity(self.velocity)
-            .spawn(commands);
+            .velocity(if self.pinned {
+                Vec2::ZERO
+            } else {
+                self.velocity
+            })
+            .species(self.species)
+            .fixed(self.pinned);
+
+        if self.pinned {
+            match self.path_kind {
+                PathKind::Still => (),
+                PathKind::Circular => {
+                    particle = particle.kinematic_path(KinematicPath::Circular {
+                        center: self.path_center,
+                        angular_speed: self.path_angular_speed,
+                    })
+                }
+                PathKind::Linear => {
+                    particle = particle.kinematic_path(KinematicPath::Linear {
+                        velocity: self.path_velocity,
+                    })
+                }
+            }
+        }
+
+        if self.gas {
+            particle = particle.gas(self.internal_energy);
+        }
+
+        if self.sink {
+            particle = particle.sink(self.accretion_radius);
+        }
+
+        particle.spawn(commands);
     }
 
     /// spawn random particles using the config in the state
@@ -88,17 +307,325 @@ impl ToolState {
             .position(self.position)
             .radius(self.radius)
             .mass(self.mass)
+            .mass_distribution(self.mass_distribution)
+            .mass_range(self.min_mass, self.max_mass)
+            .mass_exponent(self.mass_exponent)
+            .size_distribution(self.size_distribution)
+            .radius_range(self.min_radius, self.max_radius)
+            .density(self.particle_density)
             .velocity(self.max_random_velocity)
+            .velocity_model(self.velocity_model)
+            .temperature(self.temperature)
+            .angular_velocity(self.angular_velocity)
+            .central_mass(self.central_mass)
+            .expansion_rate(self.expansion_rate)
+            .clockwise(self.clockwise)
+            .virial_ratio(self.virial_scaling.then_some(self.virial_ratio))
+            .shape(self.spawn_shape)
+            .size(self.spawn_size)
+            .polygon(
+                self.polygon_points
+                    .iter()
+                    .map(|point| *point - self.position)
+                    .collect(),
+            )
+            .spiral_arms(self.spiral_arms)
+            .pitch_angle(self.pitch_angle.to_radians())
+            .arm_width(self.arm_width)
+            .ring_width(self.ring_width)
+            .image_mask(self.image_mask.clone())
+            .color_from_image(self.color_from_image)
             .inner_radius(self.inner_radius)
             .outer_radius(self.outer_radius)
             .amount(self.amount)
+            .species(self.species_mix)
+            .gas(self.gas.then_some(self.internal_energy))
+            .test_particles(self.test_particles)
+            .gravitational_constant(self.gravitational_constant())
+            .spawn(commands);
+    }
+
+    /// spawn a particle at `position` on an orbit around the chosen primary, moving along with it.
+    /// `dt` is the physics timestep, velocities are stored as distance per step
+    fn spawn_in_orbit(
+        &self,
+        commands: &mut Commands,
+        primaries: &Query<(&Transform, &OldPosition, &Mass), With<Particle>>,
+        position: Vec2,
+        dt: f32,
+    ) {
+        let Some(Ok((primary, primary_old, Mass(primary_mass)))) =
+            self.orbit_primary.map(|entity| primaries.get(entity))
+        else {
+            return;
+        };
+
+        let primary_position = primary.translation.truncate();
+        let primary_step = primary_position - primary_old.0.translation.truncate();
+        let satellite_mass = if self.test_particles { 0.0 } else { self.mass };
+        let velocity = orbital_velocity(
+            position - primary_position,
+            (primary_mass + satellite_mass) * self.gravitational_constant(),
+            self.eccentricity,
+            self.retrograde,
+        );
+
+        ParticleBundle::new()
+            .radius(self.radius)
+            .mass(self.mass)
+            .position(position)
+            .velocity(primary_step + velocity * dt)
+            .species(self.species)
+            .test_particle(self.test_particles)
+            .spawn(commands);
+    }
+
+    /// spawn a galaxy using the config, moving at `velocity` units per second
+    fn spawn_galaxy(&self, commands: &mut Commands, velocity: Vec2) {
+        SpawnGalaxyDisk::new()
+            .amount(self.amount)
+            .position(self.position)
+            .velocity(velocity)
+            .profile(self.disk_profile)
+            .disk_mass(self.disk_mass)
+            .scale_length(self.scale_length)
+            .outer_radius(self.outer_radius)
+            .bulge_mass(self.bulge_mass)
+            .bulge_radius(self.bulge_radius)
+            .nucleus_mass(self.nucleus_mass)
+            .toomre_q(self.toomre_q)
+            .clockwise(self.clockwise)
+            .radius(self.radius)
+            .test_particles(self.test_particles)
+            .gravitational_constant(self.gravitational_constant())
+            .spawn(commands);
+    }
+
+    /// spawn a star cluster using the config, moving at `velocity` units per second
+    fn spawn_cluster(&self, commands: &mut Commands, velocity: Vec2) {
+        SpawnStarCluster::new(self.cluster_model)
+            .king_depth(self.king_depth)
+            .amount(self.amount)
+            .position(self.position)
+            .velocity(velocity)
+            .total_mass(self.cluster_mass)
+            .virial_radius(self.virial_radius)
+            .radius(self.radius)
+            .gravitational_constant(self.gravitational_constant())
+            .spawn(commands);
+    }
+
+    /// spawn a lattice using the config, moving at `velocity` units per second. `growth_rate` is
+    /// how fast zel'dovich displacements grow in the current cosmology
+    fn spawn_lattice(&self, commands: &mut Commands, velocity: Vec2, growth_rate: f32) {
+        SpawnLattice::new(self.lattice_kind)
+            .columns(self.columns)
+            .rows(self.rows)
+            .spacing(self.spacing)
+            .jitter(self.jitter)
+            .position(self.position)
+            .velocity(velocity)
+            .mass(self.mass)
+            .radius(self.radius)
+            .species(self.species)
+            .test_particles(self.test_particles)
+            .central_mass(self.central_mass)
+            .ring_rotation(self.ring_rotation)
+            .angular_velocity(self.angular_velocity)
+            .spectral_index(self.spectral_index)
+            .displacement(self.displacement)
+            .growth_rate(growth_rate)
+            .gravitational_constant(self.gravitational_constant())
+            .spawn(commands);
+    }
+
+    fn gravitational_constant(&self) -> f32 {
+        self.units.gravitational_constant() as f32
+    }
+
+    /// gizmo preview for the lattice tool
+    fn preview_lattice(&self, gizmos: &mut Gizmos, center: Vec2) {
+        match self.lattice_kind {
+            LatticeKind::Square | LatticeKind::Hexagonal => {
+                let row_spacing = if self.lattice_kind == LatticeKind::Hexagonal {
+                    self.spacing * 3.0_f32.sqrt() / 2.0
+                } else {
+                    self.spacing
+                };
+                let size = Vec2::new(
+                    self.columns.saturating_sub(1) as f32 * self.spacing,
+                    self.rows.saturating_sub(1) as f32 * row_spacing,
+                );
+                gizmos.rect_2d(center, size, Color::WHITE);
+            }
+            LatticeKind::Rings => {
+                gizmos.circle_2d(center, self.spacing, Color::WHITE);
+                gizmos.circle_2d(center, self.rows as f32 * self.spacing, Color::WHITE);
+            }
+            LatticeKind::Zeldovich => {
+                let side = self.columns.next_power_of_two().max(2) - 1;
+                gizmos.rect_2d(
+                    center,
+                    Vec2::splat(side as f32 * self.spacing),
+                    Color::WHITE,
+                );
+            }
+        }
+    }
+
+    /// gizmo preview for the galaxy tool
+    fn preview_galaxy(&self, gizmos: &mut Gizmos, center: Vec2) {
+        gizmos.circle_2d(center, self.outer_radius, Color::WHITE);
+        if self.disk_profile == DiskProfile::Exponential {
+            gizmos.circle_2d(center, self.scale_length, Color::WHITE);
+        }
+        if self.bulge_mass > 0.0 {
+            gizmos.circle_2d(center, self.bulge_radius, Color::WHITE);
+        }
+    }
+
+    /// gizmo preview of the orbit a particle placed at `position` would follow
+    fn preview_orbit(
+        &self,
+        gizmos: &mut Gizmos,
+        primaries: &Query<(&Transform, &OldPosition, &Mass), With<Particle>>,
+        position: Vec2,
+    ) {
+        let Some(Ok((primary, _, _))) = self.orbit_primary.map(|entity| primaries.get(entity))
+        else {
+            return;
+        };
+
+        // the primary sits at a focus and the placed particle starts at periapsis
+        let focus = primary.translation.truncate();
+        let offset = position - focus;
+        let periapsis = offset.length();
+        let semi_major_axis = periapsis / (1.0 - self.eccentricity);
+        let semi_minor_axis = semi_major_axis * (1.0 - self.eccentricity.powi(2)).sqrt();
+        let direction = offset.normalize_or(Vec2::X);
+        let center = focus - direction * semi_major_axis * self.eccentricity;
+
+        gizmos.line_2d(focus, position, Color::WHITE);
+        gizmos.ellipse_2d(
+            Isometry2d::new(center, Rot2::radians(direction.to_angle())),
+            Vec2::new(semi_major_axis, semi_minor_axis),
+            Color::WHITE,
+        );
+        gizmos.circle_2d(position, self.radius, Color::WHITE);
+    }
+
+    /// bond the particle the drag started on to `end`, the current distance becomes the rest length
+    fn connect_particles(
+        &self,
+        commands: &mut Commands,
+        particles: &Query<(Entity, &Transform, &Radius), With<Particle>>,
+        end: Entity,
+    ) {
+        let Some(start) = self.bond_start else {
+            return;
+        };
+
+        if start == end {
+            return;
+        }
+
+        if let (Ok((_, start_pos, _)), Ok((_, end_pos, _))) =
+            (particles.get(start), particles.get(end))
+        {
+            let rest_length = start_pos.translation.distance(end_pos.translation);
+            let breaking_strain = (self.breaking_strain > 0.0).then_some(self.breaking_strain);
+            Bond::new(start, end, rest_length)
+                .stiffness(self.stiffness)
+                .damping(self.damping)
+                .breaking_strain(breaking_strain)
+                .spawn(commands);
+        }
+    }
+
+    /// the collider shape dragged out from the click position to `cursor_coords`
+    fn dragged_collider_shape(&self, cursor_coords: Vec2) -> ColliderShape {
+        match self.collider_kind {
+            ColliderKind::Segment => ColliderShape::Segment {
+                start: self.position,
+                end: cursor_coords,
+            },
+            ColliderKind::Rectangle => ColliderShape::Rectangle {
+                center: (self.position + cursor_coords) / 2.0,
+                half_size: (cursor_coords - self.position).abs() / 2.0,
+            },
+            ColliderKind::Circle => ColliderShape::Circle {
+                center: self.position,
+                radius: self.position.distance(cursor_coords),
+            },
+            ColliderKind::Polygon => ColliderShape::Polygon {
+                points: self.polygon_points.clone(),
+            },
+        }
+    }
+
+    fn spawn_collider(&self, commands: &mut Commands, shape: ColliderShape) {
+        Collider::new(shape)
+            .restitution(self.restitution)
             .spawn(commands);
     }
 
     /// gizmo preview for random particles tool
     fn preview_random_particles(&self, gizmos: &mut Gizmos, cursor_coords: Vec2) {
-        gizmos.circle_2d(cursor_coords, self.inner_radius, Color::WHITE);
-        gizmos.circle_2d(cursor_coords, self.outer_radius, Color::WHITE);
+        match self.spawn_shape {
+            SpawnShape::Circle => {
+                gizmos.circle_2d(cursor_coords, self.inner_radius, Color::WHITE);
+                gizmos.circle_2d(cursor_coords, self.outer_radius, Color::WHITE);
+            }
+            SpawnShape::Rectangle => {
+                gizmos.rect_2d(cursor_coords, self.spawn_size, Color::WHITE);
+            }
+            SpawnShape::Ellipse => {
+                gizmos.ellipse_2d(cursor_coords, self.spawn_size / 2.0, Color::WHITE);
+            }
+            SpawnShape::Polygon => {
+                let points = self.polygon_points.iter().copied();
+                gizmos.linestrip_2d(points.chain([cursor_coords]), Color::WHITE);
+            }
+            SpawnShape::Spiral => {
+                // the middle of each arm, the same curve the spawner spreads particles around
+                let start = self
+                    .inner_radius
+                    .max(self.outer_radius * 0.05)
+                    .max(f32::EPSILON);
+                let winding = self.pitch_angle.to_radians().tan();
+                for arm in 0..self.spiral_arms.max(1) {
+                    let arm_angle =
+                        arm as f32 / self.spiral_arms.max(1) as f32 * std::f32::consts::TAU;
+                    let points = (0..=64).map(|i| {
+                        let distance =
+                            start + (self.outer_radius - start).max(0.0) * i as f32 / 64.0;
+                        let angle = arm_angle + (distance / start).ln() / winding;
+                        cursor_coords + Vec2::from_angle(angle) * distance
+                    });
+                    gizmos.linestrip_2d(points, Color::WHITE);
+                }
+            }
+            SpawnShape::Ring => {
+                gizmos.circle_2d(cursor_coords, self.outer_radius, Color::WHITE);
+                gizmos.circle_2d(
+                    cursor_coords,
+                    (self.outer_radius - self.ring_width).max(0.0),
+                    Color::WHITE,
+                );
+                gizmos.circle_2d(
+                    cursor_coords,
+                    self.outer_radius + self.ring_width,
+                    Color::WHITE,
+                );
+            }
+            SpawnShape::Image => match &self.image_mask {
+                Some(mask) => {
+                    let size = mask.fitted_size(self.spawn_size);
+                    gizmos.rect_2d(cursor_coords, size, Color::WHITE);
+                }
+                None => gizmos.cross_2d(cursor_coords, 2.0, Color::WHITE),
+            },
+        }
     }
 
     // functions for reusable ui widgets
@@ -107,7 +634,7 @@ impl ToolState {
             ui,
             &mut self.mass,
             1.0,
-            "mass",
+            &self.units.label("mass", Quantity::Mass),
             "set the mass of the spawned particle",
         );
     }
@@ -117,18 +644,248 @@ impl ToolState {
             ui,
             &mut self.radius,
             1.0,
-            "radius",
+            &self.units.label("radius", Quantity::Length),
             "set the radius of the spawned particle",
         );
         self.radius = self.radius.max(0.0);
     }
 
+    fn spawn_shape_ui(&mut self, ui: &mut egui::Ui) {
+        ui.label("shape");
+        egui::ComboBox::from_id_salt("spawn_shape")
+            .selected_text(format!("{}", self.spawn_shape))
+            .show_ui(ui, |ui| {
+                for shape in [
+                    SpawnShape::Circle,
+                    SpawnShape::Rectangle,
+                    SpawnShape::Ellipse,
+                    SpawnShape::Polygon,
+                    SpawnShape::Spiral,
+                    SpawnShape::Ring,
+                    SpawnShape::Image,
+                ] {
+                    ui.selectable_value(&mut self.spawn_shape, shape, format!("{shape}"));
+                }
+            })
+            .response
+            .on_hover_text_at_pointer("polygons are clicked point by point then filled with enter");
+        ui.end_row();
+
+        match self.spawn_shape {
+            SpawnShape::Circle => {
+                self.inner_radius_ui(ui);
+                self.outer_radius_ui(ui);
+            }
+            SpawnShape::Rectangle | SpawnShape::Ellipse => self.spawn_size_ui(ui),
+            SpawnShape::Polygon => {}
+            SpawnShape::Spiral => {
+                self.inner_radius_ui(ui);
+                self.outer_radius_ui(ui);
+
+                ui.label("arms");
+                ui.add(
+                    egui::DragValue::new(&mut self.spiral_arms)
+                        .speed(0.05)
+                        .range(1..=12),
+                );
+                ui.end_row();
+
+                ui.label("pitch angle");
+                ui.add(
+                    egui::DragValue::new(&mut self.pitch_angle)
+                        .speed(0.1)
+                        .range(1.0..=89.0)
+                        .suffix("°"),
+                )
+                .on_hover_text_at_pointer(
+                    "angle between the arms and a circle, smaller winds tighter",
+                );
+                ui.end_row();
+
+                value_editor_row(
+                    ui,
+                    &mut self.arm_width,
+                    0.1,
+                    &self.units.label("arm width", Quantity::Length),
+                    "how far particles spread either side of an arm",
+                );
+                self.arm_width = self.arm_width.max(0.0);
+            }
+            SpawnShape::Ring => {
+                self.outer_radius_ui(ui);
+                value_editor_row(
+                    ui,
+                    &mut self.ring_width,
+                    0.1,
+                    &self.units.label("ring width", Quantity::Length),
+                    "how far particles spread either side of the ring",
+                );
+                self.ring_width = self.ring_width.max(0.0);
+            }
+            SpawnShape::Image => self.image_ui(ui),
+        }
+    }
+
+    fn spawn_size_ui(&mut self, ui: &mut egui::Ui) {
+        ui.label("size");
+        ui.horizontal(|ui| {
+            ui.add(egui::DragValue::new(&mut self.spawn_size.x).speed(1.0));
+            ui.add(egui::DragValue::new(&mut self.spawn_size.y).speed(1.0));
+        })
+        .response
+        .on_hover_text_at_pointer("width and height of the region");
+        self.spawn_size = self.spawn_size.max(Vec2::ZERO);
+        ui.end_row();
+    }
+
+    fn image_ui(&mut self, ui: &mut egui::Ui) {
+        ui.label("png");
+        ui.text_edit_singleline(&mut self.image_path)
+            .on_hover_text_at_pointer("path to a png, brighter pixels get more particles");
+        if ui.button("load").clicked() {
+            match ImageMask::load(&self.image_path) {
+                Ok(mask) => {
+                    self.image_mask = Some(Arc::new(mask));
+                    self.image_error = None;
+                }
+                Err(error) => self.image_error = Some(error),
+            }
+        }
+        ui.end_row();
+
+        if let Some(error) = &self.image_error {
+            ui.label("");
+            ui.colored_label(egui::Color32::LIGHT_RED, error);
+            ui.end_row();
+        }
+
+        self.spawn_size_ui(ui);
+
+        ui.label("pixel colours");
+        ui.checkbox(&mut self.color_from_image, "")
+            .on_hover_text_at_pointer("colour each particle like the pixel it came from");
+        ui.end_row();
+    }
+
+    fn mass_distribution_ui(&mut self, ui: &mut egui::Ui) {
+        ui.label("mass distribution");
+        egui::ComboBox::from_id_salt("mass_distribution")
+            .selected_text(format!("{}", self.mass_distribution))
+            .show_ui(ui, |ui| {
+                for distribution in [
+                    MassDistribution::Fixed,
+                    MassDistribution::Uniform,
+                    MassDistribution::LogUniform,
+                    MassDistribution::Salpeter,
+                    MassDistribution::Kroupa,
+                    MassDistribution::PowerLaw,
+                ] {
+                    ui.selectable_value(
+                        &mut self.mass_distribution,
+                        distribution,
+                        format!("{distribution}"),
+                    );
+                }
+            })
+            .response
+            .on_hover_text_at_pointer(
+                "kroupa breaks at 0.08 and 0.5, as if mass is in solar masses",
+            );
+        ui.end_row();
+
+        if self.mass_distribution == MassDistribution::Fixed {
+            self.mass_ui(ui);
+            return;
+        }
+
+        value_editor_row(
+            ui,
+            &mut self.min_mass,
+            0.01,
+            &self.units.label("min mass", Quantity::Mass),
+            "mass of the lightest particles",
+        );
+        self.min_mass = self.min_mass.max(0.001);
+
+        value_editor_row(
+            ui,
+            &mut self.max_mass,
+            1.0,
+            &self.units.label("max mass", Quantity::Mass),
+            "mass of the heaviest particles",
+        );
+        self.max_mass = self.max_mass.max(self.min_mass);
+
+        if self.mass_distribution == MassDistribution::PowerLaw {
+            value_editor_row(
+                ui,
+                &mut self.mass_exponent,
+                0.01,
+                "exponent",
+                "how fast the number of particles falls with mass, dN/dm ~ m^-exponent",
+            );
+        }
+    }
+
+    fn size_distribution_ui(&mut self, ui: &mut egui::Ui) {
+        ui.label("size distribution");
+        egui::ComboBox::from_id_salt("size_distribution")
+            .selected_text(format!("{}", self.size_distribution))
+            .show_ui(ui, |ui| {
+                for distribution in [
+                    SizeDistribution::Fixed,
+                    SizeDistribution::Uniform,
+                    SizeDistribution::FromDensity,
+                ] {
+                    ui.selectable_value(
+                        &mut self.size_distribution,
+                        distribution,
+                        format!("{distribution}"),
+                    );
+                }
+            });
+        ui.end_row();
+
+        match self.size_distribution {
+            SizeDistribution::Fixed => self.radius_ui(ui),
+            SizeDistribution::Uniform => {
+                value_editor_row(
+                    ui,
+                    &mut self.min_radius,
+                    0.1,
+                    &self.units.label("min radius", Quantity::Length),
+                    "radius of the smallest particles",
+                );
+                self.min_radius = self.min_radius.max(0.0);
+
+                value_editor_row(
+                    ui,
+                    &mut self.max_radius,
+                    0.1,
+                    &self.units.label("max radius", Quantity::Length),
+                    "radius of the biggest particles",
+                );
+                self.max_radius = self.max_radius.max(self.min_radius);
+            }
+            SizeDistribution::FromDensity => {
+                value_editor_row(
+                    ui,
+                    &mut self.particle_density,
+                    0.01,
+                    "density",
+                    "mass per unit area, heavier particles come out bigger",
+                );
+                self.particle_density = self.particle_density.max(0.001);
+            }
+        }
+    }
+
     fn inner_radius_ui(&mut self, ui: &mut egui::Ui) {
         value_editor_row(
             ui,
             &mut self.inner_radius,
             1.0,
-            "inner radius",
+            &self.units.label("inner radius", Quantity::Length),
             "inner radius of random particle spawning",
         );
         self.inner_radius = self.inner_radius.max(0.0);
@@ -139,7 +896,7 @@ impl ToolState {
             ui,
             &mut self.outer_radius,
             1.0,
-            "outer radius",
+            &self.units.label("outer radius", Quantity::Length),
             "outer radius of random particle spawning",
         );
         self.outer_radius = self.outer_radius.max(self.inner_radius)
@@ -156,6 +913,97 @@ impl ToolState {
         self.max_random_velocity = self.max_random_velocity.max(0.0);
     }
 
+    fn velocity_model_ui(&mut self, ui: &mut egui::Ui) {
+        ui.label("velocity model");
+        egui::ComboBox::from_id_salt("velocity_model")
+            .selected_text(format!("{}", self.velocity_model))
+            .show_ui(ui, |ui| {
+                for model in [
+                    VelocityModel::Random,
+                    VelocityModel::MaxwellBoltzmann,
+                    VelocityModel::SolidBody,
+                    VelocityModel::Keplerian,
+                    VelocityModel::Radial,
+                ] {
+                    ui.selectable_value(&mut self.velocity_model, model, format!("{model}"));
+                }
+            });
+        ui.end_row();
+
+        match self.velocity_model {
+            VelocityModel::Random => self.max_random_velocity_ui(ui),
+            VelocityModel::MaxwellBoltzmann => {
+                value_editor_row(
+                    ui,
+                    &mut self.temperature,
+                    0.1,
+                    "temperature",
+                    "how fast the particles jiggle, lighter particles move faster",
+                );
+                self.temperature = self.temperature.max(0.0);
+            }
+            VelocityModel::SolidBody => {
+                value_editor_row(
+                    ui,
+                    &mut self.angular_velocity,
+                    0.001,
+                    "angular velocity",
+                    "radians per second the particles turn around the center",
+                );
+                self.angular_velocity = self.angular_velocity.max(0.0);
+            }
+            VelocityModel::Keplerian => {
+                value_editor_row(
+                    ui,
+                    &mut self.central_mass,
+                    1.0,
+                    &self.units.label("central mass", Quantity::Mass),
+                    "mass the particles orbit, on top of the spawned mass inside each orbit",
+                );
+                self.central_mass = self.central_mass.max(0.0);
+            }
+            VelocityModel::Radial => {
+                value_editor_row(
+                    ui,
+                    &mut self.expansion_rate,
+                    0.001,
+                    "expansion rate",
+                    "speed per unit distance from the center, negative falls inwards",
+                );
+            }
+        }
+
+        if matches!(
+            self.velocity_model,
+            VelocityModel::SolidBody | VelocityModel::Keplerian
+        ) {
+            ui.label("clockwise");
+            ui.checkbox(&mut self.clockwise, "")
+                .on_hover_text_at_pointer("turn clockwise instead of anticlockwise");
+            ui.end_row();
+        }
+
+        ui.label("virial scaling");
+        ui.checkbox(&mut self.virial_scaling, "")
+            .on_hover_text_at_pointer(
+                "scale the velocities to a set ratio of kinetic to potential energy",
+            );
+        ui.end_row();
+
+        if self.virial_scaling {
+            ui.label("virial ratio");
+            ui.add(
+                egui::DragValue::new(&mut self.virial_ratio)
+                    .speed(0.01)
+                    .range(0.0..=10.0),
+            )
+            .on_hover_text_at_pointer(
+                "2K / |W|, 1 is in equilibrium, below collapses, above flies apart",
+            );
+            ui.end_row();
+        }
+    }
+
     fn amount_ui(&mut self, ui: &mut egui::Ui) {
         let mut amount_f32 = self.amount as f32;
         value_editor_row(
@@ -167,6 +1015,514 @@ impl ToolState {
         );
         self.amount = amount_f32 as u32;
     }
+
+    fn collider_kind_ui(&mut self, ui: &mut egui::Ui) {
+        ui.label("shape");
+        egui::ComboBox::from_id_salt("collider_kind")
+            .selected_text(format!("{}", self.collider_kind))
+            .show_ui(ui, |ui| {
+                for kind in [
+                    ColliderKind::Segment,
+                    ColliderKind::Rectangle,
+                    ColliderKind::Circle,
+                    ColliderKind::Polygon,
+                ] {
+                    ui.selectable_value(&mut self.collider_kind, kind, format!("{kind}"));
+                }
+            })
+            .response
+            .on_hover_text_at_pointer(
+                "drag to draw, polygons are clicked point by point then finished with enter",
+            );
+        ui.end_row();
+    }
+
+    fn restitution_ui(&mut self, ui: &mut egui::Ui) {
+        ui.label("restitution");
+        ui.add(
+            egui::DragValue::new(&mut self.restitution)
+                .speed(0.01)
+                .range(0.0..=1.0),
+        )
+        .on_hover_text_at_pointer("how bouncy the wall is");
+        ui.end_row();
+    }
+
+    fn container_ui(&mut self, ui: &mut egui::Ui) {
+        ui.label("container");
+        egui::ComboBox::from_id_salt("container_preset")
+            .selected_text(format!("{}", self.container_preset))
+            .show_ui(ui, |ui| {
+                for preset in [
+                    ContainerPreset::OpenBox,
+                    ContainerPreset::ClosedBox,
+                    ContainerPreset::Hourglass,
+                ] {
+                    ui.selectable_value(&mut self.container_preset, preset, format!("{preset}"));
+                }
+            });
+        ui.end_row();
+
+        value_editor_row(
+            ui,
+            &mut self.container_size,
+            1.0,
+            &self.units.label("container size", Quantity::Length),
+            "height of the container, spawned at the origin",
+        );
+        self.container_size = self.container_size.max(1.0);
+
+        if ui.button("spawn container").clicked() {
+            self.should_spawn_container = true;
+        }
+        if ui.button("clear colliders").clicked() {
+            self.should_clear_colliders = true;
+        }
+        ui.end_row();
+    }
+
+    fn orbit_ui(&mut self, ui: &mut egui::Ui) {
+        ui.label("eccentricity");
+        ui.add(
+            egui::DragValue::new(&mut self.eccentricity)
+                .speed(0.01)
+                .range(0.0..=0.95),
+        )
+        .on_hover_text_at_pointer(
+            "0 is a circular orbit, the particle is placed at the closest point of the orbit",
+        );
+        ui.end_row();
+
+        ui.label("retrograde");
+        ui.checkbox(&mut self.retrograde, "")
+            .on_hover_text_at_pointer("orbit clockwise instead of anticlockwise");
+        ui.end_row();
+
+        ui.label("primary");
+        ui.label(if self.orbit_primary.is_some() {
+            "click a particle to change it"
+        } else {
+            "click a particle to pick it"
+        });
+        ui.end_row();
+    }
+
+    fn galaxy_ui(&mut self, ui: &mut egui::Ui) {
+        ui.label("profile");
+        egui::ComboBox::from_id_salt("disk_profile")
+            .selected_text(format!("{}", self.disk_profile))
+            .show_ui(ui, |ui| {
+                for profile in [DiskProfile::Exponential, DiskProfile::Mestel] {
+                    ui.selectable_value(&mut self.disk_profile, profile, format!("{profile}"));
+                }
+            });
+        ui.end_row();
+
+        value_editor_row(
+            ui,
+            &mut self.disk_mass,
+            1.0,
+            &self.units.label("disk mass", Quantity::Mass),
+            "total mass of the disk particles",
+        );
+        self.disk_mass = self.disk_mass.max(0.0);
+
+        if self.disk_profile == DiskProfile::Exponential {
+            value_editor_row(
+                ui,
+                &mut self.scale_length,
+                0.1,
+                &self.units.label("scale length", Quantity::Length),
+                "distance over which the disk gets e times less dense",
+            );
+            self.scale_length = self.scale_length.max(0.1);
+        }
+
+        value_editor_row(
+            ui,
+            &mut self.bulge_mass,
+            1.0,
+            &self.units.label("bulge mass", Quantity::Mass),
+            "mass of the round central bulge, 0 for none",
+        );
+        self.bulge_mass = self.bulge_mass.max(0.0);
+
+        if self.bulge_mass > 0.0 {
+            value_editor_row(
+                ui,
+                &mut self.bulge_radius,
+                0.1,
+                &self.units.label("bulge radius", Quantity::Length),
+                "plummer scale radius of the bulge",
+            );
+            self.bulge_radius = self.bulge_radius.max(0.1);
+        }
+
+        value_editor_row(
+            ui,
+            &mut self.nucleus_mass,
+            1.0,
+            &self.units.label("nucleus mass", Quantity::Mass),
+            "mass of a single particle in the middle, 0 for none",
+        );
+        self.nucleus_mass = self.nucleus_mass.max(0.0);
+
+        ui.label("toomre Q");
+        ui.add(
+            egui::DragValue::new(&mut self.toomre_q)
+                .speed(0.01)
+                .range(0.0..=10.0),
+        )
+        .on_hover_text_at_pointer(
+            "how much random motion the disk has, below 1 it clumps up, higher is hotter and more stable",
+        );
+        ui.end_row();
+
+        ui.label("clockwise");
+        ui.checkbox(&mut self.clockwise, "")
+            .on_hover_text_at_pointer(
+                "spin clockwise instead of anticlockwise, drag when placing to give the galaxy a velocity",
+            );
+        ui.end_row();
+    }
+
+    fn cluster_ui(&mut self, ui: &mut egui::Ui) {
+        ui.label("model");
+        egui::ComboBox::from_id_salt("cluster_model")
+            .selected_text(format!("{}", self.cluster_model))
+            .show_ui(ui, |ui| {
+                for model in [ClusterModel::Plummer, ClusterModel::King] {
+                    ui.selectable_value(&mut self.cluster_model, model, format!("{model}"));
+                }
+            });
+        ui.end_row();
+
+        if self.cluster_model == ClusterModel::King {
+            ui.label("central potential");
+            ui.add(
+                egui::DragValue::new(&mut self.king_depth)
+                    .speed(0.05)
+                    .range(0.5..=15.0),
+            )
+            .on_hover_text_at_pointer(
+                "king W0, how deep the potential is in the middle, higher is more concentrated",
+            );
+            ui.end_row();
+        }
+
+        value_editor_row(
+            ui,
+            &mut self.cluster_mass,
+            1.0,
+            &self.units.label("total mass", Quantity::Mass),
+            "mass of the whole cluster, shared between the particles",
+        );
+        self.cluster_mass = self.cluster_mass.max(0.0);
+
+        value_editor_row(
+            ui,
+            &mut self.virial_radius,
+            0.1,
+            &self.units.label("virial radius", Quantity::Length),
+            "size of the cluster, drag when placing to give the cluster a velocity",
+        );
+        self.virial_radius = self.virial_radius.max(0.1);
+    }
+
+    fn lattice_ui(&mut self, ui: &mut egui::Ui) {
+        ui.label("lattice");
+        egui::ComboBox::from_id_salt("lattice_kind")
+            .selected_text(format!("{}", self.lattice_kind))
+            .show_ui(ui, |ui| {
+                for kind in [
+                    LatticeKind::Square,
+                    LatticeKind::Hexagonal,
+                    LatticeKind::Rings,
+                    LatticeKind::Zeldovich,
+                ] {
+                    ui.selectable_value(&mut self.lattice_kind, kind, format!("{kind}"));
+                }
+            });
+        ui.end_row();
+
+        match self.lattice_kind {
+            LatticeKind::Square | LatticeKind::Hexagonal => {
+                ui.label("columns");
+                ui.add(
+                    egui::DragValue::new(&mut self.columns)
+                        .speed(0.2)
+                        .range(1..=1000),
+                );
+                ui.end_row();
+
+                ui.label("rows");
+                ui.add(
+                    egui::DragValue::new(&mut self.rows)
+                        .speed(0.2)
+                        .range(1..=1000),
+                );
+                ui.end_row();
+            }
+            LatticeKind::Rings => {
+                ui.label("rings");
+                ui.add(
+                    egui::DragValue::new(&mut self.rows)
+                        .speed(0.1)
+                        .range(1..=200),
+                );
+                ui.end_row();
+            }
+            LatticeKind::Zeldovich => {
+                ui.label("grid size");
+                egui::ComboBox::from_id_salt("zeldovich_size")
+                    .selected_text(format!("{}", self.columns.next_power_of_two()))
+                    .show_ui(ui, |ui| {
+                        for size in [16, 32, 64, 128, 256] {
+                            ui.selectable_value(&mut self.columns, size, format!("{size}"));
+                        }
+                    })
+                    .response
+                    .on_hover_text_at_pointer(
+                        "particles along each side, a power of two for the fft",
+                    );
+                ui.end_row();
+            }
+        }
+
+        value_editor_row(
+            ui,
+            &mut self.spacing,
+            0.1,
+            &self.units.label("spacing", Quantity::Length),
+            "distance between neighbouring particles",
+        );
+        self.spacing = self.spacing.max(0.01);
+
+        value_editor_row(
+            ui,
+            &mut self.jitter,
+            0.01,
+            &self.units.label("jitter", Quantity::Length),
+            "move each particle randomly up to this far from its place, breaks the symmetry",
+        );
+        self.jitter = self.jitter.max(0.0);
+
+        match self.lattice_kind {
+            LatticeKind::Rings => {
+                value_editor_row(
+                    ui,
+                    &mut self.central_mass,
+                    1.0,
+                    &self.units.label("central mass", Quantity::Mass),
+                    "mass of a particle in the middle, 0 for none",
+                );
+                self.central_mass = self.central_mass.max(0.0);
+
+                ui.label("rotation");
+                egui::ComboBox::from_id_salt("ring_rotation")
+                    .selected_text(format!("{}", self.ring_rotation))
+                    .show_ui(ui, |ui| {
+                        for rotation in [
+                            RingRotation::Still,
+                            RingRotation::Keplerian,
+                            RingRotation::Rigid,
+                        ] {
+                            ui.selectable_value(
+                                &mut self.ring_rotation,
+                                rotation,
+                                format!("{rotation}"),
+                            );
+                        }
+                    })
+                    .response
+                    .on_hover_text_at_pointer(
+                        "keplerian orbits the mass inside each ring, rigid turns like a wheel",
+                    );
+                ui.end_row();
+
+                if self.ring_rotation == RingRotation::Rigid {
+                    value_editor_row(
+                        ui,
+                        &mut self.angular_velocity,
+                        0.001,
+                        "angular velocity",
+                        "radians per second, negative turns clockwise",
+                    );
+                }
+            }
+            LatticeKind::Zeldovich => {
+                value_editor_row(
+                    ui,
+                    &mut self.spectral_index,
+                    0.01,
+                    "spectral index",
+                    "slope n of the power spectrum k^n, lower puts more power on large scales",
+                );
+
+                value_editor_row(
+                    ui,
+                    &mut self.displacement,
+                    0.01,
+                    "displacement",
+                    "root mean square displacement in spacings, velocities follow the cosmology settings",
+                );
+                self.displacement = self.displacement.max(0.0);
+            }
+            _ => {}
+        }
+    }
+
+    fn stiffness_ui(&mut self, ui: &mut egui::Ui) {
+        value_editor_row(
+            ui,
+            &mut self.stiffness,
+            0.1,
+            "stiffness",
+            "how hard the bond pulls back to its rest length",
+        );
+        self.stiffness = self.stiffness.max(0.0);
+    }
+
+    fn damping_ui(&mut self, ui: &mut egui::Ui) {
+        value_editor_row(
+            ui,
+            &mut self.damping,
+            0.01,
+            "damping",
+            "how much the bond resists stretching and squashing",
+        );
+        self.damping = self.damping.max(0.0);
+    }
+
+    fn breaking_strain_ui(&mut self, ui: &mut egui::Ui) {
+        value_editor_row(
+            ui,
+            &mut self.breaking_strain,
+            0.01,
+            "breaking strain",
+            "fraction of the rest length the bond can stretch before breaking, 0 never breaks",
+        );
+        self.breaking_strain = self.breaking_strain.max(0.0);
+    }
+
+    fn pinned_ui(&mut self, ui: &mut egui::Ui) {
+        ui.label("pinned");
+        ui.checkbox(&mut self.pinned, "")
+            .on_hover_text_at_pointer("the particle pulls on others but forces never move it");
+        ui.end_row();
+
+        if self.pinned {
+            self.path_ui(ui);
+        }
+    }
+
+    fn path_ui(&mut self, ui: &mut egui::Ui) {
+        ui.label("path");
+        egui::ComboBox::from_id_salt("path_kind")
+            .selected_text(format!("{}", self.path_kind))
+            .show_ui(ui, |ui| {
+                for kind in [PathKind::Still, PathKind::Circular, PathKind::Linear] {
+                    ui.selectable_value(&mut self.path_kind, kind, format!("{kind}"));
+                }
+            })
+            .response
+            .on_hover_text_at_pointer(
+                "move the pinned particle along a set path, forces still ignored",
+            );
+        ui.end_row();
+
+        match self.path_kind {
+            PathKind::Still => (),
+            PathKind::Circular => {
+                ui.label(self.units.label("center", Quantity::Length));
+                ui.horizontal(|ui| {
+                    ui.add(egui::DragValue::new(&mut self.path_center.x).speed(1.0));
+                    ui.add(egui::DragValue::new(&mut self.path_center.y).speed(1.0));
+                })
+                .response
+                .on_hover_text_at_pointer("point to circle around, at the distance it is spawned");
+                ui.end_row();
+
+                value_editor_row(
+                    ui,
+                    &mut self.path_angular_speed,
+                    0.01,
+                    "angular speed",
+                    "radians per second, positive is anticlockwise",
+                );
+            }
+            PathKind::Linear => {
+                ui.label(self.units.label("path velocity", Quantity::Velocity));
+                ui.horizontal(|ui| {
+                    ui.add(egui::DragValue::new(&mut self.path_velocity.x).speed(0.1));
+                    ui.add(egui::DragValue::new(&mut self.path_velocity.y).speed(0.1));
+                })
+                .response
+                .on_hover_text_at_pointer("constant velocity to move at");
+                ui.end_row();
+            }
+        }
+    }
+
+    fn sink_ui(&mut self, ui: &mut egui::Ui) {
+        ui.label("sink");
+        ui.checkbox(&mut self.sink, "")
+            .on_hover_text_at_pointer("swallow particles that get too close, keeping their mass");
+        ui.end_row();
+
+        if self.sink {
+            value_editor_row(
+                ui,
+                &mut self.accretion_radius,
+                0.1,
+                &self.units.label("accretion radius", Quantity::Length),
+                "particles closer than this are swallowed",
+            );
+            self.accretion_radius = self.accretion_radius.max(0.0);
+        }
+    }
+
+    fn test_particles_ui(&mut self, ui: &mut egui::Ui) {
+        ui.label("test particles");
+        ui.checkbox(&mut self.test_particles, "")
+            .on_hover_text_at_pointer(
+                "massless tracers that feel gravity but do not pull on anything",
+            );
+        ui.end_row();
+    }
+
+    fn gas_ui(&mut self, ui: &mut egui::Ui) {
+        ui.label("gas");
+        ui.checkbox(&mut self.gas, "")
+            .on_hover_text_at_pointer("spawn particles as sph gas");
+        ui.end_row();
+
+        if self.gas {
+            value_editor_row(
+                ui,
+                &mut self.internal_energy,
+                0.1,
+                "internal energy",
+                "starting temperature of the spawned gas",
+            );
+            self.internal_energy = self.internal_energy.max(0.0);
+        }
+    }
+
+    fn species_ui(&mut self, ui: &mut egui::Ui) {
+        ui.label("species");
+        ui.add(egui::DragValue::new(&mut self.species).speed(0.05))
+            .on_hover_text_at_pointer("species of the spawned particle, used by particle life");
+        ui.end_row();
+    }
+
+    fn species_mix_ui(&mut self, ui: &mut egui::Ui) {
+        ui.label("species mix");
+        ui.add(egui::DragValue::new(&mut self.species_mix).speed(0.05))
+            .on_hover_text_at_pointer("amount of species to randomly mix, used by particle life");
+        self.species_mix = self.species_mix.max(1);
+        ui.end_row();
+    }
 }
 
 impl Display for Tool {
@@ -174,6 +1530,33 @@ impl Display for Tool {
         match self {
             Tool::SpawnParticle => write!(f, "spawn particle"),
             Tool::SpawnRandomParticles => write!(f, "spawn random particle"),
+            Tool::ConnectParticles => write!(f, "connect particles"),
+            Tool::DrawCollider => write!(f, "draw collider"),
+            Tool::SpawnInOrbit => write!(f, "spawn in orbit"),
+            Tool::SpawnGalaxy => write!(f, "spawn galaxy"),
+            Tool::SpawnCluster => write!(f, "spawn cluster"),
+            Tool::SpawnLattice => write!(f, "spawn lattice"),
+        }
+    }
+}
+
+impl Display for ColliderKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ColliderKind::Segment => write!(f, "segment"),
+            ColliderKind::Rectangle => write!(f, "rectangle"),
+            ColliderKind::Circle => write!(f, "circle"),
+            ColliderKind::Polygon => write!(f, "polygon"),
+        }
+    }
+}
+
+impl Display for PathKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PathKind::Still => write!(f, "still"),
+            PathKind::Circular => write!(f, "circular"),
+            PathKind::Linear => write!(f, "linear"),
         }
     }
 }
@@ -190,19 +1573,150 @@ impl Default for ToolState {
             amount: 100,
             inner_radius: 0.0,
             outer_radius: 100.0,
+            species: 0,
+            species_mix: 1,
+            bond_start: None,
+            stiffness: 10.0,
+            damping: 0.5,
+            breaking_strain: 0.0,
+            gas: false,
+            internal_energy: 10.0,
+            pinned: false,
+            path_kind: PathKind::Still,
+            path_center: Vec2::ZERO,
+            path_angular_speed: 0.5,
+            path_velocity: Vec2::new(10.0, 0.0),
+            test_particles: false,
+            sink: false,
+            accretion_radius: 5.0,
+            collider_kind: ColliderKind::Segment,
+            restitution: 0.5,
+            polygon_points: Vec::new(),
+            container_preset: ContainerPreset::ClosedBox,
+            container_size: 200.0,
+            should_spawn_container: false,
+            should_clear_colliders: false,
+            orbit_primary: None,
+            eccentricity: 0.0,
+            retrograde: false,
+            disk_profile: DiskProfile::Exponential,
+            disk_mass: 1000.0,
+            scale_length: 30.0,
+            bulge_mass: 0.0,
+            bulge_radius: 10.0,
+            nucleus_mass: 0.0,
+            toomre_q: 1.5,
+            clockwise: false,
+            cluster_model: ClusterModel::Plummer,
+            king_depth: 6.0,
+            cluster_mass: 1000.0,
+            virial_radius: 50.0,
+            mass_distribution: MassDistribution::Fixed,
+            min_mass: 0.1,
+            max_mass: 100.0,
+            mass_exponent: 2.35,
+            size_distribution: SizeDistribution::Fixed,
+            min_radius: 0.5,
+            max_radius: 2.0,
+            particle_density: 1.0,
+            velocity_model: VelocityModel::Random,
+            temperature: 1.0,
+            angular_velocity: 0.1,
+            central_mass: 0.0,
+            expansion_rate: 0.1,
+            virial_scaling: false,
+            virial_ratio: 1.0,
+            spawn_shape: SpawnShape::Circle,
+            spawn_size: Vec2::splat(200.0),
+            spiral_arms: 2,
+            pitch_angle: 15.0,
+            arm_width: 5.0,
+            ring_width: 5.0,
+            image_path: String::new(),
+            image_mask: None,
+            image_error: None,
+            color_from_image: true,
+            lattice_kind: LatticeKind::Square,
+            columns: 10,
+            rows: 10,
+            spacing: 10.0,
+            jitter: 0.0,
+            ring_rotation: RingRotation::Keplerian,
+            spectral_index: -1.0,
+            displacement: 0.2,
+            units: UnitSystem::default(),
         }
     }
 }
 
+/// Returns the particle under `position`, picking the closest one if they overlap
+fn particle_at(
+    particles: &Query<(Entity, &Transform, &Radius), With<Particle>>,
+    position: Vec2,
+) -> Option<Entity> {
+    particles
+        .iter()
+        .map(|(entity, transform, radius)| {
+            let distance = transform.translation.truncate().distance(position);
+            (entity, distance, radius.0.max(1.0))
+        })
+        .filter(|(_, distance, radius)| distance <= radius)
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(entity, _, _)| entity)
+}
+
 /// Define actions for tools to do when clicking, dragging etc
+#[allow(clippy::too_many_arguments)]
 pub fn tool_interactions_system(
     mut tool_state: ResMut<ToolState>,
     mut commands: Commands,
     mut gizmos: Gizmos,
     cursor_coords: Res<CursorWorldCoords>,
     mouse_input: Res<ButtonInput<MouseButton>>,
+    key_input: Res<ButtonInput<KeyCode>>,
+    particles: Query<(Entity, &Transform, &Radius), With<Particle>>,
+    primaries: Query<(&Transform, &OldPosition, &Mass), With<Particle>>,
+    fixed_time: Res<Time<Fixed>>,
+    sim_settings: Res<SimSettings>,
+    expansion: Res<Expansion>,
+    units: Res<UnitSystem>,
 ) {
     let cursor_coords = cursor_coords.0;
+    if tool_state.units != *units {
+        tool_state.units = *units;
+    }
+
+    if tool_state.selected_tool == Tool::SpawnInOrbit && key_input.just_pressed(KeyCode::Escape) {
+        tool_state.orbit_primary = None;
+    }
+
+    if tool_state.selected_tool == Tool::SpawnRandomParticles
+        && tool_state.spawn_shape == SpawnShape::Polygon
+    {
+        if key_input.just_pressed(KeyCode::Enter) && tool_state.polygon_points.len() >= 3 {
+            // spawn around the middle of the corners so rotating velocity models turn about it
+            let points = &tool_state.polygon_points;
+            tool_state.position = points.iter().sum::<Vec2>() / points.len() as f32;
+            tool_state.spawn_random_particles(&mut commands);
+            tool_state.polygon_points.clear();
+        }
+        if key_input.just_pressed(KeyCode::Escape) {
+            tool_state.polygon_points.clear();
+        }
+    }
+
+    if tool_state.selected_tool == Tool::DrawCollider
+        && tool_state.collider_kind == ColliderKind::Polygon
+    {
+        if key_input.just_pressed(KeyCode::Enter) && tool_state.polygon_points.len() >= 3 {
+            let shape = tool_state.dragged_collider_shape(cursor_coords);
+            tool_state.spawn_collider(&mut commands, shape);
+            tool_state.polygon_points.clear();
+        }
+        if key_input.just_pressed(KeyCode::Escape) {
+            tool_state.polygon_points.clear();
+        }
+    }
 
     let just_released = mouse_input.just_released(MouseButton::Left);
     let just_pressed = mouse_input.just_pressed(MouseButton::Left);
@@ -216,6 +1730,40 @@ pub fn tool_interactions_system(
             Tool::SpawnRandomParticles => {
                 tool_state.preview_random_particles(&mut gizmos, cursor_coords)
             }
+            Tool::ConnectParticles => {
+                if let Some((_, transform, radius)) = particle_at(&particles, cursor_coords)
+                    .and_then(|entity| particles.get(entity).ok())
+                {
+                    gizmos.circle_2d(transform.translation.truncate(), radius.0, Color::WHITE);
+                }
+            }
+            Tool::DrawCollider => {
+                if tool_state.collider_kind == ColliderKind::Polygon {
+                    let points = tool_state.polygon_points.iter().copied();
+                    gizmos.linestrip_2d(points.chain([cursor_coords]), Color::WHITE);
+                }
+                gizmos.cross_2d(cursor_coords, 2.0, Color::WHITE);
+            }
+            Tool::SpawnInOrbit => {
+                if let Some(Ok((primary, _, _))) =
+                    tool_state.orbit_primary.map(|entity| primaries.get(entity))
+                {
+                    gizmos.cross_2d(primary.translation.truncate(), 4.0, Color::WHITE);
+                }
+                match particle_at(&particles, cursor_coords)
+                    .and_then(|entity| particles.get(entity).ok())
+                {
+                    Some((_, transform, radius)) => {
+                        gizmos.circle_2d(transform.translation.truncate(), radius.0, Color::WHITE);
+                    }
+                    None => tool_state.preview_orbit(&mut gizmos, &primaries, cursor_coords),
+                }
+            }
+            Tool::SpawnGalaxy => tool_state.preview_galaxy(&mut gizmos, cursor_coords),
+            Tool::SpawnCluster => {
+                gizmos.circle_2d(cursor_coords, tool_state.virial_radius, Color::WHITE);
+            }
+            Tool::SpawnLattice => tool_state.preview_lattice(&mut gizmos, cursor_coords),
         }
     }
 
@@ -223,8 +1771,31 @@ pub fn tool_interactions_system(
         match tool_state.selected_tool {
             Tool::SpawnParticle => tool_state.position = cursor_coords,
             Tool::SpawnRandomParticles => {
-                tool_state.position = cursor_coords;
-                tool_state.spawn_random_particles(&mut commands)
+                if tool_state.spawn_shape == SpawnShape::Polygon {
+                    tool_state.polygon_points.push(cursor_coords);
+                } else {
+                    tool_state.position = cursor_coords;
+                    tool_state.spawn_random_particles(&mut commands)
+                }
+            }
+            Tool::ConnectParticles => {
+                tool_state.bond_start = particle_at(&particles, cursor_coords)
+            }
+            Tool::DrawCollider => match tool_state.collider_kind {
+                ColliderKind::Polygon => tool_state.polygon_points.push(cursor_coords),
+                _ => tool_state.position = cursor_coords,
+            },
+            Tool::SpawnInOrbit => match particle_at(&particles, cursor_coords) {
+                Some(primary) => tool_state.orbit_primary = Some(primary),
+                None => tool_state.spawn_in_orbit(
+                    &mut commands,
+                    &primaries,
+                    cursor_coords,
+                    fixed_time.timestep().as_secs_f32(),
+                ),
+            },
+            Tool::SpawnGalaxy | Tool::SpawnCluster | Tool::SpawnLattice => {
+                tool_state.position = cursor_coords
             }
         }
     }
@@ -241,6 +1812,37 @@ pub fn tool_interactions_system(
             Tool::SpawnRandomParticles => {
                 tool_state.preview_random_particles(&mut gizmos, cursor_coords);
             }
+            Tool::ConnectParticles => {
+                if let Some(Ok((_, start, _))) = tool_state.bond_start.map(|e| particles.get(e)) {
+                    gizmos.line_2d(start.translation.truncate(), cursor_coords, Color::WHITE);
+                }
+            }
+            Tool::DrawCollider => {
+                if tool_state.collider_kind != ColliderKind::Polygon {
+                    tool_state
+                        .dragged_collider_shape(cursor_coords)
+                        .draw(&mut gizmos, Color::WHITE);
+                }
+            }
+            Tool::SpawnInOrbit => {}
+            Tool::SpawnGalaxy => {
+                tool_state.preview_galaxy(&mut gizmos, tool_state.position);
+                gizmos.arrow_2d(
+                    tool_state.position,
+                    tool_state.position * 2.0 - cursor_coords,
+                    Color::WHITE,
+                );
+            }
+            Tool::SpawnCluster => {
+                let position = tool_state.position;
+                gizmos.circle_2d(position, tool_state.virial_radius, Color::WHITE);
+                gizmos.arrow_2d(position, position * 2.0 - cursor_coords, Color::WHITE);
+            }
+            Tool::SpawnLattice => {
+                let position = tool_state.position;
+                tool_state.preview_lattice(&mut gizmos, position);
+                gizmos.arrow_2d(position, position * 2.0 - cursor_coords, Color::WHITE);
+            }
         }
     }
 
@@ -250,6 +1852,63 @@ pub fn tool_interactions_system(
             Tool::SpawnRandomParticles => {
                 tool_state.preview_random_particles(&mut gizmos, cursor_coords);
             }
+            Tool::ConnectParticles => {
+                if let Some(end) = particle_at(&particles, cursor_coords) {
+                    tool_state.connect_particles(&mut commands, &particles, end);
+                }
+                tool_state.bond_start = None;
+            }
+            Tool::DrawCollider => {
+                if tool_state.collider_kind != ColliderKind::Polygon {
+                    let shape = tool_state.dragged_collider_shape(cursor_coords);
+                    tool_state.spawn_collider(&mut commands, shape);
+                }
+            }
+            Tool::SpawnInOrbit => {}
+            Tool::SpawnGalaxy => {
+                // the arrow is how far the galaxy moves in a second
+                let velocity = tool_state.position - cursor_coords;
+                tool_state.spawn_galaxy(&mut commands, velocity);
+            }
+            Tool::SpawnCluster => {
+                let velocity = tool_state.position - cursor_coords;
+                tool_state.spawn_cluster(&mut commands, velocity);
+            }
+            Tool::SpawnLattice => {
+                let velocity = tool_state.position - cursor_coords;
+                let cosmology = sim_settings.cosmology;
+                let growth_rate = if cosmology.enabled {
+                    cosmology.growth_rate(expansion.scale_factor)
+                } else {
+                    0.0
+                };
+                tool_state.spawn_lattice(&mut commands, velocity, growth_rate);
+            }
+        }
+    }
+}
+
+/// Spawns container presets and clears colliders when asked to from the tool ui
+pub fn collider_actions_system(
+    mut tool_state: ResMut<ToolState>,
+    mut commands: Commands,
+    colliders: Query<Entity, With<Collider>>,
+) {
+    if tool_state.should_spawn_container {
+        for shape in tool_state
+            .container_preset
+            .shapes(Vec2::ZERO, tool_state.container_size)
+        {
+            tool_state.spawn_collider(&mut commands, shape);
+        }
+        tool_state.should_spawn_container = false;
+    }
+
+    if tool_state.should_clear_colliders {
+        for entity in colliders.iter() {
+            commands.entity(entity).despawn();
         }
+        tool_state.polygon_points.clear();
+        tool_state.should_clear_colliders = false;
     }
 }
@@ -6,5 +6,6 @@ pub mod camera;
 pub mod gui;
 pub mod input;
 pub mod particle;
+pub mod scenarios;
 pub mod simulation;
 pub mod render;
@@ -8,6 +8,7 @@ use n_body::gui::GuiPlugin;
 use n_body::input::InputPlugin;
 use n_body::particle::ParticlePlugin;
 use n_body::render::RenderPlugin;
+use n_body::scenarios::ScenarioPlugin;
 use n_body::simulation::SimPlugin;
 
 fn main() {
@@ -41,6 +42,7 @@ fn main() {
             SimPlugin,
             ParticlePlugin,
             RenderPlugin,
+            ScenarioPlugin,
         ))
         .insert_resource(ClearColor(Color::srgb_u8(0x28, 0x28, 0x28)))
         .run();
@@ -2,8 +2,12 @@ use bevy::prelude::*;
 
 use crate::simulation;
 use crate::simulation::motion::Acceleration;
+use crate::simulation::motion::Fixed;
+use crate::simulation::motion::KinematicPath;
 use crate::simulation::motion::OldPosition;
 use crate::simulation::motion::PreviousAcceleration;
+use crate::simulation::sinks::Sink;
+use crate::simulation::sph::Gas;
 
 pub mod spawners;
 
@@ -29,15 +33,48 @@ pub struct Radius(pub f32);
 #[derive(Component, Clone, Copy, Debug)]
 pub struct Mass(pub f32);
 
+/// Test particles feel gravity from massive particles but do not pull on anything themselves
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct TestParticle;
+
+/// Colour to draw a particle with instead of the colour of its species
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Tint(pub Color);
+
+/// Draws a ring around the particle that stays the same size on screen, so it can still be
+/// found when it's far too small to see
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct Marker;
+
+/// Which row and column of the particle life interaction matrix a particle uses
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Species(pub usize);
+
 #[derive(Bundle)]
 pub struct ParticleBundle {
     particle: Particle,
     radius: Radius,
     mass: Mass,
+    species: Species,
     position: Transform,
     old_position: OldPosition,
     acceleration: Acceleration,
     previous_acceleration: PreviousAcceleration,
+    // only one field can be ignored, so everything that isnt always inserted lives in here
+    #[bundle(ignore)]
+    optional: OptionalComponents,
+}
+
+/// Components that [ParticleBundle::spawn] only inserts when they are asked for
+#[derive(Default, Clone, Copy)]
+struct OptionalComponents {
+    gas: Option<Gas>,
+    fixed: bool,
+    path: Option<KinematicPath>,
+    test_particle: bool,
+    sink: Option<Sink>,
+    tint: Option<Tint>,
+    marker: bool,
 }
 
 impl ParticleBundle {
@@ -46,24 +83,59 @@ impl ParticleBundle {
             particle: Particle,
             radius: Radius(1.0),
             mass: Mass(1.0),
+            species: Species(0),
             position: Transform::from_xyz(0.0, 0.0, 0.0),
             old_position: OldPosition(Transform::from_xyz(0.0, 0.0, 0.0)),
             acceleration: Acceleration(Vec2::ZERO),
             previous_acceleration: PreviousAcceleration(Vec2::ZERO),
+            optional: OptionalComponents::default(),
         }
     }
 
-    /// Spawn particle
-    pub fn spawn(self, commands: &mut Commands) {
-        commands.spawn(ParticleBundle {
+    /// Spawn particle, returning its entity
+    pub fn spawn(self, commands: &mut Commands) -> Entity {
+        let optional = self.optional;
+        let mut entity = commands.spawn(ParticleBundle {
             particle: self.particle,
             radius: self.radius,
             mass: self.mass,
+            species: self.species,
             position: self.position,
             old_position: self.old_position,
             acceleration: self.acceleration,
             previous_acceleration: self.previous_acceleration,
+            optional: OptionalComponents::default(),
         });
+
+        if let Some(gas) = optional.gas {
+            entity.insert(gas);
+        }
+
+        if optional.fixed || optional.path.is_some() {
+            entity.insert(Fixed);
+        }
+
+        if let Some(path) = optional.path {
+            entity.insert(path);
+        }
+
+        if optional.test_particle {
+            entity.insert(TestParticle);
+        }
+
+        if let Some(sink) = optional.sink {
+            entity.insert(sink);
+        }
+
+        if let Some(tint) = optional.tint {
+            entity.insert(tint);
+        }
+
+        if optional.marker {
+            entity.insert(Marker);
+        }
+
+        entity.id()
     }
 
     /// Set the radius of the spawned particle
@@ -80,6 +152,62 @@ impl ParticleBundle {
         self
     }
 
+    /// Set the species of the spawned particle
+    /// default: 0
+    pub fn species(mut self, species: usize) -> Self {
+        self.species = Species(species);
+        self
+    }
+
+    /// Make the spawned particle gas with the given internal energy
+    /// default: not gas
+    pub fn gas(mut self, internal_energy: f32) -> Self {
+        self.optional.gas = Some(Gas::new(internal_energy));
+        self
+    }
+
+    /// Pin the spawned particle in place, it still pulls on other particles
+    /// default: false
+    pub fn fixed(mut self, fixed: bool) -> Self {
+        self.optional.fixed = fixed;
+        self
+    }
+
+    /// Move the spawned particle along a scripted path, this also pins it
+    /// default: none
+    pub fn kinematic_path(mut self, path: KinematicPath) -> Self {
+        self.optional.path = Some(path);
+        self
+    }
+
+    /// Make the spawned particle a massless test particle
+    /// default: false
+    pub fn test_particle(mut self, test_particle: bool) -> Self {
+        self.optional.test_particle = test_particle;
+        self
+    }
+
+    /// Make the spawned particle a sink that swallows anything within `accretion_radius`
+    /// default: not a sink
+    pub fn sink(mut self, accretion_radius: f32) -> Self {
+        self.optional.sink = Some(Sink::new(accretion_radius));
+        self
+    }
+
+    /// Draw the spawned particle in this colour instead of its species colour
+    /// default: species colour
+    pub fn tint(mut self, color: Color) -> Self {
+        self.optional.tint = Some(Tint(color));
+        self
+    }
+
+    /// Draw a ring around the spawned particle that doesn't shrink when zooming out
+    /// default: false
+    pub fn marker(mut self, marker: bool) -> Self {
+        self.optional.marker = marker;
+        self
+    }
+
     /// Set the starting position of the spawned particle
     /// default: 0.0 , 0.0
     pub fn position(mut self, pos: Vec2) -> Self {
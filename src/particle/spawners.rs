@@ -1,108 +1,1800 @@
 use std::f32::consts::PI;
+use std::sync::Arc;
 
+use bevy::asset::RenderAssetUsages;
+use bevy::image::{CompressedImageFormats, ImageSampler, ImageType};
 use bevy::prelude::*;
 use rand::random_range;
 
 use super::ParticleBundle;
+use crate::simulation::fft::{fft_2d, Complex};
+use crate::simulation::PHYSICS_UPDATE_HZ;
+
+/// Turns a velocity in units per second into the distance moved per physics step, which is what
+/// [ParticleBundle::velocity] takes
+pub fn per_step(velocity: Vec2) -> Vec2 {
+    velocity / PHYSICS_UPDATE_HZ as f32
+}
+
+/// A normally distributed random number with mean 0 and standard deviation 1, box-muller
+pub fn gaussian() -> f32 {
+    let u: f32 = random_range(f32::EPSILON..1.0);
+    let angle = random_range(0.0..2.0 * PI);
+    (-2.0 * u.ln()).sqrt() * angle.cos()
+}
+
+/// Spawns with more particles than this estimate their potential energy from a random sample of
+/// this many, so summing every pair doesn't stall the frame the spawn happens in
+const POTENTIAL_SAMPLE: usize = 2000;
+
+/// Potential energy of particles given as position, mass and radius, softened the same way as in
+/// the direct solver
+fn potential_energy(particles: &[(Vec2, f32, f32)]) -> f32 {
+    let sample: Vec<&(Vec2, f32, f32)> = if particles.len() > POTENTIAL_SAMPLE {
+        rand::seq::index::sample(&mut rand::rng(), particles.len(), POTENTIAL_SAMPLE)
+            .into_iter()
+            .map(|index| &particles[index])
+            .collect()
+    } else {
+        particles.iter().collect()
+    };
+
+    let mut energy = 0.0;
+    for (i, (position_a, mass_a, radius_a)) in sample.iter().enumerate() {
+        for (position_b, mass_b, radius_b) in &sample[i + 1..] {
+            energy -= mass_a * mass_b / position_a.distance(*position_b).max(radius_a + radius_b);
+        }
+    }
+
+    // every pair is as likely to be in the sample, so scale up by how many were left out
+    let total = particles.len() as f32;
+    let sampled = sample.len() as f32;
+    if sampled < 2.0 {
+        return energy;
+    }
+    energy * total * (total - 1.0) / (sampled * (sampled - 1.0))
+}
+
+/// How random particles start moving
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum VelocityModel {
+    /// Random direction with a speed up to the maximum velocity
+    Random,
+    /// Random thermal motion, every velocity component has variance `temperature / mass`
+    MaxwellBoltzmann,
+    /// Turning like a rigid disk, speed grows with distance from the center
+    SolidBody,
+    /// Circular orbits around the center, from the central mass plus the spawned mass inside
+    Keplerian,
+    /// Moving straight out from the center at a speed proportional to the distance, or in when
+    /// the rate is negative
+    Radial,
+}
+
+impl std::fmt::Display for VelocityModel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VelocityModel::Random => write!(f, "random"),
+            VelocityModel::MaxwellBoltzmann => write!(f, "maxwell boltzmann"),
+            VelocityModel::SolidBody => write!(f, "solid body"),
+            VelocityModel::Keplerian => write!(f, "keplerian"),
+            VelocityModel::Radial => write!(f, "radial"),
+        }
+    }
+}
+
+/// The region random particles are spread over
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum SpawnShape {
+    /// Between the inner and outer radius, denser towards the middle
+    Circle,
+    Rectangle,
+    Ellipse,
+    /// Inside a polygon given point by point
+    Polygon,
+    /// Logarithmic spiral arms between the inner and outer radius
+    Spiral,
+    /// A ring at the outer radius with a gaussian profile across it
+    Ring,
+    /// Density following the brightness of a picture, see [ImageMask]
+    Image,
+}
+
+impl std::fmt::Display for SpawnShape {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SpawnShape::Circle => write!(f, "circle"),
+            SpawnShape::Rectangle => write!(f, "rectangle"),
+            SpawnShape::Ellipse => write!(f, "ellipse"),
+            SpawnShape::Polygon => write!(f, "polygon"),
+            SpawnShape::Spiral => write!(f, "spiral"),
+            SpawnShape::Ring => write!(f, "ring"),
+            SpawnShape::Image => write!(f, "image"),
+        }
+    }
+}
+
+/// Brightness and colour of every pixel of a picture, for spawning particles in its shape
+pub struct ImageMask {
+    width: u32,
+    height: u32,
+    /// Running total of brightness over the pixels, row major from the top
+    cumulative: Vec<f32>,
+    colors: Vec<Color>,
+}
+
+impl ImageMask {
+    /// Load a png from `path`, brightness is luminance times alpha
+    pub fn load(path: &str) -> Result<Self, String> {
+        let bytes =
+            std::fs::read(path).map_err(|error| format!("could not read {path}: {error}"))?;
+        Self::from_png(&bytes, path)
+    }
+
+    /// Decode a png already in memory, `name` is only used in errors
+    fn from_png(bytes: &[u8], name: &str) -> Result<Self, String> {
+        let image = Image::from_buffer(
+            bytes,
+            ImageType::Extension("png"),
+            CompressedImageFormats::NONE,
+            true,
+            ImageSampler::Default,
+            RenderAssetUsages::MAIN_WORLD,
+        )
+        .map_err(|error| format!("could not decode {name}: {error}"))?;
+
+        let (width, height) = (image.width(), image.height());
+        let mut colors = Vec::with_capacity((width * height) as usize);
+        let mut cumulative = Vec::with_capacity(colors.capacity());
+        let mut total = 0.0;
+        for y in 0..height {
+            for x in 0..width {
+                let color = image.get_color_at(x, y).unwrap_or(Color::NONE);
+                total += color.luminance() * color.alpha();
+                cumulative.push(total);
+                colors.push(color.with_alpha(1.0));
+            }
+        }
+
+        if total <= 0.0 {
+            return Err(format!("{name} is completely dark"));
+        }
+
+        Ok(Self {
+            width,
+            height,
+            cumulative,
+            colors,
+        })
+    }
+
+    /// Size of the picture scaled to fit inside `bounds` without stretching it
+    pub fn fitted_size(&self, bounds: Vec2) -> Vec2 {
+        let size = Vec2::new(self.width as f32, self.height as f32);
+        size * (bounds / size).min_element()
+    }
+
+    /// A random point in the picture fitted inside `bounds` and centered on the origin, picked in
+    /// proportion to brightness, and the colour of the pixel it's in
+    fn sample(&self, bounds: Vec2) -> (Vec2, Color) {
+        let total = *self.cumulative.last().unwrap_or(&0.0);
+        let target = random_range(0.0..=1.0) * total;
+        let index = self
+            .cumulative
+            .partition_point(|brightness| *brightness < target)
+            .min(self.cumulative.len() - 1);
+        let pixel = Vec2::new(
+            (index as u32 % self.width) as f32 + random_range(0.0..1.0),
+            (index as u32 / self.width) as f32 + random_range(0.0..1.0),
+        );
+
+        // rows go down the picture but y goes up in the world
+        let size = Vec2::new(self.width as f32, self.height as f32);
+        let offset = (pixel / size - Vec2::splat(0.5)) * Vec2::new(1.0, -1.0);
+        (offset * self.fitted_size(bounds), self.colors[index])
+    }
+}
+
+/// Even odd test for whether `point` is inside the polygon
+fn inside_polygon(points: &[Vec2], point: Vec2) -> bool {
+    let mut inside = false;
+    let mut previous = points[points.len() - 1];
+    for &current in points {
+        if (current.y > point.y) != (previous.y > point.y)
+            && point.x
+                < previous.x
+                    + (point.y - previous.y) / (current.y - previous.y) * (current.x - previous.x)
+        {
+            inside = !inside;
+        }
+        previous = current;
+    }
+    inside
+}
+
+/// How the masses of randomly spawned particles are spread out
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum MassDistribution {
+    /// Every particle gets the same mass
+    Fixed,
+    /// Evenly spread between the minimum and maximum mass
+    Uniform,
+    /// Evenly spread in log mass, so there are as many in 1..10 as in 10..100
+    LogUniform,
+    /// `dN/dm ~ m^-2.35`, salpeter (1955)
+    Salpeter,
+    /// Broken power law with breaks at masses 0.08 and 0.5, kroupa (2001). The breaks are in
+    /// solar masses, so it only looks right if one mass unit is a solar mass
+    Kroupa,
+    /// `dN/dm ~ m^-exponent`
+    PowerLaw,
+}
+
+impl std::fmt::Display for MassDistribution {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MassDistribution::Fixed => write!(f, "fixed"),
+            MassDistribution::Uniform => write!(f, "uniform"),
+            MassDistribution::LogUniform => write!(f, "log uniform"),
+            MassDistribution::Salpeter => write!(f, "salpeter"),
+            MassDistribution::Kroupa => write!(f, "kroupa"),
+            MassDistribution::PowerLaw => write!(f, "power law"),
+        }
+    }
+}
+
+/// How the radii of randomly spawned particles are picked
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum SizeDistribution {
+    /// Every particle gets the same radius
+    Fixed,
+    /// Evenly spread between the minimum and maximum radius, whatever the mass
+    Uniform,
+    /// From the mass, so every particle has the same surface density
+    FromDensity,
+}
+
+impl std::fmt::Display for SizeDistribution {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SizeDistribution::Fixed => write!(f, "fixed"),
+            SizeDistribution::Uniform => write!(f, "uniform"),
+            SizeDistribution::FromDensity => write!(f, "from density"),
+        }
+    }
+}
+
+/// Sample `dN/dm ~ m^-exponent` between `min` and `max` by inverting its cumulative distribution
+fn sample_power_law(min: f32, max: f32, exponent: f32) -> f32 {
+    let u: f32 = random_range(0.0..=1.0);
+    if (exponent - 1.0).abs() < 1e-4 {
+        return min * (max / min).powf(u);
+    }
+    let power = 1.0 - exponent;
+    (min.powf(power) + u * (max.powf(power) - min.powf(power))).powf(1.0 / power)
+}
+
+/// Sample a continuous power law made of `segments`, each the mass it ends at and its exponent,
+/// clipped to `min..max`
+fn sample_broken_power_law(min: f32, max: f32, segments: &[(f32, f32)]) -> f32 {
+    // (lower, upper, exponent, number of particles in it)
+    let mut pieces = Vec::new();
+    let mut start = 0.0_f32;
+    let mut scale = 1.0_f32;
+    for (index, &(end, exponent)) in segments.iter().enumerate() {
+        let (lower, upper) = (start.max(min), end.min(max));
+        if lower < upper {
+            let count = if (exponent - 1.0).abs() < 1e-4 {
+                scale * (upper / lower).ln()
+            } else {
+                let power = 1.0 - exponent;
+                scale * (upper.powf(power) - lower.powf(power)) / power
+            };
+            pieces.push((lower, upper, exponent, count));
+        }
+        // keep the distribution continuous across the break
+        if let Some(&(_, next_exponent)) = segments.get(index + 1) {
+            scale *= end.powf(next_exponent - exponent);
+        }
+        start = end;
+    }
+
+    let total: f32 = pieces.iter().map(|piece| piece.3).sum();
+    let mut pick = random_range(0.0..=1.0) * total;
+    for &(lower, upper, exponent, count) in &pieces {
+        if pick <= count {
+            return sample_power_law(lower, upper, exponent);
+        }
+        pick -= count;
+    }
+    pieces.last().map_or(min, |&(lower, upper, exponent, _)| {
+        sample_power_law(lower, upper, exponent)
+    })
+}
 
 pub struct SpawnRandomParticles {
     amount: u32,
-    outer_radius: f32,
-    inner_radius: f32,
-    radius: f32,
-    mass: f32,
-    velocity_range: f32,
-    value_variation: bool,
+    outer_radius: f32,
+    inner_radius: f32,
+    radius: f32,
+    mass: f32,
+    velocity_range: f32,
+    mass_distribution: MassDistribution,
+    min_mass: f32,
+    max_mass: f32,
+    mass_exponent: f32,
+    size_distribution: SizeDistribution,
+    min_radius: f32,
+    max_radius: f32,
+    density: f32,
+    velocity_model: VelocityModel,
+    temperature: f32,
+    angular_velocity: f32,
+    central_mass: f32,
+    expansion_rate: f32,
+    clockwise: bool,
+    virial_ratio: Option<f32>,
+    shape: SpawnShape,
+    size: Vec2,
+    polygon: Vec<Vec2>,
+    spiral_arms: u32,
+    pitch_angle: f32,
+    arm_width: f32,
+    ring_width: f32,
+    image_mask: Option<Arc<ImageMask>>,
+    color_from_image: bool,
+    position: Vec2,
+    species: usize,
+    gas: Option<f32>,
+    test_particles: bool,
+    gravitational_constant: f32,
+}
+
+impl SpawnRandomParticles {
+    /// Create new random particle spawner, call spawn to actually spawn it
+    pub fn new() -> Self {
+        Self {
+            amount: 100,
+            outer_radius: 100.0,
+            inner_radius: 0.0,
+            radius: 1.0,
+            mass: 1.0,
+            mass_distribution: MassDistribution::Fixed,
+            min_mass: 0.1,
+            max_mass: 100.0,
+            mass_exponent: 2.35,
+            size_distribution: SizeDistribution::Fixed,
+            min_radius: 0.5,
+            max_radius: 2.0,
+            density: 1.0,
+            velocity_range: 0.0,
+            velocity_model: VelocityModel::Random,
+            temperature: 1.0,
+            angular_velocity: 0.1,
+            central_mass: 0.0,
+            expansion_rate: 0.1,
+            clockwise: false,
+            virial_ratio: None,
+            shape: SpawnShape::Circle,
+            size: Vec2::splat(200.0),
+            polygon: Vec::new(),
+            spiral_arms: 2,
+            pitch_angle: 0.3,
+            arm_width: 5.0,
+            ring_width: 5.0,
+            image_mask: None,
+            color_from_image: false,
+            position: Vec2::ZERO,
+            species: 1,
+            gas: None,
+            test_particles: false,
+            gravitational_constant: 1.0,
+        }
+    }
+
+    /// The amount of particles to spawn
+    pub fn amount(mut self, amount: u32) -> Self {
+        self.amount = amount;
+        self
+    }
+
+    /// The mass of the spawned particles
+    pub fn mass(mut self, mass: f32) -> Self {
+        self.mass = mass;
+        self
+    }
+
+    /// The maximum velocity of spawned particles, in units per step, for [VelocityModel::Random]
+    pub fn velocity(mut self, velocity_range: f32) -> Self {
+        self.velocity_range = velocity_range;
+        self
+    }
+
+    /// How the particles start moving, see [VelocityModel]
+    /// default: [VelocityModel::Random]
+    pub fn velocity_model(mut self, velocity_model: VelocityModel) -> Self {
+        self.velocity_model = velocity_model;
+        self
+    }
+
+    /// Temperature for [VelocityModel::MaxwellBoltzmann], with boltzmann's constant 1 and
+    /// velocities in units per second
+    pub fn temperature(mut self, temperature: f32) -> Self {
+        self.temperature = temperature.max(0.0);
+        self
+    }
+
+    /// Radians per second for [VelocityModel::SolidBody]
+    pub fn angular_velocity(mut self, angular_velocity: f32) -> Self {
+        self.angular_velocity = angular_velocity;
+        self
+    }
+
+    /// Mass assumed to sit at the center for [VelocityModel::Keplerian], on top of the spawned
+    /// particles inside each orbit
+    pub fn central_mass(mut self, central_mass: f32) -> Self {
+        self.central_mass = central_mass.max(0.0);
+        self
+    }
+
+    /// Speed per unit distance from the center, per second, for [VelocityModel::Radial].
+    /// Negative makes the particles fall in
+    pub fn expansion_rate(mut self, expansion_rate: f32) -> Self {
+        self.expansion_rate = expansion_rate;
+        self
+    }
+
+    /// If true the rotating models turn clockwise instead of anticlockwise
+    pub fn clockwise(mut self, clockwise: bool) -> Self {
+        self.clockwise = clockwise;
+        self
+    }
+
+    /// Scale the velocities afterwards so `2K / |W|` is this, 1 is virial equilibrium.
+    /// `None` leaves them as the velocity model made them
+    /// default: `None`
+    pub fn virial_ratio(mut self, virial_ratio: Option<f32>) -> Self {
+        self.virial_ratio = virial_ratio;
+        self
+    }
+
+    /// Newton's constant the velocities are worked out with
+    /// default: 1.0
+    pub fn gravitational_constant(mut self, gravitational_constant: f32) -> Self {
+        self.gravitational_constant = gravitational_constant;
+        self
+    }
+
+    /// The outer radius of the circle that the particles will be spawned in
+    pub fn outer_radius(mut self, outer_radius: f32) -> Self {
+        self.outer_radius = outer_radius;
+        self
+    }
+
+    /// The inner radius of the circle that the particles will be spawned in
+    pub fn inner_radius(mut self, inner_radius: f32) -> Self {
+        self.inner_radius = inner_radius;
+        self
+    }
+
+    /// How the masses are spread out, see [MassDistribution]
+    /// default: [MassDistribution::Fixed]
+    pub fn mass_distribution(mut self, mass_distribution: MassDistribution) -> Self {
+        self.mass_distribution = mass_distribution;
+        self
+    }
+
+    /// Smallest and largest mass when the masses aren't fixed
+    pub fn mass_range(mut self, min_mass: f32, max_mass: f32) -> Self {
+        self.min_mass = min_mass.max(f32::EPSILON);
+        self.max_mass = max_mass.max(self.min_mass);
+        self
+    }
+
+    /// The exponent of [MassDistribution::PowerLaw]
+    /// default: 2.35
+    pub fn mass_exponent(mut self, mass_exponent: f32) -> Self {
+        self.mass_exponent = mass_exponent;
+        self
+    }
+
+    /// How the radii are picked, see [SizeDistribution]
+    /// default: [SizeDistribution::Fixed]
+    pub fn size_distribution(mut self, size_distribution: SizeDistribution) -> Self {
+        self.size_distribution = size_distribution;
+        self
+    }
+
+    /// Smallest and largest radius for [SizeDistribution::Uniform]
+    pub fn radius_range(mut self, min_radius: f32, max_radius: f32) -> Self {
+        self.min_radius = min_radius.max(0.0);
+        self.max_radius = max_radius.max(self.min_radius);
+        self
+    }
+
+    /// Mass per unit area for [SizeDistribution::FromDensity]
+    pub fn density(mut self, density: f32) -> Self {
+        self.density = density.max(f32::EPSILON);
+        self
+    }
+
+    fn sample_mass(&self) -> f32 {
+        let (min, max) = (self.min_mass, self.max_mass);
+        if self.mass_distribution != MassDistribution::Fixed && min >= max {
+            return min;
+        }
+        match self.mass_distribution {
+            MassDistribution::Fixed => self.mass,
+            MassDistribution::Uniform => random_range(min..=max),
+            MassDistribution::LogUniform => sample_power_law(min, max, 1.0),
+            MassDistribution::Salpeter => sample_power_law(min, max, 2.35),
+            MassDistribution::Kroupa => {
+                sample_broken_power_law(min, max, &[(0.08, 0.3), (0.5, 1.3), (f32::INFINITY, 2.3)])
+            }
+            MassDistribution::PowerLaw => sample_power_law(min, max, self.mass_exponent),
+        }
+    }
+
+    fn sample_radius(&self, mass: f32) -> f32 {
+        match self.size_distribution {
+            SizeDistribution::Fixed => self.radius,
+            SizeDistribution::Uniform => random_range(self.min_radius..=self.max_radius),
+            SizeDistribution::FromDensity => (mass / (PI * self.density)).sqrt(),
+        }
+    }
+
+    /// The region to spawn particles in, see [SpawnShape]
+    /// default: [SpawnShape::Circle]
+    pub fn shape(mut self, shape: SpawnShape) -> Self {
+        self.shape = shape;
+        self
+    }
+
+    /// Width and height of [SpawnShape::Rectangle] and [SpawnShape::Ellipse], pictures for
+    /// [SpawnShape::Image] are scaled to fit inside it
+    pub fn size(mut self, size: Vec2) -> Self {
+        self.size = size.max(Vec2::ZERO);
+        self
+    }
+
+    /// Corners of [SpawnShape::Polygon] relative to the position
+    pub fn polygon(mut self, polygon: Vec<Vec2>) -> Self {
+        self.polygon = polygon;
+        self
+    }
+
+    /// Number of arms of [SpawnShape::Spiral]
+    /// default: 2
+    pub fn spiral_arms(mut self, spiral_arms: u32) -> Self {
+        self.spiral_arms = spiral_arms.max(1);
+        self
+    }
+
+    /// Angle in radians between a [SpawnShape::Spiral] arm and a circle, smaller winds tighter
+    /// default: 0.3
+    pub fn pitch_angle(mut self, pitch_angle: f32) -> Self {
+        self.pitch_angle = pitch_angle.clamp(0.01, PI / 2.0 - 0.01);
+        self
+    }
+
+    /// Standard deviation of the spread around a [SpawnShape::Spiral] arm
+    pub fn arm_width(mut self, arm_width: f32) -> Self {
+        self.arm_width = arm_width.max(0.0);
+        self
+    }
+
+    /// Standard deviation of the spread across a [SpawnShape::Ring]
+    pub fn ring_width(mut self, ring_width: f32) -> Self {
+        self.ring_width = ring_width.max(0.0);
+        self
+    }
+
+    /// Picture for [SpawnShape::Image]
+    pub fn image_mask(mut self, image_mask: Option<Arc<ImageMask>>) -> Self {
+        self.image_mask = image_mask;
+        self
+    }
+
+    /// If true particles spawned from an image take the colour of their pixel
+    pub fn color_from_image(mut self, color_from_image: bool) -> Self {
+        self.color_from_image = color_from_image;
+        self
+    }
+
+    /// The center of the region for particles to be spawned in
+    pub fn position(mut self, position: Vec2) -> Self {
+        self.position = position;
+        self
+    }
+
+    /// The radius of the spawned particles
+    pub fn radius(mut self, radius: f32) -> Self {
+        self.radius = radius;
+        self
+    }
+
+    /// The amount of species to mix, each particle gets a random species from `0..species`
+    pub fn species(mut self, species: usize) -> Self {
+        self.species = species.max(1);
+        self
+    }
+
+    /// Spawn the particles as gas with this internal energy, `None` for normal particles
+    pub fn gas(mut self, internal_energy: Option<f32>) -> Self {
+        self.gas = internal_energy;
+        self
+    }
+
+    /// If true the particles are massless test particles
+    pub fn test_particles(mut self, test_particles: bool) -> Self {
+        self.test_particles = test_particles;
+        self
+    }
+
+    /// A random point in the spawn region relative to the position, and the colour of the pixel
+    /// it came from when spawning from a picture
+    fn sample_offset(&self) -> (Vec2, Option<Color>) {
+        let offset = match self.shape {
+            SpawnShape::Circle => {
+                let angle = random_range(0.0..2.0 * PI);
+                let distance = random_range(self.inner_radius..=self.outer_radius);
+                Vec2::from_angle(angle) * distance
+            }
+            SpawnShape::Rectangle => {
+                let half_size = self.size / 2.0;
+                Vec2::new(
+                    random_range(-half_size.x..=half_size.x),
+                    random_range(-half_size.y..=half_size.y),
+                )
+            }
+            SpawnShape::Ellipse => {
+                let distance = random_range(0.0_f32..=1.0).sqrt();
+                Vec2::from_angle(random_range(0.0..2.0 * PI)) * distance * self.size / 2.0
+            }
+            SpawnShape::Polygon => {
+                if self.polygon.len() < 3 {
+                    return (Vec2::ZERO, None);
+                }
+                let min = self.polygon.iter().copied().reduce(Vec2::min).unwrap();
+                let max = self.polygon.iter().copied().reduce(Vec2::max).unwrap();
+                let mut point = min;
+                // a very thin polygon could take forever, give up and put it on a corner
+                for _ in 0..1000 {
+                    point = Vec2::new(random_range(min.x..=max.x), random_range(min.y..=max.y));
+                    if inside_polygon(&self.polygon, point) {
+                        break;
+                    }
+                }
+                point
+            }
+            SpawnShape::Spiral => {
+                let start = self
+                    .inner_radius
+                    .max(self.outer_radius * 0.05)
+                    .max(f32::EPSILON);
+                let distance = random_range(start..=self.outer_radius.max(start));
+                let arm = random_range(0..self.spiral_arms) as f32;
+                let angle = arm / self.spiral_arms as f32 * 2.0 * PI
+                    + (distance / start).ln() / self.pitch_angle.tan();
+                Vec2::from_angle(angle) * distance
+                    + Vec2::new(gaussian(), gaussian()) * self.arm_width
+            }
+            SpawnShape::Ring => {
+                let distance = self.outer_radius + gaussian() * self.ring_width;
+                Vec2::from_angle(random_range(0.0..2.0 * PI)) * distance
+            }
+            SpawnShape::Image => {
+                let Some(mask) = &self.image_mask else {
+                    return (Vec2::ZERO, None);
+                };
+                let (offset, color) = mask.sample(self.size);
+                return (offset, self.color_from_image.then_some(color));
+            }
+        };
+        (offset, None)
+    }
+
+    /// Velocities in units per second for particles given as position relative to the center,
+    /// mass and radius
+    fn sample_velocities(&self, particles: &[(Vec2, f32, f32)]) -> Vec<Vec2> {
+        let turn = if self.clockwise { -1.0 } else { 1.0 };
+
+        // mass inside each particle's orbit, not counting the particle itself
+        let mut enclosed = vec![self.central_mass; particles.len()];
+        if self.velocity_model == VelocityModel::Keplerian && !self.test_particles {
+            let mut order: Vec<usize> = (0..particles.len()).collect();
+            order.sort_by(|a, b| {
+                particles[*a]
+                    .0
+                    .length()
+                    .total_cmp(&particles[*b].0.length())
+            });
+            let mut inside = self.central_mass;
+            for index in order {
+                enclosed[index] = inside;
+                inside += particles[index].1;
+            }
+        }
+
+        particles
+            .iter()
+            .zip(enclosed)
+            .map(|((offset, mass, _), enclosed)| match self.velocity_model {
+                VelocityModel::Random => {
+                    let speed = random_range(0.0..=self.velocity_range.max(0.0));
+                    Vec2::from_angle(random_range(0.0..2.0 * PI)) * speed * PHYSICS_UPDATE_HZ as f32
+                }
+                VelocityModel::MaxwellBoltzmann => {
+                    let sigma = (self.temperature / mass.max(f32::EPSILON)).sqrt();
+                    Vec2::new(gaussian(), gaussian()) * sigma
+                }
+                VelocityModel::SolidBody => offset.perp() * self.angular_velocity * turn,
+                VelocityModel::Keplerian => {
+                    let distance = offset.length();
+                    if distance <= 0.0 {
+                        return Vec2::ZERO;
+                    }
+                    offset.perp() / distance
+                        * (self.gravitational_constant * enclosed / distance).sqrt()
+                        * turn
+                }
+                VelocityModel::Radial => *offset * self.expansion_rate,
+            })
+            .collect()
+    }
+
+    /// Spawn the particles
+    pub fn spawn(self, commands: &mut Commands) {
+        if self.shape == SpawnShape::Image && self.image_mask.is_none() {
+            return;
+        }
+
+        // position relative to the center, mass and radius
+        let mut colors = Vec::with_capacity(self.amount as usize);
+        let particles: Vec<(Vec2, f32, f32)> = (0..self.amount)
+            .map(|_| {
+                let (offset, color) = self.sample_offset();
+                colors.push(color);
+                let mass = self.sample_mass();
+                (offset, mass, self.sample_radius(mass))
+            })
+            .collect();
+
+        let mut velocities = self.sample_velocities(&particles);
+
+        if let (Some(virial_ratio), false) = (self.virial_ratio, self.test_particles) {
+            // measured against the center of mass, so a moving clump isn't counted as hot
+            let total_mass: f32 = particles.iter().map(|(_, mass, _)| mass).sum();
+            let drift = particles
+                .iter()
+                .zip(&velocities)
+                .map(|((_, mass, _), velocity)| *velocity * *mass)
+                .sum::<Vec2>()
+                / total_mass.max(f32::EPSILON);
+            let kinetic: f32 = particles
+                .iter()
+                .zip(&velocities)
+                .map(|((_, mass, _), velocity)| 0.5 * mass * (*velocity - drift).length_squared())
+                .sum();
+            // the central mass sits at the center the offsets are measured from
+            let central: f32 = particles
+                .iter()
+                .map(|(offset, mass, radius)| {
+                    -self.central_mass * mass / offset.length().max(*radius)
+                })
+                .sum();
+            let potential = self.gravitational_constant * (potential_energy(&particles) + central);
+
+            if kinetic > 0.0 && potential < 0.0 {
+                let scale = (virial_ratio.max(0.0) * -potential / (2.0 * kinetic)).sqrt();
+                for velocity in &mut velocities {
+                    *velocity = drift + (*velocity - drift) * scale;
+                }
+            }
+        }
+
+        for (((offset, mass, radius), velocity), color) in
+            particles.into_iter().zip(velocities).zip(colors)
+        {
+            let mut particle = ParticleBundle::new()
+                .radius(radius)
+                .position(self.position + offset)
+                .velocity(per_step(velocity))
+                .mass(mass)
+                .species(random_range(0..self.species))
+                .test_particle(self.test_particles);
+
+            if let Some(internal_energy) = self.gas {
+                particle = particle.gas(internal_energy);
+            }
+
+            if let Some(color) = color {
+                particle = particle.tint(color);
+            }
+
+            particle.spawn(commands);
+        }
+    }
+}
+
+/// How the mass of a [SpawnGalaxyDisk] falls off with radius
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum DiskProfile {
+    /// Surface density `exp(-R / scale_length)`, like most real spiral galaxies
+    Exponential,
+    /// Surface density `1 / R`, which has a flat rotation curve
+    Mestel,
+}
+
+impl std::fmt::Display for DiskProfile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DiskProfile::Exponential => write!(f, "exponential"),
+            DiskProfile::Mestel => write!(f, "mestel"),
+        }
+    }
+}
+
+/// A rotating disk galaxy, with an optional bulge and a single heavy particle as a nucleus.
+///
+/// Disk particles go round at the circular velocity of the mass inside them, plus random
+/// motions sized so the disk has the requested toomre Q. Bulge particles are held up by random
+/// motions alone. Velocities here are all in units per second.
+pub struct SpawnGalaxyDisk {
+    amount: u32,
+    position: Vec2,
+    velocity: Vec2,
+    profile: DiskProfile,
+    disk_mass: f32,
+    scale_length: f32,
+    outer_radius: f32,
+    bulge_mass: f32,
+    bulge_radius: f32,
+    nucleus_mass: f32,
+    toomre_q: f32,
+    clockwise: bool,
+    radius: f32,
+    test_particles: bool,
+    gravitational_constant: f32,
+}
+
+impl SpawnGalaxyDisk {
+    /// Create new galaxy spawner, call spawn to actually spawn it
+    pub fn new() -> Self {
+        Self {
+            amount: 1000,
+            position: Vec2::ZERO,
+            velocity: Vec2::ZERO,
+            profile: DiskProfile::Exponential,
+            disk_mass: 1000.0,
+            scale_length: 30.0,
+            outer_radius: 150.0,
+            bulge_mass: 0.0,
+            bulge_radius: 10.0,
+            nucleus_mass: 0.0,
+            toomre_q: 1.5,
+            clockwise: false,
+            radius: 1.0,
+            test_particles: false,
+            gravitational_constant: 1.0,
+        }
+    }
+
+    /// The amount of particles in the disk and bulge, the nucleus is one more
+    pub fn amount(mut self, amount: u32) -> Self {
+        self.amount = amount;
+        self
+    }
+
+    /// The center of the galaxy
+    pub fn position(mut self, position: Vec2) -> Self {
+        self.position = position;
+        self
+    }
+
+    /// Newton's constant the velocities are worked out with
+    /// default: 1.0
+    pub fn gravitational_constant(mut self, gravitational_constant: f32) -> Self {
+        self.gravitational_constant = gravitational_constant;
+        self
+    }
+
+    /// Velocity of the whole galaxy, in units per second
+    pub fn velocity(mut self, velocity: Vec2) -> Self {
+        self.velocity = velocity;
+        self
+    }
+
+    pub fn profile(mut self, profile: DiskProfile) -> Self {
+        self.profile = profile;
+        self
+    }
+
+    /// Total mass of the disk particles
+    pub fn disk_mass(mut self, disk_mass: f32) -> Self {
+        self.disk_mass = disk_mass;
+        self
+    }
+
+    /// Distance the surface density of an exponential disk falls by a factor of e over
+    pub fn scale_length(mut self, scale_length: f32) -> Self {
+        self.scale_length = scale_length;
+        self
+    }
+
+    /// The disk is cut off past this radius
+    pub fn outer_radius(mut self, outer_radius: f32) -> Self {
+        self.outer_radius = outer_radius;
+        self
+    }
+
+    /// Total mass of the bulge, 0 for no bulge
+    pub fn bulge_mass(mut self, bulge_mass: f32) -> Self {
+        self.bulge_mass = bulge_mass;
+        self
+    }
+
+    /// Plummer scale radius of the bulge
+    pub fn bulge_radius(mut self, bulge_radius: f32) -> Self {
+        self.bulge_radius = bulge_radius;
+        self
+    }
+
+    /// Mass of the central particle, 0 for no nucleus
+    pub fn nucleus_mass(mut self, nucleus_mass: f32) -> Self {
+        self.nucleus_mass = nucleus_mass;
+        self
+    }
+
+    /// Toomre stability parameter of the disk, below 1 it breaks up into clumps and higher is
+    /// hotter and more stable
+    pub fn toomre_q(mut self, toomre_q: f32) -> Self {
+        self.toomre_q = toomre_q;
+        self
+    }
+
+    /// If true the disk turns clockwise
+    pub fn clockwise(mut self, clockwise: bool) -> Self {
+        self.clockwise = clockwise;
+        self
+    }
+
+    /// The radius of the spawned particles
+    pub fn radius(mut self, radius: f32) -> Self {
+        self.radius = radius;
+        self
+    }
+
+    /// If true the disk and bulge are massless test particles moving in the field of the
+    /// nucleus, and start cold
+    pub fn test_particles(mut self, test_particles: bool) -> Self {
+        self.test_particles = test_particles;
+        self
+    }
+
+    fn disk_fraction(&self, radius: f32) -> f32 {
+        match self.profile {
+            DiskProfile::Exponential => {
+                let enclosed = |x: f32| 1.0 - (1.0 + x) * (-x).exp();
+                enclosed(radius / self.scale_length)
+                    / enclosed(self.outer_radius / self.scale_length)
+            }
+            DiskProfile::Mestel => radius / self.outer_radius,
+        }
+        .min(1.0)
+    }
+
+    fn surface_density(&self, radius: f32) -> f32 {
+        if radius > self.outer_radius {
+            return 0.0;
+        }
+        match self.profile {
+            DiskProfile::Exponential => {
+                let x = self.outer_radius / self.scale_length;
+                let normalisation =
+                    2.0 * PI * self.scale_length.powi(2) * (1.0 - (1.0 + x) * (-x).exp());
+                self.disk_mass * (-radius / self.scale_length).exp() / normalisation
+            }
+            DiskProfile::Mestel => self.disk_mass / (2.0 * PI * self.outer_radius * radius),
+        }
+    }
+
+    /// Mass inside `radius` pulling on the particles, and its derivative
+    fn enclosed_mass(&self, radius: f32) -> (f32, f32) {
+        let mut mass = self.nucleus_mass;
+        let mut derivative = 0.0;
+        if !self.test_particles {
+            let a_sq = self.bulge_radius * self.bulge_radius;
+            let r_sq = radius * radius;
+            mass += self.disk_mass * self.disk_fraction(radius)
+                + self.bulge_mass * r_sq / (r_sq + a_sq);
+            derivative += 2.0 * PI * radius * self.surface_density(radius)
+                + 2.0 * self.bulge_mass * radius * a_sq / (r_sq + a_sq).powi(2);
+        }
+        (mass, derivative)
+    }
+
+    /// Random radius from the disk profile
+    fn disk_radius(&self) -> f32 {
+        match self.profile {
+            DiskProfile::Exponential => loop {
+                // the sum of two exponentials has the distribution R exp(-R / scale_length)
+                let u: f32 = random_range(f32::EPSILON..1.0);
+                let v: f32 = random_range(f32::EPSILON..1.0);
+                let radius = -self.scale_length * (u * v).ln();
+                if radius <= self.outer_radius {
+                    return radius;
+                }
+            },
+            DiskProfile::Mestel => random_range(0.0..self.outer_radius),
+        }
+    }
+
+    /// Spawn the galaxy
+    pub fn spawn(self, commands: &mut Commands) {
+        let sense = if self.clockwise { -1.0 } else { 1.0 };
+        let total_mass = self.disk_mass + self.bulge_mass;
+        let bulge_amount = if total_mass > 0.0 {
+            (self.amount as f32 * self.bulge_mass / total_mass).round() as u32
+        } else {
+            0
+        };
+        let disk_amount = self.amount - bulge_amount;
+
+        // every speed below goes as sqrt(G M / r), so they're worked out with G = 1 and scaled here
+        let speed_scale = self.gravitational_constant.sqrt();
+        let spawn = |commands: &mut Commands, offset: Vec2, velocity: Vec2, mass: f32| {
+            ParticleBundle::new()
+                .radius(self.radius)
+                .position(self.position + offset)
+                .velocity(per_step(self.velocity + velocity * speed_scale))
+                .mass(mass)
+                .test_particle(self.test_particles)
+                .spawn(commands);
+        };
+
+        for _ in 0..disk_amount {
+            let radius = self.disk_radius().max(f32::EPSILON);
+            let direction = Vec2::from_angle(random_range(0.0..2.0 * PI));
+
+            let (mass, derivative) = self.enclosed_mass(radius);
+            let circular_speed = (mass / radius).sqrt();
+            let mut radial_speed = 0.0;
+            let mut tangential_speed = circular_speed;
+
+            // epicyclic approximation, kappa^2 = R d(omega^2)/dR + 4 omega^2
+            let kappa_sq = derivative / (radius * radius) + mass / radius.powi(3);
+            if !self.test_particles && kappa_sq > 0.0 {
+                let kappa = kappa_sq.sqrt();
+                let omega = circular_speed / radius;
+                let sigma_radial = (self.toomre_q * 3.36 * self.surface_density(radius) / kappa)
+                    .min(circular_speed);
+                let sigma_tangential = sigma_radial * kappa / (2.0 * omega);
+                radial_speed = sigma_radial * gaussian();
+                tangential_speed += sigma_tangential * gaussian();
+            }
+
+            let velocity = direction * radial_speed + direction.perp() * tangential_speed * sense;
+            spawn(
+                commands,
+                direction * radius,
+                velocity,
+                self.disk_mass / disk_amount as f32,
+            );
+        }
+
+        for _ in 0..bulge_amount {
+            // projected plummer sphere, M(<R) = M R^2 / (R^2 + a^2)
+            let u: f32 = random_range(0.0..0.99);
+            let radius = (self.bulge_radius * (u / (1.0 - u)).sqrt()).max(f32::EPSILON);
+            let direction = Vec2::from_angle(random_range(0.0..2.0 * PI));
+            let (mass, _) = self.enclosed_mass(radius);
+            // isotropic, with the kinetic energy of a circular orbit split between both directions
+            let sigma = (mass / radius / 2.0).sqrt();
+            let velocity = Vec2::new(gaussian(), gaussian()) * sigma;
+            spawn(
+                commands,
+                direction * radius,
+                velocity,
+                self.bulge_mass / bulge_amount as f32,
+            );
+        }
+
+        if self.nucleus_mass > 0.0 {
+            ParticleBundle::new()
+                .radius(self.radius * 3.0)
+                .position(self.position)
+                .velocity(per_step(self.velocity))
+                .mass(self.nucleus_mass)
+                .spawn(commands);
+        }
+    }
+}
+
+/// Distribution function a [SpawnStarCluster] is drawn from
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum ClusterModel {
+    /// Plummer sphere, a smooth core with density falling as r^-5
+    Plummer,
+    /// King model, a lowered isothermal sphere with a sharp tidal edge. How concentrated it is
+    /// is set with [SpawnStarCluster::king_depth]
+    King,
+}
+
+impl std::fmt::Display for ClusterModel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ClusterModel::Plummer => write!(f, "plummer"),
+            ClusterModel::King => write!(f, "king"),
+        }
+    }
+}
+
+/// A star cluster in virial equilibrium.
+///
+/// Positions and velocities are drawn from the 3d distribution function of the model and
+/// projected onto the plane. The projected cluster is then scaled to the requested virial radius
+/// `r_v = M^2 / 2|W|`, and its velocities scaled so `2T = |W|` for the potential energy `W` it
+/// actually has in the plane, so it doesn't start out collapsing or expanding.
+pub struct SpawnStarCluster {
+    model: ClusterModel,
+    king_depth: f32,
+    amount: u32,
     position: Vec2,
+    velocity: Vec2,
+    total_mass: f32,
+    virial_radius: f32,
+    radius: f32,
+    gravitational_constant: f32,
 }
 
-impl SpawnRandomParticles {
-    /// Create new random particle spawner, call spawn to actually spawn it
-    pub fn new() -> Self {
+impl SpawnStarCluster {
+    /// Create new cluster spawner, call spawn to actually spawn it
+    pub fn new(model: ClusterModel) -> Self {
         Self {
-            amount: 100,
-            outer_radius: 100.0,
-            inner_radius: 0.0,
-            value_variation: false,
-            radius: 1.0,
-            mass: 1.0,
-            velocity_range: 0.0,
+            model,
+            king_depth: 6.0,
+            amount: 500,
             position: Vec2::ZERO,
+            velocity: Vec2::ZERO,
+            total_mass: 1000.0,
+            virial_radius: 50.0,
+            radius: 1.0,
+            gravitational_constant: 1.0,
         }
     }
 
+    /// Dimensionless central potential W0 of a king model, higher is more concentrated
+    /// default: 6.0
+    pub fn king_depth(mut self, king_depth: f32) -> Self {
+        self.king_depth = king_depth;
+        self
+    }
+
     /// The amount of particles to spawn
     pub fn amount(mut self, amount: u32) -> Self {
         self.amount = amount;
         self
     }
 
-    /// The mass of the spawned particles
-    pub fn mass(mut self, mass: f32) -> Self {
-        self.mass = mass;
+    /// The center of the cluster
+    pub fn position(mut self, position: Vec2) -> Self {
+        self.position = position;
         self
     }
 
-    /// The maximum velocity of spawned particles
-    pub fn velocity(mut self, velocity_range: f32) -> Self {
-        self.velocity_range = velocity_range;
+    /// Velocity of the whole cluster, in units per second
+    pub fn velocity(mut self, velocity: Vec2) -> Self {
+        self.velocity = velocity;
         self
     }
 
-    /// The outer radius of the circle that the particles will be spawned in
-    pub fn outer_radius(mut self, outer_radius: f32) -> Self {
-        self.outer_radius = outer_radius;
+    /// Mass of the whole cluster, shared equally between the particles
+    pub fn total_mass(mut self, total_mass: f32) -> Self {
+        self.total_mass = total_mass;
         self
     }
 
-    /// The inner radius of the circle that the particles will be spawned in
-    pub fn inner_radius(mut self, inner_radius: f32) -> Self {
-        self.inner_radius = inner_radius;
+    pub fn virial_radius(mut self, virial_radius: f32) -> Self {
+        self.virial_radius = virial_radius;
+        self
+    }
+
+    /// The radius of the spawned particles
+    pub fn radius(mut self, radius: f32) -> Self {
+        self.radius = radius;
+        self
+    }
+
+    /// Newton's constant the velocities are worked out with
+    /// default: 1.0
+    pub fn gravitational_constant(mut self, gravitational_constant: f32) -> Self {
+        self.gravitational_constant = gravitational_constant;
+        self
+    }
+
+    /// Spawn the cluster
+    pub fn spawn(self, commands: &mut Commands) {
+        if self.amount < 2 {
+            return;
+        }
+
+        let samples: Vec<(Vec3, Vec3)> = match self.model {
+            ClusterModel::Plummer => (0..self.amount).map(|_| sample_plummer()).collect(),
+            ClusterModel::King => {
+                let profile = KingProfile::new(self.king_depth as f64);
+                (0..self.amount).map(|_| profile.sample()).collect()
+            }
+        };
+
+        // project onto the plane, about the center of mass so the cluster doesn't drift
+        let count = samples.len() as f32;
+        let center: Vec2 = samples.iter().map(|(p, _)| p.truncate()).sum::<Vec2>() / count;
+        let drift: Vec2 = samples.iter().map(|(_, v)| v.truncate()).sum::<Vec2>() / count;
+        let mut positions: Vec<Vec2> = samples.iter().map(|(p, _)| p.truncate() - center).collect();
+        let mut velocities: Vec<Vec2> = samples.iter().map(|(_, v)| v.truncate() - drift).collect();
+
+        let mass = self.total_mass / count;
+        let total_mass = mass * count;
+
+        let potential_energy = |positions: &[Vec2]| {
+            let particles: Vec<(Vec2, f32, f32)> = positions
+                .iter()
+                .map(|position| (*position, mass, self.radius))
+                .collect();
+            potential_energy(&particles)
+        };
+
+        // scale to the virial radius, twice since softening doesn't scale with the cluster
+        for _ in 0..2 {
+            let energy = potential_energy(&positions);
+            if energy >= 0.0 {
+                break;
+            }
+            let scale = self.virial_radius / (total_mass * total_mass / (-2.0 * energy));
+            positions.iter_mut().for_each(|position| *position *= scale);
+        }
+
+        // the virial radius is a ratio of energies so G only matters here
+        let potential = self.gravitational_constant * potential_energy(&positions);
+        let kinetic: f32 = velocities
+            .iter()
+            .map(|v| 0.5 * mass * v.length_squared())
+            .sum();
+        if kinetic > 0.0 {
+            let scale = (-potential / (2.0 * kinetic)).sqrt();
+            velocities
+                .iter_mut()
+                .for_each(|velocity| *velocity *= scale);
+        }
+
+        for (position, velocity) in positions.into_iter().zip(velocities) {
+            ParticleBundle::new()
+                .radius(self.radius)
+                .position(self.position + position)
+                .velocity(per_step(self.velocity + velocity))
+                .mass(mass)
+                .spawn(commands);
+        }
+    }
+}
+
+/// Uniformly distributed direction in 3d
+fn random_direction() -> Vec3 {
+    let z: f32 = random_range(-1.0..=1.0);
+    let angle = random_range(0.0..2.0 * PI);
+    let ring = (1.0 - z * z).max(0.0).sqrt();
+    Vec3::new(ring * angle.cos(), ring * angle.sin(), z)
+}
+
+/// Position and velocity from a plummer sphere with G = M = a = 1, aarseth, henon and wielen (1974)
+fn sample_plummer() -> (Vec3, Vec3) {
+    // cut off the very few particles that land extremely far out
+    let u: f32 = random_range(1e-3..0.999);
+    let radius = 1.0 / (u.powf(-2.0 / 3.0) - 1.0).sqrt();
+
+    // von neumann rejection for q = v / v_escape, g(q) = q^2 (1 - q^2)^(7/2)
+    let q = loop {
+        let q: f32 = random_range(0.0..1.0);
+        let g: f32 = random_range(0.0..0.1);
+        if g < q * q * (1.0 - q * q).powf(3.5) {
+            break q;
+        }
+    };
+    let speed = q * 2.0_f32.sqrt() * (1.0 + radius * radius).powf(-0.25);
+
+    (random_direction() * radius, random_direction() * speed)
+}
+
+/// `erf(x)` from its taylor series, accurate enough in f64 for the small arguments the king
+/// density needs without the cancellation an approximation would give near the tidal radius
+fn erf(x: f64) -> f64 {
+    let mut term = x;
+    let mut sum = x;
+    for n in 1..200 {
+        term *= -x * x / n as f64;
+        let contribution = term / (2 * n + 1) as f64;
+        sum += contribution;
+        if contribution.abs() < 1e-17 * sum.abs() {
+            break;
+        }
+    }
+    sum * 2.0 / std::f64::consts::PI.sqrt()
+}
+
+/// Density of a king model at dimensionless potential `w`, with the velocity dispersion
+/// parameter set to 1
+fn king_density(w: f64) -> f64 {
+    if w <= 0.0 {
+        return 0.0;
+    }
+    w.exp() * erf(w.sqrt()) - (4.0 * w / std::f64::consts::PI).sqrt() * (1.0 + 2.0 * w / 3.0)
+}
+
+/// Potential and enclosed mass of a king model against radius, in units of the king radius
+struct KingProfile {
+    radii: Vec<f64>,
+    potential: Vec<f64>,
+    mass: Vec<f64>,
+}
+
+impl KingProfile {
+    /// Integrates poisson's equation `W'' + 2W'/r = -9 rho(W) / rho(W0)` out from the center
+    /// until the potential reaches zero at the tidal radius
+    fn new(depth: f64) -> Self {
+        let depth = depth.clamp(0.5, 15.0);
+        let central_density = king_density(depth);
+        let step = 1e-3;
+        let derivatives = |r: f64, (w, dw): (f64, f64)| {
+            (dw, -2.0 * dw / r - 9.0 * king_density(w) / central_density)
+        };
+
+        // start just off the center using the series W = W0 - 3r^2/2
+        let mut r = step;
+        let mut state = (depth - 1.5 * r * r, -3.0 * r);
+        let mut profile = Self {
+            radii: vec![0.0, r],
+            potential: vec![depth, state.0],
+            mass: vec![0.0, 4.0 / 3.0 * std::f64::consts::PI * r.powi(3)],
+        };
+
+        while state.0 > 0.0 && r < 1e4 {
+            let add = |(w, dw): (f64, f64), (kw, kdw): (f64, f64), scale: f64| {
+                (w + kw * scale, dw + kdw * scale)
+            };
+            let k1 = derivatives(r, state);
+            let k2 = derivatives(r + step / 2.0, add(state, k1, step / 2.0));
+            let k3 = derivatives(r + step / 2.0, add(state, k2, step / 2.0));
+            let k4 = derivatives(r + step, add(state, k3, step));
+            let old_density = king_density(state.0);
+            state = (
+                state.0 + step / 6.0 * (k1.0 + 2.0 * k2.0 + 2.0 * k3.0 + k4.0),
+                state.1 + step / 6.0 * (k1.1 + 2.0 * k2.1 + 2.0 * k3.1 + k4.1),
+            );
+            let shell = 2.0
+                * std::f64::consts::PI
+                * step
+                * (r * r * old_density + (r + step).powi(2) * king_density(state.0))
+                / central_density;
+            r += step;
+
+            profile.radii.push(r);
+            profile.potential.push(state.0.max(0.0));
+            profile.mass.push(profile.mass.last().unwrap() + shell);
+        }
+        profile
+    }
+
+    fn sample(&self) -> (Vec3, Vec3) {
+        // invert the enclosed mass for the radius
+        let total = *self.mass.last().unwrap();
+        let target = random_range(0.0..1.0) * total;
+        let index = self
+            .mass
+            .partition_point(|mass| *mass < target)
+            .clamp(1, self.mass.len() - 1);
+        let fraction =
+            (target - self.mass[index - 1]) / (self.mass[index] - self.mass[index - 1]).max(1e-300);
+        let radius = self.radii[index - 1] + fraction * (self.radii[index] - self.radii[index - 1]);
+        let w = self.potential[index - 1]
+            + fraction * (self.potential[index] - self.potential[index - 1]);
+
+        // speeds up to escape have the distribution v^2 (exp(W - v^2 / 2) - 1)
+        let escape = (2.0 * w).sqrt();
+        let distribution = |v: f64| v * v * ((w - v * v / 2.0).exp() - 1.0);
+        let peak = (0..=32)
+            .map(|i| distribution(escape * i as f64 / 32.0))
+            .fold(0.0, f64::max)
+            * 1.1;
+        let speed = if peak <= 0.0 {
+            0.0
+        } else {
+            loop {
+                let v = random_range(0.0..=escape);
+                if random_range(0.0..=peak) < distribution(v) {
+                    break v;
+                }
+            }
+        };
+
+        (
+            random_direction() * radius as f32,
+            random_direction() * speed as f32,
+        )
+    }
+}
+
+/// Arrangement of a [SpawnLattice]
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum LatticeKind {
+    /// Square grid of columns and rows
+    Square,
+    /// Rows offset by half a spacing, so every particle has six neighbours the same distance away
+    Hexagonal,
+    /// Concentric rings a spacing apart, each with particles a spacing apart around it
+    Rings,
+    /// Square grid displaced by a random gaussian field, the zel'dovich approximation used to
+    /// start cosmological runs
+    Zeldovich,
+}
+
+impl std::fmt::Display for LatticeKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LatticeKind::Square => write!(f, "square"),
+            LatticeKind::Hexagonal => write!(f, "hexagonal"),
+            LatticeKind::Rings => write!(f, "rings"),
+            LatticeKind::Zeldovich => write!(f, "zel'dovich"),
+        }
+    }
+}
+
+/// How the rings of [LatticeKind::Rings] move
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum RingRotation {
+    Still,
+    /// Every ring on a circular orbit around the central mass and the rings inside it
+    Keplerian,
+    /// Turning together like a wheel
+    Rigid,
+}
+
+impl std::fmt::Display for RingRotation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RingRotation::Still => write!(f, "still"),
+            RingRotation::Keplerian => write!(f, "keplerian"),
+            RingRotation::Rigid => write!(f, "rigid"),
+        }
+    }
+}
+
+/// Particles laid out in a regular pattern, centered on the position. Velocities are in units per
+/// second.
+pub struct SpawnLattice {
+    kind: LatticeKind,
+    columns: u32,
+    rows: u32,
+    spacing: f32,
+    jitter: f32,
+    position: Vec2,
+    velocity: Vec2,
+    mass: f32,
+    radius: f32,
+    species: usize,
+    test_particles: bool,
+    central_mass: f32,
+    ring_rotation: RingRotation,
+    angular_velocity: f32,
+    spectral_index: f32,
+    displacement: f32,
+    growth_rate: f32,
+    gravitational_constant: f32,
+}
+
+impl SpawnLattice {
+    /// Create new lattice spawner, call spawn to actually spawn it
+    pub fn new(kind: LatticeKind) -> Self {
+        Self {
+            kind,
+            columns: 10,
+            rows: 10,
+            spacing: 10.0,
+            jitter: 0.0,
+            position: Vec2::ZERO,
+            velocity: Vec2::ZERO,
+            mass: 1.0,
+            radius: 1.0,
+            species: 0,
+            test_particles: false,
+            central_mass: 0.0,
+            ring_rotation: RingRotation::Still,
+            angular_velocity: 0.1,
+            spectral_index: -1.0,
+            displacement: 0.2,
+            growth_rate: 0.0,
+            gravitational_constant: 1.0,
+        }
+    }
+
+    /// Particles along each row, zel'dovich grids are square with a power of two along each side
+    /// default: 10
+    pub fn columns(mut self, columns: u32) -> Self {
+        self.columns = columns.max(1);
+        self
+    }
+
+    /// Number of rows, or of rings for [LatticeKind::Rings]
+    /// default: 10
+    pub fn rows(mut self, rows: u32) -> Self {
+        self.rows = rows.max(1);
+        self
+    }
+
+    /// Distance between neighbouring particles
+    /// default: 10.0
+    pub fn spacing(mut self, spacing: f32) -> Self {
+        self.spacing = spacing.max(f32::EPSILON);
         self
     }
 
-    /// If true, all the particles values will be different, making them appear a bit different
-    pub fn value_variation(mut self, value_variation: bool) -> Self {
-        self.value_variation = value_variation;
+    /// Move every particle a random distance up to this from its lattice point
+    /// default: 0.0
+    pub fn jitter(mut self, jitter: f32) -> Self {
+        self.jitter = jitter.max(0.0);
         self
     }
 
-    /// The center of the circle for particles to be spawned in
+    /// The center of the lattice
     pub fn position(mut self, position: Vec2) -> Self {
         self.position = position;
         self
     }
 
+    /// Velocity of the whole lattice, in units per second
+    pub fn velocity(mut self, velocity: Vec2) -> Self {
+        self.velocity = velocity;
+        self
+    }
+
+    /// The mass of the spawned particles
+    pub fn mass(mut self, mass: f32) -> Self {
+        self.mass = mass;
+        self
+    }
+
     /// The radius of the spawned particles
     pub fn radius(mut self, radius: f32) -> Self {
         self.radius = radius;
         self
     }
 
-    /// Spawn the particles
-    pub fn spawn(self, commands: &mut Commands) {
-        for _ in 0..self.amount {
-            let angle = random_range(0.0..2.0 * PI);
-            let distance = random_range(self.inner_radius..self.outer_radius);
-            let position = self.position + Vec2::from_angle(angle) * distance;
-            let mut velocity = Vec2::ZERO;
+    /// The species of the spawned particles
+    pub fn species(mut self, species: usize) -> Self {
+        self.species = species;
+        self
+    }
+
+    /// If true the particles are massless test particles
+    pub fn test_particles(mut self, test_particles: bool) -> Self {
+        self.test_particles = test_particles;
+        self
+    }
+
+    /// Mass of a particle spawned in the middle of [LatticeKind::Rings], 0 for none
+    /// default: 0.0
+    pub fn central_mass(mut self, central_mass: f32) -> Self {
+        self.central_mass = central_mass.max(0.0);
+        self
+    }
+
+    /// How the rings of [LatticeKind::Rings] move
+    /// default: [RingRotation::Still]
+    pub fn ring_rotation(mut self, ring_rotation: RingRotation) -> Self {
+        self.ring_rotation = ring_rotation;
+        self
+    }
+
+    /// Radians per second for [RingRotation::Rigid], negative turns clockwise
+    pub fn angular_velocity(mut self, angular_velocity: f32) -> Self {
+        self.angular_velocity = angular_velocity;
+        self
+    }
+
+    /// Slope `n` of the power spectrum `P(k) ~ k^n` of a [LatticeKind::Zeldovich] field
+    /// default: -1.0
+    pub fn spectral_index(mut self, spectral_index: f32) -> Self {
+        self.spectral_index = spectral_index;
+        self
+    }
+
+    /// Root mean square displacement of a [LatticeKind::Zeldovich] grid, in spacings
+    /// default: 0.2
+    pub fn displacement(mut self, displacement: f32) -> Self {
+        self.displacement = displacement.max(0.0);
+        self
+    }
+
+    /// `H f`, the growth rate of the displacements per second, which makes the velocity of the
+    /// growing mode `H f` times the displacement. With comoving cosmology this is the hubble
+    /// parameter times `omega_matter^0.55` at the starting scale factor
+    /// default: 0.0
+    pub fn growth_rate(mut self, growth_rate: f32) -> Self {
+        self.growth_rate = growth_rate;
+        self
+    }
+
+    /// Newton's constant the velocities are worked out with
+    /// default: 1.0
+    pub fn gravitational_constant(mut self, gravitational_constant: f32) -> Self {
+        self.gravitational_constant = gravitational_constant;
+        self
+    }
+
+    /// Offsets from the center and velocities in units per second before jitter
+    fn lattice(&self) -> Vec<(Vec2, Vec2)> {
+        let spacing = self.spacing;
+        match self.kind {
+            LatticeKind::Square | LatticeKind::Hexagonal => {
+                let hexagonal = self.kind == LatticeKind::Hexagonal;
+                let row_spacing = if hexagonal {
+                    spacing * 3.0_f32.sqrt() / 2.0
+                } else {
+                    spacing
+                };
+                let corner = -Vec2::new(
+                    (self.columns - 1) as f32 * spacing,
+                    (self.rows - 1) as f32 * row_spacing,
+                ) / 2.0;
 
-            if self.velocity_range != 0.0 {
-                let velo_angle = random_range(0.0..2.0 * PI);
-                let velo = random_range(0.0..=self.velocity_range);
-                velocity = Vec2::from_angle(velo_angle) * velo;
+                (0..self.rows)
+                    .flat_map(|row| (0..self.columns).map(move |column| (row, column)))
+                    .map(|(row, column)| {
+                        let shift = if hexagonal && row % 2 == 1 {
+                            spacing / 2.0
+                        } else {
+                            0.0
+                        };
+                        let offset = corner
+                            + Vec2::new(column as f32 * spacing + shift, row as f32 * row_spacing);
+                        (offset, Vec2::ZERO)
+                    })
+                    .collect()
             }
+            LatticeKind::Rings => {
+                let ring_mass = if self.test_particles { 0.0 } else { self.mass };
+                let mut enclosed = self.central_mass;
+                let mut particles = Vec::new();
+                for ring in 1..=self.rows {
+                    let distance = ring as f32 * spacing;
+                    let count = ((2.0 * PI * distance / spacing).round() as u32).max(1);
+                    let speed = match self.ring_rotation {
+                        RingRotation::Still => 0.0,
+                        RingRotation::Keplerian => {
+                            (self.gravitational_constant * enclosed / distance).sqrt()
+                        }
+                        RingRotation::Rigid => self.angular_velocity * distance,
+                    };
+                    for i in 0..count {
+                        let direction = Vec2::from_angle(i as f32 / count as f32 * 2.0 * PI);
+                        particles.push((direction * distance, direction.perp() * speed));
+                    }
+                    enclosed += ring_mass * count as f32;
+                }
+                particles
+            }
+            LatticeKind::Zeldovich => {
+                let size = (self.columns as usize).next_power_of_two().max(2);
+                let corner = -Vec2::splat((size - 1) as f32 * spacing / 2.0);
+                zeldovich_displacements(size, self.spectral_index)
+                    .into_iter()
+                    .enumerate()
+                    .map(|(index, unit_displacement)| {
+                        let grid = Vec2::new((index % size) as f32, (index / size) as f32);
+                        let displacement = unit_displacement * self.displacement * spacing;
+                        (
+                            corner + grid * spacing + displacement,
+                            displacement * self.growth_rate,
+                        )
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    /// Spawn the lattice
+    pub fn spawn(self, commands: &mut Commands) {
+        if self.kind == LatticeKind::Rings && self.central_mass > 0.0 {
+            ParticleBundle::new()
+                .radius(self.radius * 3.0)
+                .position(self.position)
+                .velocity(per_step(self.velocity))
+                .mass(self.central_mass)
+                .species(self.species)
+                .spawn(commands);
+        }
+
+        for (offset, velocity) in self.lattice() {
+            let jitter = if self.jitter > 0.0 {
+                Vec2::from_angle(random_range(0.0..2.0 * PI))
+                    * random_range(0.0_f32..=1.0).sqrt()
+                    * self.jitter
+            } else {
+                Vec2::ZERO
+            };
 
             ParticleBundle::new()
                 .radius(self.radius)
-                .position(position)
-                .velocity(velocity)
+                .position(self.position + offset + jitter)
+                .velocity(per_step(self.velocity + velocity))
                 .mass(self.mass)
+                .species(self.species)
+                .test_particle(self.test_particles)
                 .spawn(commands);
         }
     }
 }
 
+/// Displacements of a `size` by `size` grid, row major, from a gaussian random field with power
+/// spectrum `k^spectral_index`, scaled to a root mean square of one spacing. The field is
+/// periodic, so a grid exactly filling the periodic box tiles without seams.
+///
+/// The displacement is the gradient of the potential of the density field, `psi(k) = i k
+/// delta(k) / k^2`, so it's found from white noise with one fft forwards and one back for each
+/// axis.
+fn zeldovich_displacements(size: usize, spectral_index: f32) -> Vec<Vec2> {
+    let mut noise: Vec<Complex> = (0..size * size)
+        .map(|_| Complex::new(gaussian(), 0.0))
+        .collect();
+    fft_2d(&mut noise, size, false);
+
+    // wave numbers in units of the fundamental mode, the scaling is removed at the end anyway
+    let wave_number = |i: usize| {
+        if i <= size / 2 {
+            i as f32
+        } else {
+            i as f32 - size as f32
+        }
+    };
+
+    let mut x = vec![Complex::ZERO; size * size];
+    let mut y = vec![Complex::ZERO; size * size];
+    for row in 0..size {
+        for column in 0..size {
+            let k = Vec2::new(wave_number(column), wave_number(row));
+            let k_squared = k.length_squared();
+            // the nyquist modes have no partner to keep the field real, so leave them out
+            if k_squared == 0.0 || column == size / 2 || row == size / 2 {
+                continue;
+            }
+            let index = row * size + column;
+            let amplitude = noise[index].scale(k_squared.powf(spectral_index / 4.0) / k_squared);
+            // multiplying by i k
+            let i_delta = Complex::new(-amplitude.im, amplitude.re);
+            x[index] = i_delta.scale(k.x);
+            y[index] = i_delta.scale(k.y);
+        }
+    }
+    fft_2d(&mut x, size, true);
+    fft_2d(&mut y, size, true);
+
+    let displacements: Vec<Vec2> = x
+        .iter()
+        .zip(&y)
+        .map(|(x, y)| Vec2::new(x.re, y.re))
+        .collect();
+    let rms = (displacements
+        .iter()
+        .map(|d| d.length_squared())
+        .sum::<f32>()
+        / displacements.len() as f32)
+        .sqrt();
+    if rms == 0.0 {
+        return displacements;
+    }
+    displacements.into_iter().map(|d| d / rms).collect()
+}
+
 #[derive(Component)]
 pub struct ParticleHose {
     timer: Timer,
@@ -114,6 +1806,7 @@ pub struct ParticleHose {
     velocity: f32,
     direction: Vec2,
     rainbow: bool,
+    test_particles: bool,
 }
 
 /// A particle hose spawns particles in one direction with a velocity, they have
@@ -130,6 +1823,7 @@ impl ParticleHose {
             velocity: 1.0,
             direction: Vec2::from_angle(0.0),
             rainbow: false,
+            test_particles: false,
             position: Vec2::ZERO,
         }
     }
@@ -177,6 +1871,12 @@ impl ParticleHose {
         self
     }
 
+    /// If true the hose sprays massless test particles
+    pub fn test_particles(mut self, test_particles: bool) -> Self {
+        self.test_particles = test_particles;
+        self
+    }
+
     /// The location of the particle spawner
     pub fn position(mut self, pos: Vec2) -> Self {
         self.position = pos;
@@ -211,14 +1911,243 @@ pub fn particle_hose_system(
 
         let velocity = hose.direction.normalize() * hose.velocity;
 
-        commands.spawn(
-            ParticleBundle::new()
-                .radius(hose.radius)
-                .mass(hose.mass)
-                .position(hose.position)
-                .velocity(velocity),
-        );
+        ParticleBundle::new()
+            .radius(hose.radius)
+            .mass(hose.mass)
+            .position(hose.position)
+            .velocity(velocity)
+            .test_particle(hose.test_particles)
+            .spawn(&mut commands);
 
         hose.amount -= 1;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+    use std::sync::Arc;
+
+    use super::{
+        inside_polygon, sample_broken_power_law, ImageMask, KingProfile, LatticeKind, SpawnLattice,
+        SpawnRandomParticles, SpawnShape,
+    };
+    use bevy::prelude::*;
+
+    /// 4x4 png, white in the top left quarter and black everywhere else
+    const QUARTER_PNG: [u8; 79] = [
+        0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x48, 0x44,
+        0x52, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x04, 0x08, 0x06, 0x00, 0x00, 0x00, 0xa9,
+        0xf1, 0x9e, 0x7e, 0x00, 0x00, 0x00, 0x16, 0x49, 0x44, 0x41, 0x54, 0x78, 0xda, 0x63, 0xf8,
+        0x0f, 0x05, 0x0c, 0x0c, 0x0c, 0x10, 0x8c, 0x21, 0x80, 0xc4, 0xc0, 0x2e, 0x00, 0x00, 0x9d,
+        0xde, 0x1b, 0xe5, 0x44, 0xf3, 0x39, 0x55, 0x00, 0x00, 0x00, 0x00, 0x49, 0x45, 0x4e, 0x44,
+        0xae, 0x42, 0x60, 0x82,
+    ];
+
+    /// Checks 1000 samples from `spawner` all pass `inside`
+    fn assert_samples_inside(spawner: SpawnRandomParticles, inside: impl Fn(Vec2) -> bool) {
+        for _ in 0..1000 {
+            let (offset, _) = spawner.sample_offset();
+            assert!(inside(offset), "{offset} is outside the {}", spawner.shape);
+        }
+    }
+
+    #[test]
+    fn test_spawn_regions_contain_their_samples() {
+        let size = Vec2::new(80.0, 30.0);
+        let spawner = || SpawnRandomParticles::new().size(size);
+
+        assert_samples_inside(spawner().inner_radius(20.0).outer_radius(50.0), |offset| {
+            (20.0..=50.0 + 1e-3).contains(&offset.length())
+        });
+        assert_samples_inside(spawner().shape(SpawnShape::Rectangle), |offset| {
+            offset.abs().cmple(size / 2.0).all()
+        });
+        assert_samples_inside(spawner().shape(SpawnShape::Ellipse), |offset| {
+            (offset / (size / 2.0)).length() <= 1.0 + 1e-5
+        });
+
+        let triangle = vec![
+            Vec2::new(-40.0, -20.0),
+            Vec2::new(40.0, -20.0),
+            Vec2::new(0.0, 30.0),
+        ];
+        assert_samples_inside(
+            spawner()
+                .shape(SpawnShape::Polygon)
+                .polygon(triangle.clone()),
+            |offset| inside_polygon(&triangle, offset),
+        );
+
+        // gaussian spread, so nothing should be more than 6 standard deviations out
+        assert_samples_inside(
+            spawner()
+                .shape(SpawnShape::Ring)
+                .outer_radius(100.0)
+                .ring_width(2.0),
+            |offset| (offset.length() - 100.0).abs() < 6.0 * 2.0,
+        );
+
+        let (inner, outer, arms, pitch, width) = (10.0, 100.0, 3, 0.3_f32, 1.0);
+        let arm_points: Vec<Vec2> = (0..arms)
+            .flat_map(|arm| {
+                (0..=2000).map(move |i| {
+                    let distance = inner + (outer - inner) * i as f32 / 2000.0;
+                    let angle =
+                        arm as f32 / arms as f32 * 2.0 * PI + (distance / inner).ln() / pitch.tan();
+                    Vec2::from_angle(angle) * distance
+                })
+            })
+            .collect();
+        assert_samples_inside(
+            spawner()
+                .shape(SpawnShape::Spiral)
+                .inner_radius(inner)
+                .outer_radius(outer)
+                .spiral_arms(arms)
+                .pitch_angle(pitch)
+                .arm_width(width),
+            |offset| {
+                arm_points
+                    .iter()
+                    .any(|point| point.distance(offset) < 6.0 * width)
+            },
+        );
+    }
+
+    #[test]
+    fn test_image_mask_only_spawns_on_bright_pixels() {
+        let mask = ImageMask::from_png(&QUARTER_PNG, "quarter").unwrap();
+        assert_eq!(mask.fitted_size(Vec2::new(100.0, 50.0)), Vec2::splat(50.0));
+
+        let spawner = SpawnRandomParticles::new()
+            .shape(SpawnShape::Image)
+            .size(Vec2::splat(100.0))
+            .image_mask(Some(Arc::new(mask)))
+            .color_from_image(true);
+        for _ in 0..1000 {
+            let (offset, color) = spawner.sample_offset();
+            // the top left of the picture is up and to the left of the center
+            assert!(
+                (-50.0..=0.0).contains(&offset.x) && (0.0..=50.0).contains(&offset.y),
+                "{offset} is outside the bright quarter"
+            );
+            let color = color.unwrap().to_srgba();
+            assert!(color.red > 0.99 && color.green > 0.99 && color.blue > 0.99);
+        }
+    }
+
+    #[test]
+    fn test_broken_power_law_is_continuous() {
+        // flat up to 1 then m^-2, which puts half of 0.5..2 on each side of the break
+        let samples = 20000;
+        let below = (0..samples)
+            .map(|_| sample_broken_power_law(0.5, 2.0, &[(1.0, 0.0), (f32::INFINITY, 2.0)]))
+            .inspect(|mass| assert!((0.5..=2.0).contains(mass)))
+            .filter(|mass| *mass < 1.0)
+            .count();
+        let fraction = below as f32 / samples as f32;
+        assert!(
+            (fraction - 0.5).abs() < 0.02,
+            "{fraction} of the samples are below the break"
+        );
+    }
+
+    #[test]
+    fn test_king_profile_matches_tabulated_concentration() {
+        // c = log10(r_t / r_0) from king (1966)
+        for (depth, concentration) in [(3.0, 0.67), (6.0, 1.26), (9.0, 2.12)] {
+            let profile = KingProfile::new(depth);
+            let tidal_radius = *profile.radii.last().unwrap();
+            assert!(
+                (tidal_radius.log10() - concentration).abs() < 0.03,
+                "W0 = {depth} gives c = {}, expected {concentration}",
+                tidal_radius.log10()
+            );
+        }
+    }
+
+    /// Distance from every point to its closest neighbour
+    fn nearest_neighbour_distances(points: &[Vec2]) -> Vec<f32> {
+        points
+            .iter()
+            .enumerate()
+            .map(|(i, point)| {
+                points
+                    .iter()
+                    .enumerate()
+                    .filter(|(j, _)| *j != i)
+                    .map(|(_, other)| point.distance(*other))
+                    .fold(f32::INFINITY, f32::min)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_square_and_hexagonal_lattices_have_requested_spacing() {
+        for kind in [LatticeKind::Square, LatticeKind::Hexagonal] {
+            let points: Vec<Vec2> = SpawnLattice::new(kind)
+                .columns(7)
+                .rows(5)
+                .spacing(3.0)
+                .lattice()
+                .into_iter()
+                .map(|(offset, _)| offset)
+                .collect();
+
+            assert_eq!(points.len(), 7 * 5, "{kind} lattice");
+            for distance in nearest_neighbour_distances(&points) {
+                assert!(
+                    (distance - 3.0).abs() < 1e-4,
+                    "{kind} neighbours {distance} apart"
+                );
+            }
+            let center = points.iter().sum::<Vec2>() / points.len() as f32;
+            assert!(
+                center.length() < 3.0,
+                "{kind} lattice is centered on {center}"
+            );
+        }
+
+        // away from the edges every hexagonal point has six neighbours a spacing away
+        let points: Vec<Vec2> = SpawnLattice::new(LatticeKind::Hexagonal)
+            .spacing(3.0)
+            .lattice()
+            .into_iter()
+            .map(|(offset, _)| offset)
+            .collect();
+        let middle = points
+            .iter()
+            .min_by(|a, b| a.length().total_cmp(&b.length()))
+            .unwrap();
+        let neighbours = points
+            .iter()
+            .filter(|point| (point.distance(*middle) - 3.0).abs() < 1e-4)
+            .count();
+        assert_eq!(neighbours, 6);
+    }
+
+    #[test]
+    fn test_ring_and_zeldovich_lattices_stay_in_place() {
+        let rings = SpawnLattice::new(LatticeKind::Rings)
+            .rows(4)
+            .spacing(5.0)
+            .lattice();
+        for (offset, _) in &rings {
+            let ring = offset.length() / 5.0;
+            assert!((ring - ring.round()).abs() < 1e-4 && (1.0..=4.0).contains(&ring.round()));
+        }
+
+        // rounded up to a power of two along each side, and no particle wanders more than a
+        // few spacings from its grid point
+        let zeldovich = SpawnLattice::new(LatticeKind::Zeldovich)
+            .columns(12)
+            .spacing(2.0)
+            .displacement(0.2)
+            .lattice();
+        assert_eq!(zeldovich.len(), 16 * 16);
+        for (offset, _) in &zeldovich {
+            assert!(offset.abs().max_element() < 16.0 + 2.0 * 2.0, "{offset}");
+        }
+    }
+}
@@ -1,18 +1,55 @@
+use std::collections::HashMap;
+
 use bevy::{prelude::*, render::mesh::CircleMeshBuilder};
 
-use crate::particle::{Particle, Radius};
+use crate::particle::{Marker, Particle, Radius, Species, Tint};
+use crate::simulation::bonds::Bond;
+use crate::simulation::colliders::Collider;
+use crate::simulation::sinks::Sink;
+use crate::simulation::SimSettings;
 
 pub struct RenderPlugin;
 
 impl Plugin for RenderPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(Startup, init_particle_mesh_and_material)
-            .add_systems(Update, give_particles_materials);
+            .add_systems(
+                Update,
+                (
+                    give_particles_materials,
+                    draw_bonds,
+                    draw_sinks,
+                    draw_periodic_box,
+                    draw_colliders,
+                    draw_markers,
+                ),
+            );
     }
 }
 
+/// One material per species, species past the end of the palette wrap around
 #[derive(Resource)]
-pub struct ParticleColorMaterial(Handle<ColorMaterial>);
+pub struct ParticleColorMaterial(Vec<Handle<ColorMaterial>>);
+
+/// Materials for tinted particles, shared between tints that round to the same 5 bit colour so
+/// a picture full of slightly different colours doesn't make a material per particle
+#[derive(Resource, Default)]
+pub struct TintMaterials(HashMap<[u8; 3], Handle<ColorMaterial>>);
+
+const SPECIES_COLORS: [Color; 8] = [
+    Color::srgb(1.0, 1.0, 1.0),
+    Color::srgb(0.98, 0.29, 0.2),
+    Color::srgb(0.72, 0.73, 0.15),
+    Color::srgb(0.51, 0.65, 0.6),
+    Color::srgb(0.83, 0.53, 0.61),
+    Color::srgb(0.98, 0.74, 0.18),
+    Color::srgb(0.56, 0.75, 0.49),
+    Color::srgb(0.99, 0.5, 0.1),
+];
+
+pub fn species_color(species: usize) -> Color {
+    SPECIES_COLORS[species % SPECIES_COLORS.len()]
+}
 
 #[derive(Resource)]
 pub struct ParticleMesh(Handle<Mesh>);
@@ -22,18 +59,27 @@ fn init_particle_mesh_and_material(
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<ColorMaterial>>,
 ) {
-    let material = ColorMaterial::from_color(Color::srgb(1.0, 1.0, 1.0));
+    let material_handles = SPECIES_COLORS
+        .iter()
+        .map(|color| materials.add(ColorMaterial::from_color(*color)))
+        .collect();
     let mesh = CircleMeshBuilder::new(1.0, 20);
     let mesh_handle = meshes.add(mesh);
-    let material_handle = materials.add(material);
     commands.insert_resource(ParticleMesh(mesh_handle));
-    commands.insert_resource(ParticleColorMaterial(material_handle));
+    commands.insert_resource(ParticleColorMaterial(material_handles));
+    commands.init_resource::<TintMaterials>();
 }
 
 fn give_particles_materials(
     mut commands: Commands,
     mut query: Query<
-        (Entity, &Radius, &mut Transform),
+        (
+            Entity,
+            &Radius,
+            &mut Transform,
+            Option<&Species>,
+            Option<&Tint>,
+        ),
         (
             With<Particle>,
             Without<MeshMaterial2d<ColorMaterial>>,
@@ -42,15 +88,106 @@ fn give_particles_materials(
     >,
     mesh: Res<ParticleMesh>,
     material: Res<ParticleColorMaterial>,
+    mut tint_materials: ResMut<TintMaterials>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
 ) {
     if query.is_empty() {
         return;
     }
 
-    for (entity, radius, mut transform) in query.iter_mut() {
+    for (entity, radius, mut transform, species, tint) in query.iter_mut() {
         transform.scale = Vec3::splat(radius.0);
-        let material = MeshMaterial2d(material.0.clone());
+        let handle = match tint {
+            Some(Tint(color)) => {
+                let key = color
+                    .to_srgba()
+                    .to_u8_array_no_alpha()
+                    .map(|channel| channel >> 3);
+                tint_materials
+                    .0
+                    .entry(key)
+                    .or_insert_with(|| {
+                        let [r, g, b] =
+                            key.map(|channel| (channel << 3 | channel >> 2) as f32 / 255.0);
+                        materials.add(ColorMaterial::from_color(Color::srgb(r, g, b)))
+                    })
+                    .clone()
+            }
+            None => {
+                let species = species.map_or(0, |species| species.0);
+                material.0[species % material.0.len()].clone()
+            }
+        };
+        let material = MeshMaterial2d(handle);
         let mesh = Mesh2d(mesh.0.clone());
         commands.entity(entity).insert((mesh, material));
     }
 }
+
+const BOND_COLOR: Color = Color::srgba(0.66, 0.6, 0.52, 0.8);
+
+fn draw_bonds(
+    mut gizmos: Gizmos,
+    bonds: Query<&Bond>,
+    particles: Query<&Transform, With<Particle>>,
+) {
+    for bond in bonds.iter() {
+        if let Ok([a, b]) = particles.get_many([bond.a, bond.b]) {
+            gizmos.line_2d(
+                a.translation.truncate(),
+                b.translation.truncate(),
+                BOND_COLOR,
+            );
+        }
+    }
+}
+
+const SINK_COLOR: Color = Color::srgba(0.83, 0.53, 0.61, 0.5);
+
+fn draw_sinks(mut gizmos: Gizmos, sinks: Query<(&Sink, &Transform)>) {
+    for (sink, transform) in sinks.iter() {
+        gizmos.circle_2d(
+            transform.translation.truncate(),
+            sink.accretion_radius,
+            SINK_COLOR,
+        );
+    }
+}
+
+const PERIODIC_BOX_COLOR: Color = Color::srgba(0.51, 0.65, 0.6, 0.5);
+
+fn draw_periodic_box(mut gizmos: Gizmos, sim_settings: Res<SimSettings>) {
+    let periodic = sim_settings.periodic;
+    if periodic.enabled {
+        gizmos.rect_2d(Vec2::ZERO, Vec2::splat(periodic.size), PERIODIC_BOX_COLOR);
+    }
+}
+
+const COLLIDER_COLOR: Color = Color::srgb(0.92, 0.86, 0.7);
+
+fn draw_colliders(mut gizmos: Gizmos, colliders: Query<&Collider>) {
+    for collider in colliders.iter() {
+        collider.shape.draw(&mut gizmos, COLLIDER_COLOR);
+    }
+}
+
+/// Size of the ring around [Marker] particles, in pixels
+const MARKER_RADIUS: f32 = 6.0;
+
+fn draw_markers(
+    mut gizmos: Gizmos,
+    markers: Query<(&Transform, &Radius, &Species, Option<&Tint>), With<Marker>>,
+    projection: Single<&Projection, With<Camera>>,
+) {
+    let Projection::Orthographic(projection) = *projection else {
+        return;
+    };
+    for (transform, Radius(radius), Species(species), tint) in markers.iter() {
+        let color = tint.map_or_else(|| species_color(*species), |Tint(color)| *color);
+        gizmos.circle_2d(
+            transform.translation.truncate(),
+            (MARKER_RADIUS * projection.scale).max(radius * 1.5),
+            color.with_alpha(0.6),
+        );
+    }
+}
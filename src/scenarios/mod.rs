@@ -0,0 +1,353 @@
+use bevy::prelude::*;
+use std::fmt::Display;
+
+use crate::camera::MoveCamera;
+use crate::particle::spawners::{
+    per_step, ClusterModel, LatticeKind, SpawnGalaxyDisk, SpawnLattice, SpawnRandomParticles,
+    SpawnShape, SpawnStarCluster, VelocityModel,
+};
+use crate::particle::{Particle, ParticleBundle};
+use crate::simulation::bonds::Bond;
+use crate::simulation::colliders::{Collider, ContainerPreset};
+use crate::simulation::cosmology::Expansion;
+use crate::simulation::gravity::orbital_velocity;
+use crate::simulation::regularization::Binary;
+use crate::simulation::sinks::AccretedMass;
+use crate::simulation::units::UnitSystem;
+use crate::simulation::wisdom_holman::Integrator;
+use crate::simulation::{SimSettings, SimTime};
+
+pub mod solar_system;
+
+/// Loads the built in [Scenario]s when one is put in [ScenarioRequest]
+pub struct ScenarioPlugin;
+
+impl Plugin for ScenarioPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ScenarioRequest>()
+            .add_systems(Update, load_scenario.run_if(scenario_requested));
+    }
+}
+
+/// Standard setups that replace everything in the simulation when loaded
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum Scenario {
+    TwoBody,
+    FigureEight,
+    SunEarthMoon,
+    Pythagorean,
+    GalaxyMerger,
+    SaturnRing,
+    PlummerCluster,
+    CollisionBox,
+    SolarSystem,
+}
+
+impl Scenario {
+    pub const ALL: [Scenario; 9] = [
+        Scenario::TwoBody,
+        Scenario::FigureEight,
+        Scenario::SunEarthMoon,
+        Scenario::Pythagorean,
+        Scenario::GalaxyMerger,
+        Scenario::SaturnRing,
+        Scenario::PlummerCluster,
+        Scenario::CollisionBox,
+        Scenario::SolarSystem,
+    ];
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Scenario::TwoBody => "two equal stars on eccentric orbits around each other",
+            Scenario::FigureEight => {
+                "three equal bodies chasing each other around a figure eight, chenciner and montgomery (2000)"
+            }
+            Scenario::SunEarthMoon => "a moon orbiting a planet orbiting a star",
+            Scenario::Pythagorean => {
+                "burrau's problem, masses 3, 4 and 5 let go from rest on a 3-4-5 triangle, uses regularization for the close passes"
+            }
+            Scenario::GalaxyMerger => {
+                "two disk galaxies of test particles on a parabolic encounter, like toomre and toomre (1972)"
+            }
+            Scenario::SaturnRing => "a narrow ring of test particles kept in place by two shepherd moons",
+            Scenario::PlummerCluster => "a star cluster in virial equilibrium",
+            Scenario::CollisionBox => {
+                "two layers of colliding particles sliding past each other in a closed box, mixing like the kelvin-helmholtz instability"
+            }
+            Scenario::SolarSystem => {
+                "the planets, pluto, the biggest asteroids and moons on the 1st of january 2000, in au, solar masses and days"
+            }
+        }
+    }
+
+    /// Changes from the default settings the scenario needs
+    fn settings(&self, settings: &mut SimSettings) {
+        match self {
+            Scenario::Pythagorean => {
+                settings.regularization.enabled = true;
+                settings.regularization.capture_distance = 5.0;
+            }
+            Scenario::CollisionBox => settings.enable_collisions = true,
+            Scenario::SolarSystem => {
+                settings.epoch = Some(solar_system::J2000);
+                // verlet in f32 can't hold an orbit for the thousands of steps in a year, the
+                // moons are inside hill spheres but steps are short enough to kick them round
+                settings.integrator = Integrator::WisdomHolman;
+                settings.wisdom_holman.encounter_hill_radii = 0.0;
+            }
+            _ => {}
+        }
+    }
+
+    fn units(&self) -> UnitSystem {
+        match self {
+            Scenario::SolarSystem => UnitSystem::Planetary,
+            _ => UnitSystem::Dimensionless,
+        }
+    }
+
+    fn camera(&self) -> MoveCamera {
+        let zoom = match self {
+            Scenario::TwoBody | Scenario::FigureEight => 0.25,
+            Scenario::SunEarthMoon => 0.55,
+            Scenario::Pythagorean => 0.15,
+            Scenario::GalaxyMerger => 0.7,
+            Scenario::SaturnRing => 0.35,
+            Scenario::PlummerCluster => 0.3,
+            Scenario::CollisionBox => 0.45,
+            Scenario::SolarSystem => 0.005,
+        };
+        MoveCamera {
+            position: Vec2::ZERO,
+            zoom,
+        }
+    }
+
+    fn spawn(&self, commands: &mut Commands) {
+        match self {
+            Scenario::TwoBody => {
+                let mass = 10000.0;
+                let offset = Vec2::new(100.0, 0.0);
+                let relative = orbital_velocity(offset, 2.0 * mass, 0.5, false);
+                body(-offset / 2.0, -relative / 2.0, mass, 4.0).spawn(commands);
+                body(offset / 2.0, relative / 2.0, mass, 4.0).spawn(commands);
+            }
+            Scenario::FigureEight => {
+                // the g = m = 1 solution scaled up so it goes round in about 20 seconds
+                let (length, mass): (f32, f32) = (100.0, 100000.0);
+                let speed = (mass / length).sqrt();
+                let position = Vec2::new(0.970_004_4, -0.243_087_53) * length;
+                let velocity = Vec2::new(-0.932_407_4, -0.864_731_46) * speed;
+                body(position, -velocity / 2.0, mass, 2.0).spawn(commands);
+                body(-position, -velocity / 2.0, mass, 2.0).spawn(commands);
+                body(Vec2::ZERO, velocity, mass, 2.0).spawn(commands);
+            }
+            Scenario::SunEarthMoon => {
+                let (sun_mass, earth_mass, moon_mass) = (100000.0, 100.0, 1.0);
+                let earth_position = Vec2::new(300.0, 0.0);
+                let moon_offset = Vec2::new(8.0, 0.0);
+
+                let earth_velocity = orbital_velocity(
+                    earth_position,
+                    sun_mass + earth_mass + moon_mass,
+                    0.0,
+                    false,
+                );
+                let moon_velocity = earth_velocity
+                    + orbital_velocity(moon_offset, earth_mass + moon_mass, 0.0, false);
+                // keep the center of mass still
+                let sun_velocity =
+                    -(earth_velocity * earth_mass + moon_velocity * moon_mass) / sun_mass;
+
+                body(Vec2::ZERO, sun_velocity, sun_mass, 10.0).spawn(commands);
+                body(earth_position, earth_velocity, earth_mass, 3.0).spawn(commands);
+                body(earth_position + moon_offset, moon_velocity, moon_mass, 1.0).spawn(commands);
+            }
+            Scenario::Pythagorean => {
+                let (length, mass) = (20.0, 1000.0);
+                for (position, masses) in [
+                    (Vec2::new(1.0, 3.0), 3.0),
+                    (Vec2::new(-2.0, -1.0), 4.0),
+                    (Vec2::new(1.0, -1.0), 5.0),
+                ] {
+                    body(position * length, Vec2::ZERO, masses * mass, 0.5).spawn(commands);
+                }
+            }
+            Scenario::GalaxyMerger => {
+                // parabolic, just enough speed to escape each other
+                let nucleus_mass = 10000.0;
+                let offset = Vec2::new(400.0, 100.0);
+                let speed = (2.0 * 2.0 * nucleus_mass / offset.length()).sqrt() / 2.0;
+                for (position, velocity, clockwise) in [
+                    (-offset / 2.0, Vec2::new(speed, 0.0), false),
+                    (offset / 2.0, Vec2::new(-speed, 0.0), true),
+                ] {
+                    SpawnGalaxyDisk::new()
+                        .amount(1500)
+                        .position(position)
+                        .velocity(velocity)
+                        .nucleus_mass(nucleus_mass)
+                        .scale_length(25.0)
+                        .outer_radius(80.0)
+                        .clockwise(clockwise)
+                        .test_particles(true)
+                        .spawn(commands);
+                }
+            }
+            Scenario::SaturnRing => {
+                let (planet_mass, ring_radius, moon_mass) = (50000.0, 150.0, 20.0);
+                body(Vec2::ZERO, Vec2::ZERO, planet_mass, 15.0).spawn(commands);
+
+                SpawnRandomParticles::new()
+                    .amount(3000)
+                    .radius(0.3)
+                    .shape(SpawnShape::Ring)
+                    .outer_radius(ring_radius)
+                    .ring_width(2.0)
+                    .velocity_model(VelocityModel::Keplerian)
+                    .central_mass(planet_mass)
+                    .test_particles(true)
+                    .spawn(commands);
+
+                for (distance, angle) in [(ring_radius - 8.0, 0.0), (ring_radius + 8.0, 2.0)] {
+                    let position = Vec2::from_angle(angle) * distance;
+                    let velocity = orbital_velocity(position, planet_mass + moon_mass, 0.0, false);
+                    body(position, velocity, moon_mass, 2.0).spawn(commands);
+                }
+            }
+            Scenario::PlummerCluster => {
+                SpawnStarCluster::new(ClusterModel::Plummer)
+                    .amount(800)
+                    .total_mass(20000.0)
+                    .virial_radius(80.0)
+                    .spawn(commands);
+            }
+            Scenario::CollisionBox => {
+                let size = 400.0;
+                for shape in ContainerPreset::ClosedBox.shapes(Vec2::ZERO, size) {
+                    Collider::new(shape).restitution(1.0).spawn(commands);
+                }
+                for (side, species) in [(-1.0, 1), (1.0, 2)] {
+                    SpawnLattice::new(LatticeKind::Hexagonal)
+                        .columns(22)
+                        .rows(12)
+                        .spacing(15.0)
+                        .jitter(1.0)
+                        .position(Vec2::new(0.0, side * 95.0))
+                        .velocity(Vec2::new(-side * 20.0, 0.0))
+                        .mass(0.01)
+                        .radius(5.0)
+                        .species(species)
+                        .spawn(commands);
+                }
+            }
+            Scenario::SolarSystem => solar_system::spawn(commands),
+        }
+    }
+}
+
+impl Display for Scenario {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Scenario::TwoBody => write!(f, "two body orbit"),
+            Scenario::FigureEight => write!(f, "figure eight three body"),
+            Scenario::SunEarthMoon => write!(f, "sun earth moon"),
+            Scenario::Pythagorean => write!(f, "pythagorean three body"),
+            Scenario::GalaxyMerger => write!(f, "galaxy merger"),
+            Scenario::SaturnRing => write!(f, "saturn ring"),
+            Scenario::PlummerCluster => write!(f, "plummer cluster"),
+            Scenario::CollisionBox => write!(f, "collision box"),
+            Scenario::SolarSystem => write!(f, "solar system"),
+        }
+    }
+}
+
+/// The scenario to load next frame, set from the menu bar
+#[derive(Resource, Default)]
+pub struct ScenarioRequest(pub Option<Scenario>);
+
+fn scenario_requested(request: Res<ScenarioRequest>) -> bool {
+    request.0.is_some()
+}
+
+/// A particle with a velocity in units per second
+fn body(position: Vec2, velocity: Vec2, mass: f32, radius: f32) -> ParticleBundle {
+    ParticleBundle::new()
+        .position(position)
+        .velocity(per_step(velocity))
+        .mass(mass)
+        .radius(radius)
+}
+
+/// Clears everything and loads the requested scenario in its units, keeping only whether the
+/// simulation is paused from the old settings
+#[allow(clippy::too_many_arguments)]
+fn load_scenario(
+    mut commands: Commands,
+    mut request: ResMut<ScenarioRequest>,
+    mut settings: ResMut<SimSettings>,
+    mut expansion: ResMut<Expansion>,
+    mut accreted_mass: ResMut<AccretedMass>,
+    mut sim_time: ResMut<SimTime>,
+    mut units: ResMut<UnitSystem>,
+    mut camera: EventWriter<MoveCamera>,
+    clearable: Query<Entity, Or<(With<Particle>, With<Bond>, With<Collider>, With<Binary>)>>,
+) {
+    let Some(scenario) = request.0.take() else {
+        return;
+    };
+
+    for entity in clearable.iter() {
+        commands.entity(entity).despawn();
+    }
+    accreted_mass.0 = 0.0;
+    sim_time.0 = 0.0;
+
+    let paused = settings.paused;
+    *settings = SimSettings::default();
+    settings.paused = paused;
+    *units = scenario.units();
+    settings.gravitational_constant = units.gravitational_constant() as f32;
+    scenario.settings(&mut settings);
+    *expansion = Expansion::default();
+
+    scenario.spawn(&mut commands);
+    camera.write(scenario.camera());
+    info!("loaded scenario {scenario}");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{load_scenario, Scenario, ScenarioRequest};
+    use crate::camera::MoveCamera;
+    use crate::particle::Particle;
+    use crate::simulation::cosmology::Expansion;
+    use crate::simulation::sinks::AccretedMass;
+    use crate::simulation::units::UnitSystem;
+    use crate::simulation::{SimSettings, SimTime};
+    use bevy::prelude::*;
+
+    #[test]
+    fn test_every_scenario_loads() {
+        let mut app = App::new();
+        app.init_resource::<ScenarioRequest>()
+            .init_resource::<SimSettings>()
+            .init_resource::<Expansion>()
+            .init_resource::<AccretedMass>()
+            .init_resource::<SimTime>()
+            .init_resource::<UnitSystem>()
+            .add_event::<MoveCamera>()
+            .add_systems(Update, load_scenario);
+
+        for scenario in Scenario::ALL {
+            app.world_mut().resource_mut::<ScenarioRequest>().0 = Some(scenario);
+            app.update();
+
+            let mut particles = app.world_mut().query_filtered::<(), With<Particle>>();
+            assert!(
+                particles.iter(app.world()).count() > 0,
+                "{scenario} spawned no particles"
+            );
+        }
+    }
+}
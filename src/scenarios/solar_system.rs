@@ -0,0 +1,425 @@
+//! The sun, planets, their biggest moons and a few asteroids at J2000, in AU, solar masses and
+//! days. Everything is flattened onto the ecliptic by ignoring inclinations.
+
+use bevy::math::DVec2;
+use bevy::prelude::*;
+
+use super::body;
+use crate::simulation::units::UnitSystem;
+
+/// Julian date of noon on the first of january 2000
+pub const J2000: f64 = 2451545.0;
+
+const SUN_MASS: f64 = 1.0;
+const SUN_RADIUS: f64 = 4.650_47e-3;
+const SUN_COLOR: Color = Color::srgb(1.0, 0.85, 0.4);
+
+/// Orbit and size of a body, lengths in AU, masses in solar masses and angles in degrees
+struct Elements {
+    mass: f64,
+    radius: f64,
+    semi_major_axis: f64,
+    eccentricity: f64,
+    /// Longitude of periapsis
+    periapsis: f64,
+    mean_longitude: f64,
+    color: Color,
+}
+
+/// A moon with elements relative to the planet at `planet` in [PLANETS]
+struct Moon {
+    planet: usize,
+    retrograde: bool,
+    elements: Elements,
+}
+
+/// Mean elements of the planets from Standish's table for 1800-2050 and osculating elements of
+/// Pluto and the three biggest asteroids, all at J2000. The planets are really the barycenters
+/// of the planet and its moons.
+const PLANETS: [Elements; 12] = [
+    // mercury
+    Elements {
+        mass: 1.6601e-7,
+        radius: 1.6308e-5,
+        semi_major_axis: 0.387_099_27,
+        eccentricity: 0.205_635_93,
+        periapsis: 77.457_796,
+        mean_longitude: 252.250_32,
+        color: Color::srgb(0.6, 0.6, 0.6),
+    },
+    // venus
+    Elements {
+        mass: 2.4478e-6,
+        radius: 4.0454e-5,
+        semi_major_axis: 0.723_335_66,
+        eccentricity: 0.006_776_72,
+        periapsis: 131.602_47,
+        mean_longitude: 181.979_1,
+        color: Color::srgb(0.9, 0.8, 0.6),
+    },
+    // earth
+    Elements {
+        mass: 3.0035e-6,
+        radius: 4.2635e-5,
+        semi_major_axis: 1.000_002_61,
+        eccentricity: 0.016_711_23,
+        periapsis: 102.937_68,
+        mean_longitude: 100.464_57,
+        color: Color::srgb(0.3, 0.5, 0.9),
+    },
+    // mars
+    Elements {
+        mass: 3.2272e-7,
+        radius: 2.266e-5,
+        semi_major_axis: 1.523_710_34,
+        eccentricity: 0.093_394_1,
+        periapsis: -23.943_63,
+        mean_longitude: -4.553_432,
+        color: Color::srgb(0.85, 0.4, 0.25),
+    },
+    // jupiter
+    Elements {
+        mass: 9.5479e-4,
+        radius: 4.7789e-4,
+        semi_major_axis: 5.202_887,
+        eccentricity: 0.048_386_24,
+        periapsis: 14.728_48,
+        mean_longitude: 34.396_44,
+        color: Color::srgb(0.85, 0.7, 0.55),
+    },
+    // saturn
+    Elements {
+        mass: 2.8589e-4,
+        radius: 4.0287e-4,
+        semi_major_axis: 9.536_675_94,
+        eccentricity: 0.053_861_79,
+        periapsis: 92.598_88,
+        mean_longitude: 49.954_24,
+        color: Color::srgb(0.9, 0.8, 0.55),
+    },
+    // uranus
+    Elements {
+        mass: 4.3662e-5,
+        radius: 1.7085e-4,
+        semi_major_axis: 19.189_164_64,
+        eccentricity: 0.047_257_44,
+        periapsis: 170.954_28,
+        mean_longitude: 313.238_1,
+        color: Color::srgb(0.6, 0.85, 0.9),
+    },
+    // neptune
+    Elements {
+        mass: 5.1514e-5,
+        radius: 1.6554e-4,
+        semi_major_axis: 30.069_922_76,
+        eccentricity: 0.008_590_48,
+        periapsis: 44.964_76,
+        mean_longitude: -55.120_03,
+        color: Color::srgb(0.35, 0.5, 0.95),
+    },
+    // pluto
+    Elements {
+        mass: 6.55e-9,
+        radius: 7.93e-6,
+        semi_major_axis: 39.482_116_75,
+        eccentricity: 0.248_827_3,
+        periapsis: 224.068_92,
+        mean_longitude: 238.929_04,
+        color: Color::srgb(0.8, 0.7, 0.6),
+    },
+    // ceres
+    Elements {
+        mass: 4.72e-10,
+        radius: 3.16e-6,
+        semi_major_axis: 2.7666,
+        eccentricity: 0.0785,
+        periapsis: 154.41,
+        mean_longitude: 161.18,
+        color: Color::srgb(0.55, 0.5, 0.45),
+    },
+    // pallas
+    Elements {
+        mass: 1.03e-10,
+        radius: 1.71e-6,
+        semi_major_axis: 2.7717,
+        eccentricity: 0.2313,
+        periapsis: 123.15,
+        mean_longitude: 116.05,
+        color: Color::srgb(0.55, 0.5, 0.45),
+    },
+    // vesta
+    Elements {
+        mass: 1.3e-10,
+        radius: 1.76e-6,
+        semi_major_axis: 2.3619,
+        eccentricity: 0.0895,
+        periapsis: 253.7,
+        mean_longitude: 234.7,
+        color: Color::srgb(0.55, 0.5, 0.45),
+    },
+];
+
+/// The biggest moons, with elements relative to their planet. The moon's phase is from its mean
+/// elements at J2000, jupiter's moons are placed from the low accuracy theory in chapter 44 of
+/// meeus (1998) and the others are only roughly right.
+const MOONS: [Moon; 7] = [
+    // the moon
+    Moon {
+        planet: 2,
+        retrograde: false,
+        elements: Elements {
+            mass: 3.6943e-8,
+            radius: 1.1614e-5,
+            semi_major_axis: 2.569_55e-3,
+            eccentricity: 0.0549,
+            periapsis: 83.353,
+            mean_longitude: 218.316,
+            color: Color::srgb(0.7, 0.7, 0.7),
+        },
+    },
+    // io
+    Moon {
+        planet: 4,
+        retrograde: false,
+        elements: Elements {
+            mass: 4.4917e-8,
+            radius: 1.2163e-5,
+            semi_major_axis: 2.8195e-3,
+            eccentricity: 0.0041,
+            periapsis: 0.0,
+            mean_longitude: 340.17,
+            color: Color::srgb(0.9, 0.85, 0.45),
+        },
+    },
+    // europa
+    Moon {
+        planet: 4,
+        retrograde: false,
+        elements: Elements {
+            mass: 2.4138e-8,
+            radius: 1.044e-5,
+            semi_major_axis: 4.486e-3,
+            eccentricity: 0.009,
+            periapsis: 0.0,
+            mean_longitude: 174.77,
+            color: Color::srgb(0.8, 0.75, 0.65),
+        },
+    },
+    // ganymede
+    Moon {
+        planet: 4,
+        retrograde: false,
+        elements: Elements {
+            mass: 7.4525e-8,
+            radius: 1.762e-5,
+            semi_major_axis: 7.1551e-3,
+            eccentricity: 0.0013,
+            periapsis: 0.0,
+            mean_longitude: 182.08,
+            color: Color::srgb(0.65, 0.6, 0.55),
+        },
+    },
+    // callisto
+    Moon {
+        planet: 4,
+        retrograde: false,
+        elements: Elements {
+            mass: 5.4107e-8,
+            radius: 1.6114e-5,
+            semi_major_axis: 1.2585e-2,
+            eccentricity: 0.0074,
+            periapsis: 0.0,
+            mean_longitude: 41.17,
+            color: Color::srgb(0.5, 0.45, 0.4),
+        },
+    },
+    // titan
+    Moon {
+        planet: 5,
+        retrograde: false,
+        elements: Elements {
+            mass: 6.7652e-8,
+            radius: 1.7213e-5,
+            semi_major_axis: 8.1677e-3,
+            eccentricity: 0.0288,
+            periapsis: 0.0,
+            mean_longitude: 261.16,
+            color: Color::srgb(0.85, 0.65, 0.35),
+        },
+    },
+    // triton
+    Moon {
+        planet: 7,
+        retrograde: true,
+        elements: Elements {
+            mass: 1.0758e-8,
+            radius: 9.0457e-6,
+            semi_major_axis: 2.3714e-3,
+            eccentricity: 0.0,
+            periapsis: 0.0,
+            mean_longitude: 0.0,
+            color: Color::srgb(0.75, 0.75, 0.8),
+        },
+    },
+];
+
+/// Position and velocity relative to the body being orbited, found by solving kepler's
+/// equation for the eccentric anomaly. `mu` is G times the mass of both bodies.
+fn state_vector(elements: &Elements, mu: f64, retrograde: bool) -> (DVec2, DVec2) {
+    let a = elements.semi_major_axis;
+    let e = elements.eccentricity;
+    let mean_anomaly = (elements.mean_longitude - elements.periapsis).to_radians();
+
+    let mut eccentric_anomaly = mean_anomaly + e * mean_anomaly.sin();
+    for _ in 0..20 {
+        let step = (eccentric_anomaly - e * eccentric_anomaly.sin() - mean_anomaly)
+            / (1.0 - e * eccentric_anomaly.cos());
+        eccentric_anomaly -= step;
+        if step.abs() < 1e-14 {
+            break;
+        }
+    }
+
+    let (sin, cos) = eccentric_anomaly.sin_cos();
+    let mean_motion = (mu / (a * a * a)).sqrt();
+    let minor = (1.0 - e * e).sqrt();
+    let mut position = DVec2::new(a * (cos - e), a * minor * sin);
+    let mut velocity = DVec2::new(-sin, minor * cos) * mean_motion * a / (1.0 - e * cos);
+    if retrograde {
+        position.y = -position.y;
+        velocity.y = -velocity.y;
+    }
+
+    let rotation = DVec2::from_angle(elements.periapsis.to_radians());
+    (rotation.rotate(position), rotation.rotate(velocity))
+}
+
+/// Spawns everything with the barycenter still at the origin, in [UnitSystem::Planetary]
+pub fn spawn(commands: &mut Commands) {
+    let g = UnitSystem::Planetary.gravitational_constant();
+    let mut bodies: Vec<(&Elements, DVec2, DVec2)> = Vec::new();
+
+    for (index, planet) in PLANETS.iter().enumerate() {
+        let moons: Vec<&Moon> = MOONS.iter().filter(|moon| moon.planet == index).collect();
+        let system_mass = planet.mass + moons.iter().map(|moon| moon.elements.mass).sum::<f64>();
+        let (center, center_velocity) = state_vector(planet, g * (SUN_MASS + system_mass), false);
+
+        // the planet's elements are for the barycenter of it and its moons
+        let relative: Vec<(&Moon, DVec2, DVec2)> = moons
+            .into_iter()
+            .map(|moon| {
+                let mu = g * (planet.mass + moon.elements.mass);
+                let (position, velocity) = state_vector(&moon.elements, mu, moon.retrograde);
+                (moon, position, velocity)
+            })
+            .collect();
+        let mut planet_position = center;
+        let mut planet_velocity = center_velocity;
+        for (moon, position, velocity) in &relative {
+            planet_position -= *position * moon.elements.mass / system_mass;
+            planet_velocity -= *velocity * moon.elements.mass / system_mass;
+        }
+        for (moon, position, velocity) in relative {
+            bodies.push((
+                &moon.elements,
+                planet_position + position,
+                planet_velocity + velocity,
+            ));
+        }
+        bodies.push((planet, planet_position, planet_velocity));
+    }
+
+    // the elements are heliocentric, so shift everything to keep the barycenter still
+    let total_mass = SUN_MASS + bodies.iter().map(|(body, ..)| body.mass).sum::<f64>();
+    let barycenter: DVec2 = bodies
+        .iter()
+        .map(|(body, r, _)| *r * body.mass)
+        .sum::<DVec2>()
+        / total_mass;
+    let drift: DVec2 = bodies
+        .iter()
+        .map(|(body, _, v)| *v * body.mass)
+        .sum::<DVec2>()
+        / total_mass;
+    body(
+        (-barycenter).as_vec2(),
+        (-drift).as_vec2(),
+        SUN_MASS as f32,
+        SUN_RADIUS as f32,
+    )
+    .tint(SUN_COLOR)
+    .marker(true)
+    .spawn(commands);
+
+    for (elements, position, velocity) in bodies {
+        body(
+            (position - barycenter).as_vec2(),
+            (velocity - drift).as_vec2(),
+            elements.mass as f32,
+            elements.radius as f32,
+        )
+        .tint(elements.color)
+        .marker(true)
+        .spawn(commands);
+    }
+}
+
+/// Gregorian date and time of day of a julian date as `yyyy-mm-dd hh:mm`, from chapter 7 of
+/// meeus (1998)
+pub fn calendar_date(julian_date: f64) -> String {
+    let shifted = julian_date + 0.5;
+    let whole = shifted.floor();
+    let fraction = shifted - whole;
+
+    let a = if whole < 2299161.0 {
+        whole
+    } else {
+        let alpha = ((whole - 1867216.25) / 36524.25).floor();
+        whole + 1.0 + alpha - (alpha / 4.0).floor()
+    };
+    let b = a + 1524.0;
+    let c = ((b - 122.1) / 365.25).floor();
+    let d = (365.25 * c).floor();
+    let e = ((b - d) / 30.6001).floor();
+
+    let day = b - d - (30.6001 * e).floor();
+    let month = if e < 14.0 { e - 1.0 } else { e - 13.0 };
+    let year = if month > 2.0 { c - 4716.0 } else { c - 4715.0 };
+    let minutes = (fraction * 1440.0).floor() as u32;
+
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}",
+        year as i64,
+        month as u32,
+        day as u32,
+        minutes / 60,
+        minutes % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{calendar_date, state_vector, J2000, PLANETS};
+    use crate::simulation::units::UnitSystem;
+
+    #[test]
+    fn test_calendar_date_matches_meeus() {
+        assert_eq!(calendar_date(J2000), "2000-01-01 12:00");
+        // sputnik 1, example 7.c
+        assert_eq!(calendar_date(2436116.31), "1957-10-04 19:26");
+        assert_eq!(calendar_date(2299160.5), "1582-10-15 00:00");
+    }
+
+    #[test]
+    fn test_earth_is_near_perihelion_at_j2000() {
+        let earth = &PLANETS[2];
+        let mu = UnitSystem::Planetary.gravitational_constant() * (1.0 + earth.mass);
+        let (position, velocity) = state_vector(earth, mu, false);
+
+        // perihelion is on the 3rd of january, at 0.9833 au
+        assert!((position.length() - 0.9833).abs() < 1e-3, "{position}");
+        // vis viva gives back the semi major axis
+        let semi_major_axis = 1.0 / (2.0 / position.length() - velocity.length_squared() / mu);
+        assert!((semi_major_axis - earth.semi_major_axis).abs() < 1e-9);
+    }
+}
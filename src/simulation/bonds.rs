@@ -0,0 +1,183 @@
+use crate::particle::{Mass, Particle};
+use crate::simulation::motion::{Acceleration, OldPosition};
+use bevy::prelude::*;
+
+use super::SimSettings;
+
+/// A damped spring between two particles, stored on its own entity
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Bond {
+    pub a: Entity,
+    pub b: Entity,
+    pub rest_length: f32,
+    pub stiffness: f32,
+    pub damping: f32,
+    /// If the bond is stretched or squashed by more than this fraction of its rest length it breaks
+    pub breaking_strain: Option<f32>,
+}
+
+impl Bond {
+    /// Create a new bond between particles `a` and `b`, call spawn to actually spawn it
+    pub fn new(a: Entity, b: Entity, rest_length: f32) -> Self {
+        Self {
+            a,
+            b,
+            rest_length,
+            stiffness: 10.0,
+            damping: 0.5,
+            breaking_strain: None,
+        }
+    }
+
+    /// How hard the bond pulls back towards its rest length
+    /// default: 10.0
+    pub fn stiffness(mut self, stiffness: f32) -> Self {
+        self.stiffness = stiffness;
+        self
+    }
+
+    /// How strongly the bond resists the particles moving apart or together
+    /// default: 0.5
+    pub fn damping(mut self, damping: f32) -> Self {
+        self.damping = damping;
+        self
+    }
+
+    /// Strain past which the bond breaks, `None` for an unbreakable bond
+    /// default: None
+    pub fn breaking_strain(mut self, breaking_strain: Option<f32>) -> Self {
+        self.breaking_strain = breaking_strain;
+        self
+    }
+
+    /// Spawn the bond
+    pub fn spawn(self, commands: &mut Commands) {
+        commands.spawn(self);
+    }
+
+    /// Strain of the bond when its ends are `length` apart
+    pub fn strain(&self, length: f32) -> f32 {
+        (length - self.rest_length) / self.rest_length.max(1e-6)
+    }
+}
+
+pub fn calc_bond_forces(
+    mut commands: Commands,
+    bonds: Query<(Entity, &Bond)>,
+    mut particles: Query<(&mut Acceleration, &Transform, &OldPosition, &Mass), With<Particle>>,
+    sim_settings: Res<SimSettings>,
+    time: Res<Time>,
+) {
+    let dt = time.delta_secs();
+    if dt == 0.0 {
+        return;
+    }
+
+    for (entity, bond) in bonds.iter() {
+        // if either end of the bond is gone the bond goes with it
+        let Ok(
+            [(mut accel_a, pos_a, old_pos_a, Mass(mass_a)), (mut accel_b, pos_b, old_pos_b, Mass(mass_b))],
+        ) = particles.get_many_mut([bond.a, bond.b])
+        else {
+            commands.entity(entity).despawn();
+            continue;
+        };
+
+        let delta = sim_settings
+            .periodic
+            .minimum_image((pos_b.translation - pos_a.translation).truncate());
+        let length = delta.length();
+        if length < 1e-10 {
+            continue;
+        }
+
+        if let Some(breaking_strain) = bond.breaking_strain {
+            if bond.strain(length).abs() > breaking_strain {
+                commands.entity(entity).despawn();
+                continue;
+            }
+        }
+
+        let direction = delta / length;
+        let velocity_a = (pos_a.translation - old_pos_a.0.translation).truncate() / dt;
+        let velocity_b = (pos_b.translation - old_pos_b.0.translation).truncate() / dt;
+        let closing_speed = (velocity_b - velocity_a).dot(direction);
+
+        let force = bond.stiffness * (length - bond.rest_length) + bond.damping * closing_speed;
+
+        if *mass_a != 0.0 {
+            accel_a.0 += direction * force / mass_a;
+        }
+        if *mass_b != 0.0 {
+            accel_b.0 -= direction * force / mass_b;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{calc_bond_forces, Bond};
+    use crate::particle::ParticleBundle;
+    use crate::simulation::motion::Acceleration;
+    use crate::simulation::SimSettings;
+    use bevy::prelude::*;
+    use std::time::Duration;
+
+    const DT: f32 = 0.01;
+
+    /// App with two particles 15 apart, the right one twice as heavy and moving away at 2 per
+    /// second, joined by `bond`
+    fn bonded_pair(bond: impl Fn(Entity, Entity) -> Bond) -> (App, Entity, Entity, Entity) {
+        let mut app = App::new();
+        app.init_resource::<SimSettings>()
+            .init_resource::<Time>()
+            .add_systems(Update, calc_bond_forces);
+        app.world_mut()
+            .resource_mut::<Time>()
+            .advance_by(Duration::from_secs_f32(DT));
+
+        let a = app.world_mut().spawn(ParticleBundle::new()).id();
+        let b = app
+            .world_mut()
+            .spawn(
+                ParticleBundle::new()
+                    .position(Vec2::new(15.0, 0.0))
+                    .velocity(Vec2::new(2.0 * DT, 0.0))
+                    .mass(2.0),
+            )
+            .id();
+        let bond = app.world_mut().spawn(bond(a, b)).id();
+        (app, a, b, bond)
+    }
+
+    #[test]
+    fn test_stretched_bond_pulls_ends_together() {
+        let (mut app, a, b, _) = bonded_pair(|a, b| Bond::new(a, b, 10.0));
+        app.update();
+
+        // stiffness * stretch + damping * speed apart = 10 * 5 + 0.5 * 2
+        let force = 51.0;
+        let acceleration = |entity| app.world().get::<Acceleration>(entity).unwrap().0;
+        assert!(acceleration(a).distance(Vec2::new(force, 0.0)) < 1e-3);
+        assert!(acceleration(b).distance(Vec2::new(-force / 2.0, 0.0)) < 1e-3);
+    }
+
+    #[test]
+    fn test_bond_breaks_past_breaking_strain() {
+        let (mut app, a, _, bond) =
+            bonded_pair(|a, b| Bond::new(a, b, 10.0).breaking_strain(Some(0.2)));
+        app.update();
+
+        assert!(app.world().get_entity(bond).is_err());
+        assert_eq!(app.world().get::<Acceleration>(a).unwrap().0, Vec2::ZERO);
+    }
+
+    #[test]
+    fn test_bond_goes_when_an_end_is_removed() {
+        let (mut app, _, b, bond) = bonded_pair(|a, b| Bond::new(a, b, 10.0));
+        app.world_mut().despawn(b);
+        app.update();
+
+        assert!(app.world().get_entity(bond).is_err());
+    }
+}
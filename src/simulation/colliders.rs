@@ -0,0 +1,423 @@
+use std::fmt::Display;
+
+use crate::particle::{Particle, Radius};
+use crate::simulation::motion::{Fixed, OldPosition};
+use bevy::prelude::*;
+
+/// Static geometry that particles bounce off. Every shape is a thin wall, so particles can be
+/// kept inside a shape as well as outside it
+#[derive(Clone, Debug, PartialEq)]
+pub enum ColliderShape {
+    Segment {
+        start: Vec2,
+        end: Vec2,
+    },
+    Rectangle {
+        center: Vec2,
+        half_size: Vec2,
+    },
+    Circle {
+        center: Vec2,
+        radius: f32,
+    },
+    /// Closed loop through every point
+    Polygon {
+        points: Vec<Vec2>,
+    },
+}
+
+impl ColliderShape {
+    /// Closest point on the wall to `position`
+    pub fn closest_point(&self, position: Vec2) -> Vec2 {
+        match self {
+            ColliderShape::Segment { start, end } => closest_on_segment(*start, *end, position),
+            ColliderShape::Rectangle { center, half_size } => {
+                let corners = rectangle_corners(*center, *half_size);
+                closest_on_loop(&corners, position)
+            }
+            ColliderShape::Circle { center, radius } => {
+                let offset = position - *center;
+                if offset.length_squared() < 1e-20 {
+                    return *center + Vec2::X * *radius;
+                }
+                *center + offset.normalize() * *radius
+            }
+            ColliderShape::Polygon { points } => closest_on_loop(points, position),
+        }
+    }
+
+    /// Where the path from `from` to `to` first goes through the wall, along with the wall
+    /// normal on the side of `from`
+    pub fn first_crossing(&self, from: Vec2, to: Vec2) -> Option<(Vec2, Vec2)> {
+        let crossing = match self {
+            ColliderShape::Segment { start, end } => segment_crossing(*start, *end, from, to),
+            ColliderShape::Rectangle { center, half_size } => {
+                let corners = rectangle_corners(*center, *half_size);
+                loop_crossing(&corners, from, to)
+            }
+            ColliderShape::Circle { center, radius } => circle_crossing(*center, *radius, from, to),
+            ColliderShape::Polygon { points } => loop_crossing(points, from, to),
+        };
+        crossing.map(|(_, point, normal)| (point, normal))
+    }
+
+    pub fn draw(&self, gizmos: &mut Gizmos, color: Color) {
+        match self {
+            ColliderShape::Segment { start, end } => gizmos.line_2d(*start, *end, color),
+            ColliderShape::Rectangle { center, half_size } => {
+                gizmos.rect_2d(*center, *half_size * 2.0, color)
+            }
+            ColliderShape::Circle { center, radius } => {
+                gizmos.circle_2d(*center, *radius, color);
+            }
+            ColliderShape::Polygon { points } => {
+                if let Some(first) = points.first() {
+                    gizmos.linestrip_2d(points.iter().copied().chain([*first]), color);
+                }
+            }
+        }
+    }
+}
+
+fn rectangle_corners(center: Vec2, half_size: Vec2) -> [Vec2; 4] {
+    [
+        center + Vec2::new(-half_size.x, -half_size.y),
+        center + Vec2::new(half_size.x, -half_size.y),
+        center + Vec2::new(half_size.x, half_size.y),
+        center + Vec2::new(-half_size.x, half_size.y),
+    ]
+}
+
+fn closest_on_segment(start: Vec2, end: Vec2, position: Vec2) -> Vec2 {
+    let line = end - start;
+    let length_sq = line.length_squared();
+    if length_sq < 1e-20 {
+        return start;
+    }
+    let t = ((position - start).dot(line) / length_sq).clamp(0.0, 1.0);
+    start + line * t
+}
+
+fn closest_on_loop(points: &[Vec2], position: Vec2) -> Vec2 {
+    let mut closest = points.first().copied().unwrap_or(position);
+    let mut closest_distance = f32::INFINITY;
+    for (i, start) in points.iter().enumerate() {
+        let end = points[(i + 1) % points.len()];
+        let point = closest_on_segment(*start, end, position);
+        let distance = point.distance_squared(position);
+        if distance < closest_distance {
+            closest = point;
+            closest_distance = distance;
+        }
+    }
+    closest
+}
+
+/// Fraction of the path along which it crosses the segment, the crossing point and the normal
+/// of the segment facing `from`
+fn segment_crossing(start: Vec2, end: Vec2, from: Vec2, to: Vec2) -> Option<(f32, Vec2, Vec2)> {
+    let path = to - from;
+    let line = end - start;
+    let denominator = path.perp_dot(line);
+    if denominator.abs() < 1e-20 {
+        return None;
+    }
+
+    let to_start = start - from;
+    let t = to_start.perp_dot(line) / denominator;
+    let s = to_start.perp_dot(path) / denominator;
+    if !(0.0..=1.0).contains(&t) || !(0.0..=1.0).contains(&s) {
+        return None;
+    }
+
+    // starting right on the wall counts as being on the side the particle is moving away from
+    let mut normal = line.perp().normalize();
+    let side = normal.dot(from - start);
+    if side < 0.0 || (side == 0.0 && normal.dot(path) > 0.0) {
+        normal = -normal;
+    }
+    Some((t, from + path * t, normal))
+}
+
+fn loop_crossing(points: &[Vec2], from: Vec2, to: Vec2) -> Option<(f32, Vec2, Vec2)> {
+    let mut first: Option<(f32, Vec2, Vec2)> = None;
+    for (i, start) in points.iter().enumerate() {
+        let end = points[(i + 1) % points.len()];
+        if let Some(crossing) = segment_crossing(*start, end, from, to) {
+            if first.is_none_or(|(t, _, _)| crossing.0 < t) {
+                first = Some(crossing);
+            }
+        }
+    }
+    first
+}
+
+fn circle_crossing(center: Vec2, radius: f32, from: Vec2, to: Vec2) -> Option<(f32, Vec2, Vec2)> {
+    // solve |from + path * t - center| = radius for the first t in 0..=1
+    let path = to - from;
+    let offset = from - center;
+    let a = path.length_squared();
+    if a < 1e-20 {
+        return None;
+    }
+    let b = 2.0 * offset.dot(path);
+    let c = offset.length_squared() - radius * radius;
+    let discriminant = b * b - 4.0 * a * c;
+    if discriminant < 0.0 {
+        return None;
+    }
+
+    let root = discriminant.sqrt();
+    let t = [(-b - root) / (2.0 * a), (-b + root) / (2.0 * a)]
+        .into_iter()
+        .find(|t| (0.0..=1.0).contains(t))?;
+
+    let point = from + path * t;
+    let outward = (point - center) / radius;
+    let normal = if c < 0.0 { -outward } else { outward };
+    Some((t, point, normal))
+}
+
+/// A static wall, stored on its own entity
+#[derive(Component, Clone, Debug)]
+pub struct Collider {
+    pub shape: ColliderShape,
+    /// Fraction of the speed into the wall a particle keeps after bouncing off
+    pub restitution: f32,
+}
+
+impl Collider {
+    pub fn new(shape: ColliderShape) -> Self {
+        Self {
+            shape,
+            restitution: 0.5,
+        }
+    }
+
+    /// How bouncy the wall is, 0 stops particles dead and 1 bounces them back at full speed
+    /// default: 0.5
+    pub fn restitution(mut self, restitution: f32) -> Self {
+        self.restitution = restitution;
+        self
+    }
+
+    /// Spawn the collider
+    pub fn spawn(self, commands: &mut Commands) {
+        commands.spawn(self);
+    }
+}
+
+/// Ready made containers built out of colliders
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum ContainerPreset {
+    /// A box with no lid
+    OpenBox,
+    ClosedBox,
+    /// Two funnels meeting at a narrow neck
+    Hourglass,
+}
+
+impl ContainerPreset {
+    /// The walls of the container centered on `center`, `size` is its height
+    pub fn shapes(&self, center: Vec2, size: f32) -> Vec<ColliderShape> {
+        let half = size / 2.0;
+        match self {
+            ContainerPreset::OpenBox => {
+                let top_left = center + Vec2::new(-half, half);
+                let bottom_left = center + Vec2::new(-half, -half);
+                let bottom_right = center + Vec2::new(half, -half);
+                let top_right = center + Vec2::new(half, half);
+                vec![
+                    ColliderShape::Segment {
+                        start: top_left,
+                        end: bottom_left,
+                    },
+                    ColliderShape::Segment {
+                        start: bottom_left,
+                        end: bottom_right,
+                    },
+                    ColliderShape::Segment {
+                        start: bottom_right,
+                        end: top_right,
+                    },
+                ]
+            }
+            ContainerPreset::ClosedBox => vec![ColliderShape::Rectangle {
+                center,
+                half_size: Vec2::splat(half),
+            }],
+            ContainerPreset::Hourglass => {
+                let neck = size * 0.04;
+                let width = half * 0.6;
+                vec![ColliderShape::Polygon {
+                    points: vec![
+                        center + Vec2::new(-width, half),
+                        center + Vec2::new(width, half),
+                        center + Vec2::new(neck, 0.0),
+                        center + Vec2::new(width, -half),
+                        center + Vec2::new(-width, -half),
+                        center + Vec2::new(-neck, 0.0),
+                    ],
+                }]
+            }
+        }
+    }
+}
+
+impl Display for ContainerPreset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ContainerPreset::OpenBox => write!(f, "open box"),
+            ContainerPreset::ClosedBox => write!(f, "closed box"),
+            ContainerPreset::Hourglass => write!(f, "hourglass"),
+        }
+    }
+}
+
+pub fn collide_with_colliders(
+    colliders: Query<&Collider>,
+    mut particles: Query<
+        (&mut Transform, &mut OldPosition, &Radius),
+        (With<Particle>, Without<Fixed>),
+    >,
+) {
+    if colliders.is_empty() {
+        return;
+    }
+
+    particles
+        .par_iter_mut()
+        .for_each(|(mut position, mut old_position, Radius(radius))| {
+            for collider in colliders.iter() {
+                let pos = position.translation.truncate();
+                let old_pos = old_position.0.translation.truncate();
+
+                // walls are thin, so a fast particle can go straight through one in a single step
+                let (new_pos, normal) = match collider.shape.first_crossing(old_pos, pos) {
+                    // put it back on the side it came from
+                    Some((point, normal)) => (point + normal * *radius, normal),
+                    None => {
+                        let closest = collider.shape.closest_point(pos);
+                        let offset = pos - closest;
+                        let distance = offset.length();
+                        if distance >= *radius || distance < 1e-10 {
+                            continue;
+                        }
+                        // push the particle back out of the wall
+                        let normal = offset / distance;
+                        (closest + normal * *radius, normal)
+                    }
+                };
+
+                // then reflect the part of its velocity going into the wall
+                let mut velocity = pos - old_pos;
+                let into_wall = velocity.dot(normal);
+                if into_wall < 0.0 {
+                    velocity -= (1.0 + collider.restitution) * into_wall * normal;
+                }
+
+                position.translation = new_pos.extend(position.translation.z);
+                old_position.0.translation = (new_pos - velocity).extend(0.0);
+            }
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{collide_with_colliders, Collider, ColliderShape, ContainerPreset};
+    use crate::particle::{Particle, Radius};
+    use crate::simulation::motion::OldPosition;
+    use bevy::prelude::*;
+
+    /// Runs one collision pass on a single particle moving from `old` to `new`, returning where
+    /// it ends up and its velocity afterwards
+    fn collide(shapes: Vec<ColliderShape>, old: Vec2, new: Vec2, radius: f32) -> (Vec2, Vec2) {
+        let mut app = App::new();
+        app.add_systems(Update, collide_with_colliders);
+        for shape in shapes {
+            app.world_mut().spawn(Collider::new(shape));
+        }
+        let particle = app
+            .world_mut()
+            .spawn((
+                Particle,
+                Radius(radius),
+                Transform::from_translation(new.extend(0.0)),
+                OldPosition(Transform::from_translation(old.extend(0.0))),
+            ))
+            .id();
+
+        app.update();
+
+        let position = app.world().get::<Transform>(particle).unwrap().translation;
+        let old_position = app
+            .world()
+            .get::<OldPosition>(particle)
+            .unwrap()
+            .0
+            .translation;
+        (position.truncate(), (position - old_position).truncate())
+    }
+
+    #[test]
+    fn test_particle_falling_down_hourglass_centreline_stays_inside() {
+        let shapes = ContainerPreset::Hourglass.shapes(Vec2::ZERO, 100.0);
+
+        // crossing the middle of the funnel, well away from both walls
+        let (position, _) = collide(
+            shapes.clone(),
+            Vec2::new(-0.3, 25.0),
+            Vec2::new(0.3, 24.9),
+            1.0,
+        );
+        assert!(
+            position.distance(Vec2::new(0.3, 24.9)) < 1e-5,
+            "moved to {position}"
+        );
+
+        // and falling the whole way through the neck
+        let mut old = Vec2::new(0.2, 45.0);
+        for step in 1..=90 {
+            let new = Vec2::new(if step % 2 == 0 { 0.2 } else { -0.2 }, 45.0 - step as f32);
+            let (position, _) = collide(shapes.clone(), old, new, 1.0);
+            assert!(
+                position.distance(new) < 1e-5,
+                "step {step} moved to {position}"
+            );
+            old = position;
+        }
+    }
+
+    #[test]
+    fn test_fast_particle_cannot_tunnel_through_segment() {
+        let wall = ColliderShape::Segment {
+            start: Vec2::new(-10.0, 0.0),
+            end: Vec2::new(10.0, 0.0),
+        };
+
+        let (position, velocity) =
+            collide(vec![wall], Vec2::new(0.0, 5.0), Vec2::new(0.0, -5.0), 1.0);
+        assert!(
+            (position - Vec2::new(0.0, 1.0)).length() < 1e-5,
+            "ended at {position}"
+        );
+        // the default restitution keeps half the speed
+        assert!(
+            (velocity - Vec2::new(0.0, 5.0)).length() < 1e-5,
+            "moving at {velocity}"
+        );
+    }
+
+    #[test]
+    fn test_particle_resting_against_wall_is_pushed_along_normal() {
+        let walls = ContainerPreset::ClosedBox.shapes(Vec2::ZERO, 20.0);
+
+        let start = Vec2::new(9.5, 3.0);
+        let (position, velocity) = collide(walls, start, start, 1.0);
+        assert!(
+            (position - Vec2::new(9.0, 3.0)).length() < 1e-5,
+            "ended at {position}"
+        );
+        assert!(velocity.length() < 1e-5);
+    }
+}
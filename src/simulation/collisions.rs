@@ -1,10 +1,17 @@
-use crate::particle::{Mass, Particle, Radius};
+use crate::particle::{Mass, Particle, Radius, TestParticle};
 use bevy::prelude::*;
 
+use super::motion::Fixed;
+use super::sph::Gas;
 use super::SimSettings;
 
+/// Gas particles are left out, their pressure already keeps them apart, and so are test particles.
+/// [Fixed] particles push others out of the way without being moved themselves
 pub fn calculate_collisions(
-    mut particles: Query<(&mut Transform, &Radius, &Mass), With<Particle>>,
+    mut particles: Query<
+        (&mut Transform, &Radius, &Mass, Has<Fixed>),
+        (With<Particle>, Without<Gas>, Without<TestParticle>),
+    >,
     sim_settings: Res<SimSettings>,
 ) {
     if !sim_settings.enable_collisions {
@@ -13,13 +20,17 @@ pub fn calculate_collisions(
     for _ in 0..sim_settings.collision_steps {
         let mut iter = particles.iter_combinations_mut();
         while let Some(
-            [(mut position1, Radius(radius1), Mass(mass1)), (mut position2, Radius(radius2), Mass(mass2))],
+            [(mut position1, Radius(radius1), Mass(mass1), fixed1), (mut position2, Radius(radius2), Mass(mass2), fixed2)],
         ) = iter.fetch_next()
         {
+            if fixed1 && fixed2 {
+                continue;
+            }
+
             let pos1 = position1.translation.xy();
             let pos2 = position2.translation.xy();
 
-            let distance = pos1 - pos2;
+            let distance = sim_settings.periodic.minimum_image(pos1 - pos2);
             let distance_length = distance.length();
 
             if distance_length < radius1 + radius2 {
@@ -27,8 +38,14 @@ pub fn calculate_collisions(
                 let collision_normal = distance.normalize();
                 let move_distance = overlap / 2.0;
                 let correction = collision_normal * move_distance;
-                let correction2 = (mass1 / (mass1 + mass2)) * correction;
-                let correction1 = (mass2 / (mass2 + mass1)) * correction;
+                let (correction1, correction2) = match (fixed1, fixed2) {
+                    (true, _) => (Vec2::ZERO, correction),
+                    (_, true) => (correction, Vec2::ZERO),
+                    _ => (
+                        (mass2 / (mass2 + mass1)) * correction,
+                        (mass1 / (mass1 + mass2)) * correction,
+                    ),
+                };
                 position1.translation += correction1.extend(0.0);
                 position2.translation -= correction2.extend(0.0);
             }
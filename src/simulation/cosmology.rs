@@ -0,0 +1,317 @@
+use std::fmt::Display;
+
+use crate::particle::Particle;
+use crate::simulation::motion::{Acceleration, Fixed, OldPosition};
+use bevy::prelude::*;
+
+use super::SimSettings;
+
+/// Background universe the scale factor follows
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum FriedmannModel {
+    /// Flat and matter only, `a` grows as `t^(2/3)`
+    EinsteinDeSitter,
+    /// Matter, a cosmological constant and whatever curvature is left over
+    LambdaCdm,
+}
+
+impl Display for FriedmannModel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FriedmannModel::EinsteinDeSitter => write!(f, "einstein-de sitter"),
+            FriedmannModel::LambdaCdm => write!(f, "ΛCDM"),
+        }
+    }
+}
+
+/// When enabled particle positions are comoving, gravity is weakened by `1/a^3` and particles
+/// feel hubble drag as the universe expands
+#[derive(Clone, Copy, Debug)]
+pub struct CosmologySettings {
+    pub enabled: bool,
+    pub model: FriedmannModel,
+    /// Hubble constant today (at `a = 1`), per second of simulation time
+    pub hubble_constant: f32,
+    pub omega_matter: f32,
+    pub omega_lambda: f32,
+    /// Scale factor the simulation starts at when the expansion is reset
+    pub initial_scale_factor: f32,
+}
+
+impl Default for CosmologySettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            model: FriedmannModel::EinsteinDeSitter,
+            hubble_constant: 0.1,
+            omega_matter: 0.3,
+            omega_lambda: 0.7,
+            initial_scale_factor: 0.02,
+        }
+    }
+}
+
+impl CosmologySettings {
+    /// Density parameters `(matter, lambda)` of the selected model
+    pub fn omegas(&self) -> (f32, f32) {
+        match self.model {
+            FriedmannModel::EinsteinDeSitter => (1.0, 0.0),
+            FriedmannModel::LambdaCdm => (self.omega_matter, self.omega_lambda),
+        }
+    }
+
+    /// Hubble parameter at scale factor `a`, from the friedmann equation
+    pub fn hubble(&self, a: f32) -> f32 {
+        let (omega_matter, omega_lambda) = self.omegas();
+        let omega_curvature = 1.0 - omega_matter - omega_lambda;
+        let e_squared = omega_matter / (a * a * a) + omega_curvature / (a * a) + omega_lambda;
+        self.hubble_constant * e_squared.max(0.0).sqrt()
+    }
+
+    /// `H f` at scale factor `a`, how fast linear density perturbations grow, using the
+    /// `f = omega_matter(a)^0.55` fit for the logarithmic growth rate
+    pub fn growth_rate(&self, a: f32) -> f32 {
+        let hubble = self.hubble(a);
+        if hubble == 0.0 {
+            return 0.0;
+        }
+        let (omega_matter, _) = self.omegas();
+        let e_squared = (hubble / self.hubble_constant).powi(2);
+        let omega_matter_now = omega_matter / (a * a * a * e_squared);
+        hubble * omega_matter_now.max(0.0).powf(0.55)
+    }
+}
+
+/// Current state of the expanding background
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct Expansion {
+    pub scale_factor: f32,
+    /// Simulation time since the expansion was last reset
+    pub time: f32,
+}
+
+impl Default for Expansion {
+    fn default() -> Self {
+        Self {
+            scale_factor: CosmologySettings::default().initial_scale_factor,
+            time: 0.0,
+        }
+    }
+}
+
+impl Expansion {
+    pub fn redshift(&self) -> f32 {
+        1.0 / self.scale_factor - 1.0
+    }
+}
+
+pub fn advance_scale_factor(
+    mut expansion: ResMut<Expansion>,
+    sim_settings: Res<SimSettings>,
+    time: Res<Time>,
+) {
+    let settings = sim_settings.cosmology;
+    if !settings.enabled {
+        return;
+    }
+
+    // midpoint step of da/dt = a * H(a)
+    let dt = time.delta_secs();
+    let a = expansion.scale_factor;
+    let half = a + 0.5 * dt * a * settings.hubble(a);
+    expansion.scale_factor = a + dt * half * settings.hubble(half);
+    expansion.time += dt;
+}
+
+/// Comoving particles should only feel the density contrast, and the uniform background only
+/// gets taken away inside the periodic box, so it is always on while cosmology is
+pub fn require_periodic_box(mut sim_settings: ResMut<SimSettings>) {
+    sim_settings.periodic.enabled = true;
+}
+
+/// Run condition that is true when cosmology is on without the periodic box
+pub fn periodic_box_missing(sim_settings: Res<SimSettings>) -> bool {
+    sim_settings.cosmology.enabled && !sim_settings.periodic.enabled
+}
+
+/// Turns the plain gravity [super::gravity::calc_grav_accel] left in [Acceleration] into the
+/// comoving equation of motion `x'' = g / a^3 - 2 H x'`, so it has to run straight after it and
+/// before anything else adds to the acceleration
+pub fn comoving_gravity(
+    mut particles: Query<
+        (&mut Acceleration, &Transform, &OldPosition),
+        (With<Particle>, Without<Fixed>),
+    >,
+    expansion: Res<Expansion>,
+    sim_settings: Res<SimSettings>,
+    time: Res<Time>,
+) {
+    let settings = sim_settings.cosmology;
+    let dt = time.delta_secs();
+    if !settings.enabled || dt == 0.0 {
+        return;
+    }
+
+    let a = expansion.scale_factor;
+    let gravity_scale = 1.0 / (a * a * a);
+    let hubble = settings.hubble(a);
+
+    particles
+        .par_iter_mut()
+        .for_each(|(mut acceleration, position, old_position)| {
+            let velocity = (position.translation - old_position.0.translation).truncate() / dt;
+            acceleration.0 = acceleration.0 * gravity_scale - 2.0 * hubble * velocity;
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        advance_scale_factor, comoving_gravity, periodic_box_missing, require_periodic_box,
+        Expansion, FriedmannModel,
+    };
+    use crate::particle::{Particle, ParticleBundle};
+    use crate::simulation::gravity::calc_grav_accel;
+    use crate::simulation::motion::{update_particle_positions, Acceleration};
+    use crate::simulation::periodic::wrap_particles;
+    use crate::simulation::SimSettings;
+    use bevy::prelude::*;
+    use std::time::Duration;
+
+    fn settings() -> SimSettings {
+        let mut settings = SimSettings::default();
+        settings.cosmology.enabled = true;
+        settings.cosmology.model = FriedmannModel::EinsteinDeSitter;
+        settings.cosmology.hubble_constant = 0.1;
+        settings
+    }
+
+    #[test]
+    fn test_einstein_de_sitter_scale_factor_grows_as_t_two_thirds() {
+        let mut app = App::new();
+        app.insert_resource(settings())
+            .insert_resource(Expansion {
+                scale_factor: 1.0,
+                time: 0.0,
+            })
+            .init_resource::<Time>()
+            .add_systems(Update, advance_scale_factor);
+
+        // a = (3/2 H0 t + 1)^(2/3) when it starts from a = 1
+        let dt = 0.01;
+        for step in 1..=1000 {
+            app.world_mut()
+                .resource_mut::<Time>()
+                .advance_by(Duration::from_secs_f32(dt));
+            app.update();
+
+            let expansion = app.world().resource::<Expansion>();
+            let expected = (1.5 * 0.1 * step as f32 * dt + 1.0).powf(2.0 / 3.0);
+            assert!(
+                (expansion.scale_factor - expected).abs() < 1e-4 * expected,
+                "a = {} at t = {}, expected {expected}",
+                expansion.scale_factor,
+                expansion.time
+            );
+        }
+    }
+
+    #[test]
+    fn test_comoving_gravity_adds_hubble_drag() {
+        let settings = settings();
+        let scale_factor = 2.0;
+        let hubble = settings.cosmology.hubble(scale_factor);
+
+        let mut app = App::new();
+        app.insert_resource(settings)
+            .insert_resource(Expansion {
+                scale_factor,
+                time: 0.0,
+            })
+            .init_resource::<Time>()
+            .add_systems(Update, comoving_gravity);
+        let dt = 0.01;
+        app.world_mut()
+            .resource_mut::<Time>()
+            .advance_by(Duration::from_secs_f32(dt));
+
+        // moving at 5 per second with gravity of 8 already added
+        let velocity = Vec2::new(5.0, 0.0);
+        let particle = app
+            .world_mut()
+            .spawn(ParticleBundle::new().velocity(velocity * dt))
+            .id();
+        app.world_mut().get_mut::<Acceleration>(particle).unwrap().0 = Vec2::new(0.0, -8.0);
+        app.update();
+
+        let acceleration = app.world().get::<Acceleration>(particle).unwrap().0;
+        let expected = Vec2::new(-2.0 * hubble * 5.0, -1.0);
+        assert!(
+            acceleration.distance(expected) < 1e-4,
+            "{acceleration}, expected {expected}"
+        );
+    }
+
+    #[test]
+    fn test_uniform_lattice_stays_still() {
+        let mut settings = settings();
+        settings.periodic.enabled = false;
+        settings.periodic.size = 100.0;
+
+        let mut app = App::new();
+        app.insert_resource(settings)
+            .insert_resource(Expansion {
+                scale_factor: 1.0,
+                time: 0.0,
+            })
+            .init_resource::<Time>()
+            .add_systems(
+                Update,
+                (
+                    require_periodic_box.run_if(periodic_box_missing),
+                    update_particle_positions,
+                    wrap_particles,
+                    calc_grav_accel,
+                    comoving_gravity,
+                    advance_scale_factor,
+                )
+                    .chain(),
+            );
+
+        // one particle in the middle of every cell, so the density is the mean density
+        let (count, spacing) = (4, 25.0);
+        let mut lattice = Vec::new();
+        for x in 0..count {
+            for y in 0..count {
+                let position = (Vec2::new(x as f32, y as f32) + 0.5) * spacing - 50.0;
+                lattice.push(position);
+                app.world_mut()
+                    .spawn(ParticleBundle::new().position(position).mass(10.0));
+            }
+        }
+
+        let dt = 0.01;
+        for _ in 0..200 {
+            app.world_mut()
+                .resource_mut::<Time>()
+                .advance_by(Duration::from_secs_f32(dt));
+            app.update();
+        }
+
+        assert!(app.world().resource::<SimSettings>().periodic.enabled);
+        let mut particles = app
+            .world_mut()
+            .query_filtered::<&Transform, With<Particle>>();
+        for transform in particles.iter(app.world()) {
+            let position = transform.translation.truncate();
+            let nearest = lattice
+                .iter()
+                .map(|site| site.distance(position))
+                .fold(f32::INFINITY, f32::min);
+            assert!(
+                nearest < 1e-3,
+                "particle at {position} has left the lattice"
+            );
+        }
+    }
+}
@@ -0,0 +1,565 @@
+use std::collections::HashMap;
+
+use crate::particle::{Mass, Particle, Radius, TestParticle};
+use crate::simulation::fft::Complex;
+use crate::simulation::motion::Acceleration;
+use bevy::prelude::*;
+
+use super::SimSettings;
+
+/// Levels below this aren't made however many particles there are, it keeps clustered
+/// distributions from building a tree that is mostly empty boxes
+const MAX_DEPTH: usize = 12;
+
+#[derive(Clone, Copy, Debug)]
+pub struct MultipoleSettings {
+    /// Highest power kept in the expansions, the error falls off roughly geometrically with it
+    pub order: usize,
+    /// Average amount of particles the smallest boxes are aimed to hold
+    pub leaf_size: usize,
+}
+
+impl Default for MultipoleSettings {
+    fn default() -> Self {
+        Self {
+            order: 8,
+            leaf_size: 16,
+        }
+    }
+}
+
+/// Numbers used over and over by the expansions, all up to `order`
+struct Coefficients {
+    order: usize,
+    /// `(1 - x)^(-1/2) = sum a_k x^k`
+    inverse_sqrt: Vec<f32>,
+    /// `binomial[n * (order + 1) + k]` is n choose k
+    binomial: Vec<f32>,
+    /// `shifted[k * (order + 1) + n]` is the generalised binomial (-1/2 - k) choose n
+    shifted: Vec<f32>,
+}
+
+impl Coefficients {
+    fn new(order: usize) -> Self {
+        let terms = order + 1;
+        let mut inverse_sqrt = vec![1.0; terms];
+        for k in 1..terms {
+            inverse_sqrt[k] = inverse_sqrt[k - 1] * (2 * k - 1) as f32 / (2 * k) as f32;
+        }
+
+        let mut binomial = vec![0.0; terms * terms];
+        for n in 0..terms {
+            binomial[n * terms] = 1.0;
+            for k in 1..=n {
+                binomial[n * terms + k] =
+                    binomial[(n - 1) * terms + k - 1] + binomial[(n - 1) * terms + k];
+            }
+        }
+
+        let mut shifted = vec![0.0; terms * terms];
+        for k in 0..terms {
+            let exponent = -0.5 - k as f32;
+            shifted[k * terms] = 1.0;
+            for n in 1..terms {
+                shifted[k * terms + n] =
+                    shifted[k * terms + n - 1] * (exponent - (n - 1) as f32) / n as f32;
+            }
+        }
+
+        Self {
+            order,
+            inverse_sqrt,
+            binomial,
+            shifted,
+        }
+    }
+
+    fn terms(&self) -> usize {
+        self.order + 1
+    }
+
+    fn binomial(&self, n: usize, k: usize) -> f32 {
+        self.binomial[n * self.terms() + k]
+    }
+
+    fn shifted(&self, k: usize, n: usize) -> f32 {
+        self.shifted[k * self.terms() + n]
+    }
+}
+
+/// `[1, z, z^2, ...]`, `count` long
+fn powers(z: Complex, count: usize) -> Vec<Complex> {
+    let mut powers = Vec::with_capacity(count);
+    let mut power = Complex::ONE;
+    for _ in 0..count {
+        powers.push(power);
+        power = power * z;
+    }
+    powers
+}
+
+/// Every box on one level of the tree that has a particle in it
+struct Level {
+    width: f32,
+    keys: Vec<IVec2>,
+    slots: HashMap<IVec2, usize>,
+    multipoles: Vec<Complex>,
+    locals: Vec<Complex>,
+}
+
+impl Level {
+    fn new(width: f32, keys: impl IntoIterator<Item = IVec2>, terms: usize) -> Self {
+        let mut level = Self {
+            width,
+            keys: Vec::new(),
+            slots: HashMap::new(),
+            multipoles: Vec::new(),
+            locals: Vec::new(),
+        };
+        for key in keys {
+            level.slots.entry(key).or_insert_with(|| {
+                level.keys.push(key);
+                level.keys.len() - 1
+            });
+        }
+        level.multipoles = vec![Complex::ZERO; level.keys.len() * terms * terms];
+        level.locals = vec![Complex::ZERO; level.keys.len() * terms * terms];
+        level
+    }
+}
+
+/// Fast multipole method for the plain `1/r` gravity of [super::gravity::calc_grav_accel].
+///
+/// The potential of a source at `s` seen from `z` is expanded as a double series in `z` and its
+/// conjugate, `1/|z - s| = (1/|z|) sum a_k a_l s^k conj(s)^l z^-k conj(z)^-l`. Each box of the
+/// quadtree holds the moments of this for the particles inside it, and a local expansion of the
+/// potential from everything that isn't next to it. Particles in neighbouring leaves are summed
+/// over directly with the same softening as the direct solver.
+///
+/// Expansions are stored scaled by the width of their box so the powers stay near one.
+pub struct MultipoleTree {
+    coefficients: Coefficients,
+    /// Corner of the root box with the lowest coordinates
+    origin: Vec2,
+    levels: Vec<Level>,
+    /// Position, mass and radius of the sources in every leaf, by slot
+    leaf_sources: Vec<Vec<(Vec2, f32, f32)>>,
+}
+
+impl MultipoleTree {
+    /// Builds the tree for `sources`, each a position, mass and radius. Only positions in
+    /// `targets` can have their acceleration found afterwards.
+    pub fn new(
+        sources: &[(Vec2, f32, f32)],
+        targets: &[Vec2],
+        settings: &MultipoleSettings,
+    ) -> Self {
+        let coefficients = Coefficients::new(settings.order);
+        let terms = coefficients.terms();
+
+        let mut min = Vec2::splat(f32::INFINITY);
+        let mut max = Vec2::splat(f32::NEG_INFINITY);
+        for position in sources
+            .iter()
+            .map(|(position, _, _)| position)
+            .chain(targets)
+        {
+            min = min.min(*position);
+            max = max.max(*position);
+        }
+        if !min.is_finite() {
+            min = Vec2::ZERO;
+            max = Vec2::ZERO;
+        }
+        let size = (max - min).max_element().max(1.0) * 1.001;
+
+        // expansions only get used from level 2 down, where boxes start having far neighbours
+        let mut depth = 2;
+        while depth < MAX_DEPTH
+            && 4usize.pow(depth as u32) * settings.leaf_size.max(1) < sources.len()
+        {
+            depth += 1;
+        }
+
+        let leaf_width = size / (1 << depth) as f32;
+        let leaf_key = |position: Vec2| {
+            ((position - min) / leaf_width)
+                .floor()
+                .as_ivec2()
+                .clamp(IVec2::ZERO, IVec2::splat((1 << depth) - 1))
+        };
+
+        let mut levels = Vec::with_capacity(depth + 1);
+        levels.push(Level::new(
+            leaf_width,
+            sources
+                .iter()
+                .map(|(position, _, _)| leaf_key(*position))
+                .chain(targets.iter().map(|position| leaf_key(*position))),
+            terms,
+        ));
+        for _ in 0..depth {
+            let child = levels.last().unwrap();
+            let parent = Level::new(
+                child.width * 2.0,
+                child.keys.iter().map(|key| *key >> 1),
+                terms,
+            );
+            levels.push(parent);
+        }
+        levels.reverse();
+
+        let mut tree = Self {
+            coefficients,
+            origin: min,
+            levels,
+            leaf_sources: Vec::new(),
+        };
+
+        let leaves = &tree.levels[depth];
+        let mut leaf_sources = vec![Vec::new(); leaves.keys.len()];
+        for source in sources {
+            leaf_sources[leaves.slots[&leaf_key(source.0)]].push(*source);
+        }
+        tree.leaf_sources = leaf_sources;
+
+        tree.particles_to_multipoles();
+        tree.multipoles_upward();
+        tree.multipoles_to_locals();
+        tree.locals_downward();
+        tree
+    }
+
+    fn depth(&self) -> usize {
+        self.levels.len() - 1
+    }
+
+    fn center(&self, level: usize, key: IVec2) -> Vec2 {
+        self.origin + (key.as_vec2() + Vec2::splat(0.5)) * self.levels[level].width
+    }
+
+    fn particles_to_multipoles(&mut self) {
+        let terms = self.coefficients.terms();
+        let depth = self.depth();
+        for slot in 0..self.leaf_sources.len() {
+            let center = self.center(depth, self.levels[depth].keys[slot]);
+            let width = self.levels[depth].width;
+            let multipole =
+                &mut self.levels[depth].multipoles[slot * terms * terms..][..terms * terms];
+            for (position, mass, _) in &self.leaf_sources[slot] {
+                let offset = (*position - center) / width;
+                let s = powers(Complex::new(offset.x, offset.y), terms);
+                for k in 0..terms {
+                    for l in 0..terms {
+                        multipole[k * terms + l] += (s[k] * s[l].conj()).scale(*mass);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Shift every box's moments to its parent's center and add them up
+    fn multipoles_upward(&mut self) {
+        let terms = self.coefficients.terms();
+        for level in (1..=self.depth()).rev() {
+            for slot in 0..self.levels[level].keys.len() {
+                let key = self.levels[level].keys[slot];
+                let parent_key = key >> 1;
+                let parent_slot = self.levels[level - 1].slots[&parent_key];
+                let offset = (self.center(level, key) - self.center(level - 1, parent_key))
+                    / self.levels[level - 1].width;
+                let d = powers(Complex::new(offset.x, offset.y), terms);
+
+                let child = &self.levels[level].multipoles[slot * terms * terms..][..terms * terms];
+                let mut shifted = vec![Complex::ZERO; terms * terms];
+                // sum over the child's powers of s first, then its powers of conj(s)
+                let mut partial = vec![Complex::ZERO; terms * terms];
+                for k in 0..terms {
+                    for j in 0..terms {
+                        for i in 0..=k {
+                            let scale =
+                                self.coefficients.binomial(k, i) * 0.5f32.powi((i + j) as i32);
+                            partial[k * terms + j] +=
+                                (d[k - i] * child[i * terms + j]).scale(scale);
+                        }
+                    }
+                }
+                for k in 0..terms {
+                    for l in 0..terms {
+                        for j in 0..=l {
+                            shifted[k * terms + l] += (d[l - j].conj() * partial[k * terms + j])
+                                .scale(self.coefficients.binomial(l, j));
+                        }
+                    }
+                }
+
+                let parent = &mut self.levels[level - 1].multipoles[parent_slot * terms * terms..]
+                    [..terms * terms];
+                for (parent, shifted) in parent.iter_mut().zip(shifted) {
+                    *parent += shifted;
+                }
+            }
+        }
+    }
+
+    /// Convert the moments of every box in each box's interaction list, the children of its
+    /// parent's neighbours that aren't its own neighbours, into local expansions
+    fn multipoles_to_locals(&mut self) {
+        let terms = self.coefficients.terms();
+        let coefficients = &self.coefficients;
+        for level in 2..=self.depth() {
+            let width = self.levels[level].width;
+            let Level {
+                keys,
+                slots,
+                multipoles,
+                locals,
+                ..
+            } = &mut self.levels[level];
+
+            for (slot, key) in keys.iter().enumerate() {
+                let local = &mut locals[slot * terms * terms..][..terms * terms];
+                let parent = *key >> 1;
+                for neighbour in neighbours(parent) {
+                    for child in neighbours(neighbour * 2 + IVec2::ONE) {
+                        if child >> 1 != neighbour || (child - *key).abs().max_element() <= 1 {
+                            continue;
+                        }
+                        let Some(source_slot) = slots.get(&child) else {
+                            continue;
+                        };
+                        let multipole = &multipoles[source_slot * terms * terms..][..terms * terms];
+                        let offset = (*key - child).as_vec2();
+                        multipole_to_local(coefficients, multipole, offset, width, local);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Shift every box's local expansion to its children's centers
+    fn locals_downward(&mut self) {
+        let terms = self.coefficients.terms();
+        for level in 3..=self.depth() {
+            for slot in 0..self.levels[level].keys.len() {
+                let key = self.levels[level].keys[slot];
+                let parent_key = key >> 1;
+                let parent_slot = self.levels[level - 1].slots[&parent_key];
+                let offset = (self.center(level, key) - self.center(level - 1, parent_key))
+                    / self.levels[level - 1].width;
+                let e = powers(Complex::new(offset.x, offset.y), terms);
+
+                let parent =
+                    &self.levels[level - 1].locals[parent_slot * terms * terms..][..terms * terms];
+                let mut partial = vec![Complex::ZERO; terms * terms];
+                for n in 0..terms {
+                    for q in 0..terms {
+                        for m in n..terms {
+                            partial[n * terms + q] += (e[m - n] * parent[m * terms + q])
+                                .scale(self.coefficients.binomial(m, n));
+                        }
+                    }
+                }
+                let mut shifted = vec![Complex::ZERO; terms * terms];
+                for n in 0..terms {
+                    for q in 0..terms {
+                        let mut sum = Complex::ZERO;
+                        for r in q..terms {
+                            sum += (e[r - q].conj() * partial[n * terms + r])
+                                .scale(self.coefficients.binomial(r, q));
+                        }
+                        shifted[n * terms + q] = sum.scale(0.5f32.powi((n + q) as i32));
+                    }
+                }
+
+                let local = &mut self.levels[level].locals[slot * terms * terms..][..terms * terms];
+                for (local, shifted) in local.iter_mut().zip(shifted) {
+                    *local += shifted;
+                }
+            }
+        }
+    }
+
+    /// Acceleration at `position`, which has to be one of the targets the tree was built with.
+    /// `radius` softens the near field like in the direct solver
+    pub fn accel_at(&self, position: Vec2, radius: f32) -> Vec2 {
+        let terms = self.coefficients.terms();
+        let depth = self.depth();
+        let leaves = &self.levels[depth];
+        let key = ((position - self.origin) / leaves.width)
+            .floor()
+            .as_ivec2()
+            .clamp(IVec2::ZERO, IVec2::splat((1 << depth) - 1));
+        let Some(slot) = leaves.slots.get(&key) else {
+            return Vec2::ZERO;
+        };
+
+        // far field, a = -grad(phi) = -2 d(phi)/d(conj(t))
+        let offset = (position - self.center(depth, key)) / leaves.width;
+        let t = powers(Complex::new(offset.x, offset.y), terms);
+        let local = &leaves.locals[slot * terms * terms..][..terms * terms];
+        let mut far = Complex::ZERO;
+        for n in 0..terms {
+            for q in 1..terms {
+                far += (local[n * terms + q] * t[n] * t[q - 1].conj()).scale(q as f32);
+            }
+        }
+        let mut accel = Vec2::new(far.re, far.im) * (-2.0 / leaves.width);
+
+        for neighbour in neighbours(key) {
+            let Some(neighbour_slot) = leaves.slots.get(&neighbour) else {
+                continue;
+            };
+            for (source_position, source_mass, source_radius) in &self.leaf_sources[*neighbour_slot]
+            {
+                let delta = *source_position - position;
+                let distance_sq = delta.length_squared();
+                if distance_sq < 1e-20 {
+                    continue;
+                }
+                let distance = distance_sq.sqrt().max(radius + source_radius);
+                accel += (source_mass / (distance * distance * distance)) * delta;
+            }
+        }
+        accel
+    }
+}
+
+/// The 3x3 block of boxes around and including `key`
+fn neighbours(key: IVec2) -> impl Iterator<Item = IVec2> {
+    (-1..=1).flat_map(move |y| (-1..=1).map(move |x| key + IVec2::new(x, y)))
+}
+
+/// Add the local expansion of a box's moments to `local`, `offset` is how many boxes of `width`
+/// the local center is from the moments' center
+fn multipole_to_local(
+    coefficients: &Coefficients,
+    multipole: &[Complex],
+    offset: Vec2,
+    width: f32,
+    local: &mut [Complex],
+) {
+    let terms = coefficients.terms();
+    let u = Complex::new(offset.x, offset.y).inv();
+    let u_powers = powers(u, terms);
+    let scale = -1.0 / (offset.length() * width);
+
+    // sum over the moments' powers of conj(s) first, then their powers of s
+    let mut partial = vec![Complex::ZERO; terms * terms];
+    for k in 0..terms {
+        for q in 0..terms {
+            let mut sum = Complex::ZERO;
+            for l in 0..terms {
+                let factor = coefficients.inverse_sqrt[l] * coefficients.shifted(l, q);
+                sum += (u_powers[l].conj() * multipole[k * terms + l]).scale(factor);
+            }
+            partial[k * terms + q] = sum;
+        }
+    }
+    for n in 0..terms {
+        for q in 0..terms {
+            let mut sum = Complex::ZERO;
+            for k in 0..terms {
+                let factor = coefficients.inverse_sqrt[k] * coefficients.shifted(k, n);
+                sum += (u_powers[k] * partial[k * terms + q]).scale(factor);
+            }
+            local[n * terms + q] += (u_powers[n] * u_powers[q].conj() * sum).scale(scale);
+        }
+    }
+}
+
+pub fn calc_fmm_accel(
+    mut particles: Query<
+        (
+            &mut Acceleration,
+            &Transform,
+            &Mass,
+            &Radius,
+            Has<TestParticle>,
+        ),
+        With<Particle>,
+    >,
+    sim_settings: Res<SimSettings>,
+) {
+    if particles.is_empty() {
+        return;
+    }
+
+    // test particles only feel the massive particles, like in the direct solver
+    let mut sources = Vec::new();
+    let mut targets = Vec::new();
+    for (_, transform, Mass(mass), Radius(radius), test_particle) in particles.iter() {
+        let position = transform.translation.truncate();
+        targets.push(position);
+        if !test_particle {
+            sources.push((
+                position,
+                mass * sim_settings.gravitational_constant,
+                *radius,
+            ));
+        }
+    }
+
+    let tree = MultipoleTree::new(&sources, &targets, &sim_settings.multipole);
+
+    particles
+        .par_iter_mut()
+        .for_each(|(mut acceleration, transform, _, Radius(radius), _)| {
+            acceleration.0 += tree.accel_at(transform.translation.truncate(), *radius);
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MultipoleSettings, MultipoleTree};
+    use bevy::prelude::*;
+    use rand::random_range;
+
+    /// Rms error of the tree against direct summation, relative to the rms acceleration
+    fn relative_error(sources: &[(Vec2, f32, f32)], order: usize) -> f32 {
+        let targets: Vec<Vec2> = sources.iter().map(|(position, _, _)| *position).collect();
+        let settings = MultipoleSettings {
+            order,
+            leaf_size: 8,
+        };
+        let tree = MultipoleTree::new(sources, &targets, &settings);
+
+        let mut error_sq = 0.0;
+        let mut accel_sq = 0.0;
+        for (position, _, radius) in sources {
+            let mut direct = Vec2::ZERO;
+            for (source_position, source_mass, source_radius) in sources {
+                let delta = *source_position - *position;
+                if delta.length_squared() < 1e-20 {
+                    continue;
+                }
+                let distance = delta.length().max(radius + source_radius);
+                direct += source_mass / (distance * distance * distance) * delta;
+            }
+            error_sq += (tree.accel_at(*position, *radius) - direct).length_squared();
+            accel_sq += direct.length_squared();
+        }
+        (error_sq / accel_sq).sqrt()
+    }
+
+    #[test]
+    fn test_multipole_accuracy_against_direct_summation() {
+        let sources: Vec<(Vec2, f32, f32)> = (0..2000)
+            .map(|_| {
+                let position = Vec2::new(random_range(-500.0..500.0), random_range(-500.0..500.0));
+                (position, random_range(0.5..2.0), 0.1)
+            })
+            .collect();
+
+        let low_order = relative_error(&sources, 2);
+        let high_order = relative_error(&sources, 8);
+        assert!(
+            high_order < 1e-4,
+            "order 8 should be within 0.01% of direct summation, error was {high_order}"
+        );
+        assert!(
+            high_order < low_order,
+            "raising the order should make it more accurate, {low_order} went to {high_order}"
+        );
+    }
+}
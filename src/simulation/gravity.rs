@@ -1,19 +1,183 @@
-use crate::particle::{Mass, Particle, Radius};
-use crate::simulation::motion::Acceleration;
+use crate::particle::{Mass, Particle, Radius, TestParticle};
+use crate::simulation::motion::{Acceleration, OldPosition};
+use crate::simulation::periodic::EwaldTable;
 use bevy::prelude::*;
 
+use super::SimSettings;
+
+/// Which method is used to work out gravity between particles
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum GravitySolver {
+    /// Sum over every pair of particles, exact but O(n^2)
+    Direct,
+    /// Solve for the potential on a grid with ffts, see [super::particle_mesh]
+    ParticleMesh,
+    /// Multipole and local expansions on a quadtree, see [super::fmm]
+    Multipole,
+}
+
+impl std::fmt::Display for GravitySolver {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GravitySolver::Direct => write!(f, "direct"),
+            GravitySolver::ParticleMesh => write!(f, "particle mesh"),
+            GravitySolver::Multipole => write!(f, "fast multipole"),
+        }
+    }
+}
+
+/// The solver that actually runs. The multipole solver only handles isolated boundaries, so the
+/// particle mesh takes over from it while the periodic box is enabled
+pub fn active_solver(settings: &SimSettings) -> GravitySolver {
+    match settings.gravity_solver {
+        GravitySolver::Multipole if settings.periodic.enabled => GravitySolver::ParticleMesh,
+        solver => solver,
+    }
+}
+
+/// Run condition that is true when `solver` is the gravity solver that should run, see
+/// [active_solver]
+pub fn using_solver(solver: GravitySolver) -> impl Fn(Res<SimSettings>) -> bool + Clone {
+    move |settings: Res<SimSettings>| active_solver(&settings) == solver
+}
+
+/// Optional post-Newtonian terms added on top of the pairwise Newtonian gravity
+#[derive(Clone, Copy, Debug)]
+pub struct PostNewtonian {
+    /// 1PN correction, makes orbits precess
+    pub first_order: bool,
+    /// 2.5PN radiation reaction, makes binaries lose energy to gravitational waves and inspiral
+    pub radiation_reaction: bool,
+    /// Speed of light in simulation units per second, lower makes relativity stronger
+    pub speed_of_light: f32,
+}
+
+impl PostNewtonian {
+    pub fn enabled(&self) -> bool {
+        self.first_order || self.radiation_reaction
+    }
+}
+
+impl Default for PostNewtonian {
+    fn default() -> Self {
+        Self {
+            first_order: false,
+            radiation_reaction: false,
+            speed_of_light: 100.0,
+        }
+    }
+}
+
+/// Returns the post-Newtonian correction to the relative acceleration of a pair of bodies, where
+/// `position` and `velocity` are those of body 1 relative to body 2. Body 1 gets `m_2 / M` of
+/// this and body 2 gets `-m_1 / M` of it.
+///
+/// This is the two-body equation of motion in harmonic coordinates from Kidder (1995),
+/// `a = -(M/r^2) * (A * n + B * v)` without the Newtonian part.
+pub fn post_newtonian_accel(
+    position: Vec2,
+    velocity: Vec2,
+    mass_1: f32,
+    mass_2: f32,
+    settings: &PostNewtonian,
+) -> Vec2 {
+    let total_mass = mass_1 + mass_2;
+    let distance = position.length();
+    if total_mass <= 0.0 || distance < 1e-10 {
+        return Vec2::ZERO;
+    }
+
+    let c2 = settings.speed_of_light * settings.speed_of_light;
+    let eta = mass_1 * mass_2 / (total_mass * total_mass);
+    let normal = position / distance;
+    let radial_speed = normal.dot(velocity);
+    let speed_sq = velocity.length_squared();
+    let potential = total_mass / distance;
+
+    let mut a = 0.0;
+    let mut b = 0.0;
+
+    if settings.first_order {
+        a += (-1.5 * eta * radial_speed * radial_speed + (1.0 + 3.0 * eta) * speed_sq
+            - 2.0 * (2.0 + eta) * potential)
+            / c2;
+        b += -2.0 * (2.0 - eta) * radial_speed / c2;
+    }
+
+    if settings.radiation_reaction {
+        let c5 = c2 * c2 * settings.speed_of_light;
+        a += -1.6
+            * eta
+            * potential
+            * radial_speed
+            * (18.0 * speed_sq + 2.0 / 3.0 * potential - 25.0 * radial_speed * radial_speed)
+            / c5;
+        b += 1.6
+            * eta
+            * potential
+            * (6.0 * speed_sq - 2.0 * potential - 15.0 * radial_speed * radial_speed)
+            / c5;
+    }
+
+    -(total_mass / (distance * distance)) * (a * normal + b * velocity)
+}
+
+/// Velocity, in units per second, that puts a body at `offset` from a mass on an orbit of the
+/// given eccentricity with that point as periapsis. Zero eccentricity is a circular orbit.
+/// Orbits go anticlockwise unless `retrograde`
+pub fn orbital_velocity(
+    offset: Vec2,
+    total_mass: f32,
+    eccentricity: f32,
+    retrograde: bool,
+) -> Vec2 {
+    let distance = offset.length();
+    if distance < 1e-10 || total_mass <= 0.0 {
+        return Vec2::ZERO;
+    }
+    let speed = (total_mass * (1.0 + eccentricity) / distance).sqrt();
+    let direction = offset.perp() / distance;
+    if retrograde {
+        -direction * speed
+    } else {
+        direction * speed
+    }
+}
+
 pub fn calc_grav_accel(
-    mut query: Query<(&mut Acceleration, &Mass, &Transform, &Radius), With<Particle>>,
+    mut query: Query<
+        (&mut Acceleration, &Mass, &Transform, &Radius, &OldPosition),
+        (With<Particle>, Without<TestParticle>),
+    >,
+    mut test_particles: Query<
+        (&mut Acceleration, &Transform, &Radius),
+        (With<Particle>, With<TestParticle>),
+    >,
+    sim_settings: Res<SimSettings>,
+    time: Res<Time>,
+    mut ewald: Local<EwaldTable>,
 ) {
+    let post_newtonian = sim_settings.post_newtonian;
+    let periodic = sim_settings.periodic;
+    if periodic.enabled {
+        ewald.update(&periodic);
+    }
+    let ewald = &*ewald;
+    let g = sim_settings.gravitational_constant;
+    let dt = time.delta_secs();
+
     let mut iter = query.iter_combinations_mut();
     while let Some(
-        [(mut accel_1, Mass(mass_1), pos_1, Radius(radius_1)), (mut accel_2, Mass(mass_2), pos_2, Radius(radius_2))],
+        [(mut accel_1, Mass(mass_1), pos_1, Radius(radius_1), old_pos_1), (mut accel_2, Mass(mass_2), pos_2, Radius(radius_2), old_pos_2)],
     ) = iter.fetch_next()
     {
         // a_a = (m_b/|r|^3) * r * dt * G
+        let (mass_1, mass_2) = (mass_1 * g, mass_2 * g);
         let pos_1 = pos_1.translation;
         let pos_2 = pos_2.translation;
-        let delta = pos_2 - pos_1;
+        let delta = periodic
+            .minimum_image((pos_2 - pos_1).truncate())
+            .extend(0.0);
         let distance_sq = delta.length_squared();
         if distance_sq < 1e-20 {
             continue;
@@ -26,5 +190,200 @@ pub fn calc_grav_accel(
         let distance_cubed = distance * distance * distance;
         accel_1.0 += ((mass_2 / (distance_cubed)) * delta).truncate();
         accel_2.0 -= ((mass_1 / (distance_cubed)) * delta).truncate();
+
+        if periodic.enabled {
+            let correction = ewald.correction(delta.truncate());
+            accel_1.0 += mass_2 * correction;
+            accel_2.0 -= mass_1 * correction;
+        }
+
+        if post_newtonian.enabled() && dt > 0.0 {
+            let velocity_1 = (pos_1 - old_pos_1.0.translation).truncate() / dt;
+            let velocity_2 = (pos_2 - old_pos_2.0.translation).truncate() / dt;
+            let relative_position = -delta.truncate().normalize() * distance;
+            let correction = post_newtonian_accel(
+                relative_position,
+                velocity_1 - velocity_2,
+                mass_1,
+                mass_2,
+                &post_newtonian,
+            );
+            let total_mass = mass_1 + mass_2;
+            if total_mass != 0.0 {
+                accel_1.0 += correction * (mass_2 / total_mass);
+                accel_2.0 -= correction * (mass_1 / total_mass);
+            }
+        }
+    }
+
+    if test_particles.is_empty() {
+        return;
+    }
+
+    // test particles only feel the massive particles, so this is n_massive * n_test
+    let sources: Vec<(Vec2, f32, f32)> = query
+        .iter()
+        .map(|(_, Mass(mass), transform, Radius(radius), _)| {
+            (transform.translation.truncate(), mass * g, *radius)
+        })
+        .collect();
+
+    test_particles
+        .par_iter_mut()
+        .for_each(|(mut acceleration, transform, Radius(radius))| {
+            let position = transform.translation.truncate();
+            for (source_position, source_mass, source_radius) in &sources {
+                let delta = periodic.minimum_image(source_position - position);
+                let distance_sq = delta.length_squared();
+                if distance_sq < 1e-20 {
+                    continue;
+                }
+                let distance = distance_sq.sqrt().max(radius + source_radius);
+                acceleration.0 += (source_mass / (distance * distance * distance)) * delta;
+                if periodic.enabled {
+                    acceleration.0 += source_mass * ewald.correction(delta);
+                }
+            }
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        active_solver, calc_grav_accel, post_newtonian_accel, GravitySolver, PostNewtonian,
+    };
+    use crate::particle::{ParticleBundle, TestParticle};
+    use crate::simulation::motion::Acceleration;
+    use crate::simulation::SimSettings;
+    use bevy::prelude::*;
+
+    /// Returns the direction of the Runge-Lenz vector, which points at periapsis
+    fn periapsis_angle(position: Vec2, velocity: Vec2, mass: f32) -> f32 {
+        let angular_momentum = position.perp_dot(velocity);
+        let runge_lenz =
+            Vec2::new(velocity.y, -velocity.x) * angular_momentum / mass - position.normalize();
+        runge_lenz.to_angle()
+    }
+
+    #[test]
+    fn test_first_order_precession_matches_analytic_rate() {
+        let settings = PostNewtonian {
+            first_order: true,
+            radiation_reaction: false,
+            speed_of_light: 20.0,
+        };
+
+        // a test particle around a unit mass, starting at periapsis with eccentricity 0.5
+        let central_mass = 1.0;
+        let test_mass = 1e-6;
+        let total_mass = central_mass + test_mass;
+        let eccentricity = 0.5;
+        let periapsis = 0.5;
+        let semi_major_axis = periapsis / (1.0 - eccentricity);
+
+        let mut position = Vec2::new(periapsis, 0.0);
+        let mut velocity = Vec2::new(0.0, (total_mass * (1.0 + eccentricity) / periapsis).sqrt());
+
+        let accel = |position: Vec2, velocity: Vec2| {
+            let distance = position.length();
+            -total_mass * position / (distance * distance * distance)
+                + post_newtonian_accel(position, velocity, test_mass, central_mass, &settings)
+        };
+
+        let dt = 1e-3;
+        let orbits = 4;
+        let mut periapsis_angles = vec![periapsis_angle(position, velocity, total_mass)];
+        let mut radial_speed = position.dot(velocity);
+
+        while periapsis_angles.len() <= orbits {
+            // rk4
+            let k1v = accel(position, velocity);
+            let k1x = velocity;
+            let k2v = accel(position + k1x * dt / 2.0, velocity + k1v * dt / 2.0);
+            let k2x = velocity + k1v * dt / 2.0;
+            let k3v = accel(position + k2x * dt / 2.0, velocity + k2v * dt / 2.0);
+            let k3x = velocity + k2v * dt / 2.0;
+            let k4v = accel(position + k3x * dt, velocity + k3v * dt);
+            let k4x = velocity + k3v * dt;
+            position += (k1x + 2.0 * k2x + 2.0 * k3x + k4x) * dt / 6.0;
+            velocity += (k1v + 2.0 * k2v + 2.0 * k3v + k4v) * dt / 6.0;
+
+            let new_radial_speed = position.dot(velocity);
+            if radial_speed < 0.0 && new_radial_speed >= 0.0 {
+                periapsis_angles.push(periapsis_angle(position, velocity, total_mass));
+            }
+            radial_speed = new_radial_speed;
+        }
+
+        let measured = (periapsis_angles[orbits] - periapsis_angles[0]) / orbits as f32;
+        let c2 = settings.speed_of_light * settings.speed_of_light;
+        let expected = 6.0 * std::f32::consts::PI * total_mass
+            / (c2 * semi_major_axis * (1.0 - eccentricity * eccentricity));
+
+        let error = (measured - expected).abs() / expected;
+        assert!(
+            error < 0.05,
+            "precession per orbit {measured} differs from analytic {expected} by {:.1}%",
+            error * 100.0
+        );
+    }
+
+    #[test]
+    fn test_radiation_reaction_removes_energy() {
+        let settings = PostNewtonian {
+            first_order: false,
+            radiation_reaction: true,
+            speed_of_light: 10.0,
+        };
+
+        // equal mass circular binary, the correction should point against the velocity
+        let position = Vec2::new(1.0, 0.0);
+        let velocity = Vec2::new(0.0, 2.0_f32.sqrt());
+        let correction = post_newtonian_accel(position, velocity, 1.0, 1.0, &settings);
+
+        assert!(correction.dot(velocity) < 0.0);
+    }
+
+    #[test]
+    fn test_test_particles_only_feel_massive_particles() {
+        let mut app = App::new();
+        app.init_resource::<SimSettings>()
+            .init_resource::<Time>()
+            .add_systems(Update, calc_grav_accel);
+
+        let massive = app
+            .world_mut()
+            .spawn(ParticleBundle::new().mass(100.0))
+            .id();
+        // the mass of a test particle is ignored, even if it has one
+        let test_particle = |x: f32| {
+            (
+                ParticleBundle::new().position(Vec2::new(x, 0.0)).mass(50.0),
+                TestParticle,
+            )
+        };
+        let near = app.world_mut().spawn(test_particle(10.0)).id();
+        let far = app.world_mut().spawn(test_particle(20.0)).id();
+
+        app.update();
+
+        let acceleration = |entity| app.world().get::<Acceleration>(entity).unwrap().0;
+        assert_eq!(acceleration(massive), Vec2::ZERO);
+        assert!(acceleration(near).distance(Vec2::new(-1.0, 0.0)) < 1e-6);
+        assert!(acceleration(far).distance(Vec2::new(-0.25, 0.0)) < 1e-6);
+    }
+
+    #[test]
+    fn test_periodic_box_swaps_multipole_for_particle_mesh() {
+        let mut settings = SimSettings {
+            gravity_solver: GravitySolver::Multipole,
+            ..default()
+        };
+        assert_eq!(active_solver(&settings), GravitySolver::Multipole);
+
+        settings.periodic.enabled = true;
+        assert_eq!(active_solver(&settings), GravitySolver::ParticleMesh);
+        settings.gravity_solver = GravitySolver::Direct;
+        assert_eq!(active_solver(&settings), GravitySolver::Direct);
     }
 }
@@ -2,12 +2,26 @@ use bevy::prelude::*;
 
 use crate::particle::{despawn_particles, Particle};
 
+pub mod bonds;
+pub mod colliders;
 pub mod collisions;
+pub mod cosmology;
+pub mod fft;
+pub mod fmm;
 pub mod gravity;
 pub mod motion;
+pub mod particle_life;
+pub mod particle_mesh;
+pub mod periodic;
 pub mod quadtree;
+pub mod regularization;
+pub mod sinks;
+pub mod sph;
+pub mod tidal;
+pub mod units;
+pub mod wisdom_holman;
 
-const PHYSICS_UPDATE_HZ: f64 = 120.0;
+pub const PHYSICS_UPDATE_HZ: f64 = 120.0;
 
 pub struct SimPlugin;
 
@@ -17,23 +31,70 @@ impl Plugin for SimPlugin {
             FixedUpdate,
             (
                 // quadtree::quadtree_system,
-                motion::update_particle_positions,
-                gravity::calc_grav_accel,
+                advance_sim_time,
+                cosmology::advance_scale_factor,
+                wisdom_holman::wisdom_holman_step.run_if(wisdom_holman::wisdom_holman_enabled),
+                motion::update_particle_positions.run_if(wisdom_holman::verlet_active),
+                regularization::regularize_binaries.run_if(wisdom_holman::verlet_active),
+                motion::update_fixed_particles,
+                periodic::wrap_particles,
+                gravity::calc_grav_accel.run_if(
+                    not(particle_life::particle_life_enabled)
+                        .and(gravity::using_solver(gravity::GravitySolver::Direct))
+                        .and(wisdom_holman::verlet_active),
+                ),
+                particle_mesh::calc_pm_accel.run_if(
+                    not(particle_life::particle_life_enabled)
+                        .and(gravity::using_solver(gravity::GravitySolver::ParticleMesh))
+                        .and(wisdom_holman::verlet_active),
+                ),
+                fmm::calc_fmm_accel.run_if(
+                    not(particle_life::particle_life_enabled)
+                        .and(gravity::using_solver(gravity::GravitySolver::Multipole))
+                        .and(wisdom_holman::verlet_active),
+                ),
+                cosmology::comoving_gravity,
+                particle_life::calc_particle_life_accel
+                    .run_if(particle_life::particle_life_enabled),
+                sph::calc_sph_accel,
+                bonds::calc_bond_forces,
                 collisions::calculate_collisions,
+                colliders::collide_with_colliders,
+                tidal::tidal_disruption,
+                sinks::accrete_particles,
             )
                 .chain()
                 .run_if(sim_not_paused),
         )
         .add_systems(
             Update,
-            clear_particles_system.run_if(should_clear_particles),
+            (
+                clear_particles_system.run_if(should_clear_particles),
+                reset_expansion_system.run_if(should_reset_expansion),
+                cosmology::require_periodic_box.run_if(cosmology::periodic_box_missing),
+                units::apply_unit_system.run_if(resource_changed::<units::UnitSystem>),
+            ),
         )
+        .add_event::<tidal::TidalDisruption>()
         .insert_resource(Time::<Fixed>::from_hz(PHYSICS_UPDATE_HZ))
         .init_resource::<SimSettings>()
+        .init_resource::<SimTime>()
+        .init_resource::<units::UnitSystem>()
+        .init_resource::<sinks::AccretedMass>()
+        .init_resource::<cosmology::Expansion>()
+        .init_resource::<wisdom_holman::HybridState>()
         .init_resource::<quadtree::QuadTree>();
     }
 }
 
+/// Simulated time since the particles were last cleared, only counts steps that actually ran
+#[derive(Resource, Default, Clone, Copy, Debug)]
+pub struct SimTime(pub f64);
+
+fn advance_sim_time(mut sim_time: ResMut<SimTime>, time: Res<Time>) {
+    sim_time.0 += time.delta_secs_f64();
+}
+
 /// returns true if the simulation is not paused
 pub fn sim_not_paused(settings: Res<SimSettings>) -> bool {
     !settings.paused
@@ -41,10 +102,18 @@ pub fn sim_not_paused(settings: Res<SimSettings>) -> bool {
 
 fn clear_particles_system(
     particles: Query<Entity, With<Particle>>,
+    bonds: Query<Entity, With<bonds::Bond>>,
     mut commands: Commands,
     mut settings: ResMut<SimSettings>,
+    mut accreted_mass: ResMut<sinks::AccretedMass>,
+    mut sim_time: ResMut<SimTime>,
 ) {
     despawn_particles(&mut commands, particles);
+    accreted_mass.0 = 0.0;
+    sim_time.0 = 0.0;
+    for bond in bonds.iter() {
+        commands.entity(bond).despawn();
+    }
     settings.should_clear_all_particles = false;
 }
 
@@ -52,21 +121,69 @@ fn should_clear_particles(settings: Res<SimSettings>) -> bool {
     settings.should_clear_all_particles
 }
 
+fn reset_expansion_system(
+    mut expansion: ResMut<cosmology::Expansion>,
+    mut settings: ResMut<SimSettings>,
+) {
+    expansion.scale_factor = settings.cosmology.initial_scale_factor;
+    expansion.time = 0.0;
+    settings.should_reset_expansion = false;
+}
+
+fn should_reset_expansion(settings: Res<SimSettings>) -> bool {
+    settings.should_reset_expansion
+}
+
 #[derive(Resource)]
 pub struct SimSettings {
     pub paused: bool,
+    /// Newton's constant, kept in step with the [units::UnitSystem]
+    pub gravitational_constant: f32,
     pub collision_steps: u32,
     pub enable_collisions: bool,
     pub should_clear_all_particles: bool,
+    /// Replace gravity with the species [particle_life::InteractionMatrix]
+    pub enable_particle_life: bool,
+    pub interactions: particle_life::InteractionMatrix,
+    pub gravity_solver: gravity::GravitySolver,
+    pub particle_mesh: particle_mesh::ParticleMeshSettings,
+    pub multipole: fmm::MultipoleSettings,
+    pub integrator: wisdom_holman::Integrator,
+    pub regularization: regularization::RegularizationSettings,
+    pub wisdom_holman: wisdom_holman::WisdomHolmanSettings,
+    pub sph: sph::SphSettings,
+    pub post_newtonian: gravity::PostNewtonian,
+    pub tidal: tidal::TidalSettings,
+    pub periodic: periodic::PeriodicBox,
+    pub cosmology: cosmology::CosmologySettings,
+    pub should_reset_expansion: bool,
+    /// Julian date at time zero, shown as a calendar date when the units give time a length
+    pub epoch: Option<f64>,
 }
 
 impl Default for SimSettings {
     fn default() -> Self {
         SimSettings {
             paused: true,
+            gravitational_constant: 1.0,
             collision_steps: 2,
             enable_collisions: false,
             should_clear_all_particles: false,
+            enable_particle_life: false,
+            interactions: particle_life::InteractionMatrix::default(),
+            gravity_solver: gravity::GravitySolver::Direct,
+            particle_mesh: particle_mesh::ParticleMeshSettings::default(),
+            multipole: fmm::MultipoleSettings::default(),
+            integrator: wisdom_holman::Integrator::Verlet,
+            regularization: regularization::RegularizationSettings::default(),
+            wisdom_holman: wisdom_holman::WisdomHolmanSettings::default(),
+            sph: sph::SphSettings::default(),
+            post_newtonian: gravity::PostNewtonian::default(),
+            tidal: tidal::TidalSettings::default(),
+            periodic: periodic::PeriodicBox::default(),
+            cosmology: cosmology::CosmologySettings::default(),
+            should_reset_expansion: false,
+            epoch: None,
         }
     }
 }
@@ -10,13 +10,46 @@ pub struct Acceleration(pub Vec2);
 #[derive(Component)]
 pub struct PreviousAcceleration(pub Vec2);
 
+/// Particles with this marker still pull on everything else, but forces never move them
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct Fixed;
+
+/// A scripted path for a [Fixed] particle to follow instead of staying still
+#[derive(Component, Clone, Copy, Debug)]
+pub enum KinematicPath {
+    /// Go around `center` at whatever distance the particle starts at, `angular_speed` is in
+    /// radians per second, positive is anticlockwise
+    Circular { center: Vec2, angular_speed: f32 },
+    /// Move at a constant velocity in units per second
+    Linear { velocity: Vec2 },
+}
+
+impl KinematicPath {
+    /// Where the path puts a particle one step of `delta_secs` after being at `position`
+    pub fn step(&self, position: Vec2, delta_secs: f32) -> Vec2 {
+        match self {
+            KinematicPath::Circular {
+                center,
+                angular_speed,
+            } => {
+                let rotation = Vec2::from_angle(angular_speed * delta_secs);
+                *center + rotation.rotate(position - *center)
+            }
+            KinematicPath::Linear { velocity } => position + *velocity * delta_secs,
+        }
+    }
+}
+
 pub fn update_particle_positions(
-    mut query: Query<(
-        &mut Transform,
-        &mut OldPosition,
-        &mut Acceleration,
-        &mut PreviousAcceleration,
-    )>,
+    mut query: Query<
+        (
+            &mut Transform,
+            &mut OldPosition,
+            &mut Acceleration,
+            &mut PreviousAcceleration,
+        ),
+        Without<Fixed>,
+    >,
     time: Res<Time>,
 ) {
     query.par_iter_mut().for_each(
@@ -36,6 +69,37 @@ pub fn update_particle_positions(
     );
 }
 
+/// Moves fixed particles along their [KinematicPath], if they have one, and throws away the
+/// acceleration they picked up so it does not build up forever
+pub fn update_fixed_particles(
+    mut query: Query<
+        (
+            &mut Transform,
+            &mut OldPosition,
+            &mut Acceleration,
+            &mut PreviousAcceleration,
+            Option<&KinematicPath>,
+        ),
+        With<Fixed>,
+    >,
+    time: Res<Time>,
+) {
+    let dt = time.delta_secs();
+    for (mut position, mut old_position, mut acceleration, mut previous_acceleration, path) in
+        query.iter_mut()
+    {
+        old_position.0 = *position;
+
+        if let Some(path) = path {
+            let new_pos = path.step(position.translation.truncate(), dt);
+            position.translation = new_pos.extend(0.0);
+        }
+
+        previous_acceleration.0 = acceleration.0;
+        acceleration.0 = Vec2::ZERO;
+    }
+}
+
 /// Returns the next position of an object from delta time, position, previous position and acceleration.
 /// If using this to actually move things, remember to update old position and to reset acceleration at the end
 pub fn verlet_integrate(
@@ -47,3 +111,65 @@ pub fn verlet_integrate(
     let velocity = position - old_position;
     position + velocity + acceleration * delta_secs * delta_secs
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{update_fixed_particles, Fixed, KinematicPath, OldPosition};
+    use crate::particle::ParticleBundle;
+    use bevy::prelude::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_fixed_particles_follow_their_path() {
+        let mut app = App::new();
+        app.init_resource::<Time>()
+            .add_systems(Update, update_fixed_particles);
+
+        let start = Vec2::new(10.0, 0.0);
+        let still = app
+            .world_mut()
+            .spawn(ParticleBundle::new().position(start))
+            .id();
+        app.world_mut().entity_mut(still).insert(Fixed);
+        let circling = app
+            .world_mut()
+            .spawn(ParticleBundle::new().position(start))
+            .id();
+        app.world_mut().entity_mut(circling).insert((
+            Fixed,
+            KinematicPath::Circular {
+                center: Vec2::ZERO,
+                angular_speed: std::f32::consts::FRAC_PI_2,
+            },
+        ));
+
+        // a quarter turn in ten steps
+        let mut previous = start;
+        for _ in 0..10 {
+            app.world_mut()
+                .resource_mut::<Time>()
+                .advance_by(Duration::from_secs_f32(0.1));
+            app.update();
+
+            let position = app.world().get::<Transform>(circling).unwrap().translation;
+            let old_position = app
+                .world()
+                .get::<OldPosition>(circling)
+                .unwrap()
+                .0
+                .translation;
+            assert!(old_position.truncate().distance(previous) < 1e-5);
+            assert!((position.truncate().length() - 10.0).abs() < 1e-4);
+            previous = position.truncate();
+        }
+        assert!(
+            previous.distance(Vec2::new(0.0, 10.0)) < 1e-3,
+            "ended at {previous}"
+        );
+
+        let position = app.world().get::<Transform>(still).unwrap().translation;
+        let old_position = app.world().get::<OldPosition>(still).unwrap().0.translation;
+        assert_eq!(position.truncate(), start);
+        assert_eq!(old_position.truncate(), start);
+    }
+}
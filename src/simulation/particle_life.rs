@@ -0,0 +1,265 @@
+use crate::particle::{Particle, Species};
+use crate::simulation::motion::{Acceleration, OldPosition};
+use bevy::prelude::*;
+use rand::random_range;
+
+use super::SimSettings;
+
+/// How one species reacts to another. Positive strength attracts, negative repels.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Interaction {
+    pub strength: f32,
+    /// Below this distance particles always push each other apart
+    pub min_range: f32,
+    /// Beyond this distance the interaction has no effect
+    pub max_range: f32,
+}
+
+impl Interaction {
+    pub fn new(strength: f32) -> Self {
+        Self {
+            strength,
+            min_range: 5.0,
+            max_range: 40.0,
+        }
+    }
+
+    /// Magnitude of the force at `distance`, positive pulls the particles together.
+    ///
+    /// Inside `min_range` the force ramps linearly from full repulsion to zero, between
+    /// `min_range` and `max_range` it is a triangle peaking at `strength` halfway between them.
+    pub fn force(&self, distance: f32) -> f32 {
+        if distance < self.min_range {
+            return distance / self.min_range - 1.0;
+        }
+
+        if distance >= self.max_range {
+            return 0.0;
+        }
+
+        let width = self.max_range - self.min_range;
+        let middle = self.min_range + width / 2.0;
+        self.strength * (1.0 - (distance - middle).abs() / (width / 2.0))
+    }
+}
+
+/// N×N table of [Interaction]s, `get(a, b)` is how species `a` reacts to species `b`
+#[derive(Clone, Debug)]
+pub struct InteractionMatrix {
+    species: usize,
+    rules: Vec<Interaction>,
+    /// Scales every force in the matrix
+    pub force_scale: f32,
+    /// Drag applied to particles so the system settles instead of heating up forever
+    pub friction: f32,
+}
+
+impl InteractionMatrix {
+    pub fn new(species: usize) -> Self {
+        Self {
+            species,
+            rules: vec![Interaction::new(0.0); species * species],
+            force_scale: 500.0,
+            friction: 2.0,
+        }
+    }
+
+    pub fn species(&self) -> usize {
+        self.species
+    }
+
+    pub fn get(&self, a: usize, b: usize) -> &Interaction {
+        &self.rules[a * self.species + b]
+    }
+
+    pub fn get_mut(&mut self, a: usize, b: usize) -> &mut Interaction {
+        &mut self.rules[a * self.species + b]
+    }
+
+    /// Change the amount of species, keeping the rules of the species that still exist
+    pub fn resize(&mut self, species: usize) {
+        let mut resized = InteractionMatrix::new(species);
+        for a in 0..species.min(self.species) {
+            for b in 0..species.min(self.species) {
+                *resized.get_mut(a, b) = *self.get(a, b);
+            }
+        }
+        self.species = species;
+        self.rules = resized.rules;
+    }
+
+    /// Give every pair a random strength between -1 and 1
+    pub fn randomize(&mut self) {
+        for rule in self.rules.iter_mut() {
+            rule.strength = random_range(-1.0..=1.0);
+        }
+    }
+}
+
+impl Default for InteractionMatrix {
+    fn default() -> Self {
+        let mut matrix = InteractionMatrix::new(3);
+        let strengths = [[0.6, 0.8, -0.4], [-0.6, 0.2, 0.9], [0.5, -0.7, 0.3]];
+        for (a, row) in strengths.iter().enumerate() {
+            for (b, strength) in row.iter().enumerate() {
+                matrix.get_mut(a, b).strength = *strength;
+            }
+        }
+        matrix
+    }
+}
+
+/// returns true if particle life mode is replacing gravity
+pub fn particle_life_enabled(settings: Res<SimSettings>) -> bool {
+    settings.enable_particle_life
+}
+
+pub fn calc_particle_life_accel(
+    mut query: Query<(&mut Acceleration, &Transform, &OldPosition, &Species), With<Particle>>,
+    sim_settings: Res<SimSettings>,
+    time: Res<Time>,
+) {
+    let matrix = &sim_settings.interactions;
+    if matrix.species() == 0 {
+        return;
+    }
+
+    let mut iter = query.iter_combinations_mut();
+    while let Some(
+        [(mut accel_1, pos_1, _, Species(species_1)), (mut accel_2, pos_2, _, Species(species_2))],
+    ) = iter.fetch_next()
+    {
+        // species outside of the matrix dont interact with anything
+        if *species_1 >= matrix.species() || *species_2 >= matrix.species() {
+            continue;
+        }
+
+        let delta = sim_settings
+            .periodic
+            .minimum_image((pos_2.translation - pos_1.translation).truncate());
+        let distance = delta.length();
+        if distance < 1e-10 {
+            continue;
+        }
+        let direction = delta / distance;
+
+        let force_1 = matrix.get(*species_1, *species_2).force(distance);
+        let force_2 = matrix.get(*species_2, *species_1).force(distance);
+
+        accel_1.0 += direction * force_1 * matrix.force_scale;
+        accel_2.0 -= direction * force_2 * matrix.force_scale;
+    }
+
+    let dt = time.delta_secs();
+    if dt == 0.0 {
+        return;
+    }
+
+    query
+        .par_iter_mut()
+        .for_each(|(mut acceleration, position, old_position, _)| {
+            let velocity = (position.translation - old_position.0.translation).truncate() / dt;
+            acceleration.0 -= velocity * matrix.friction;
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{calc_particle_life_accel, Interaction, InteractionMatrix};
+    use crate::particle::ParticleBundle;
+    use crate::simulation::motion::Acceleration;
+    use crate::simulation::SimSettings;
+    use bevy::prelude::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_interaction_force_profile() {
+        for strength in [-0.8, 0.0, 0.8] {
+            let interaction = Interaction::new(strength);
+
+            // always repulsive up close, however the species feel about each other
+            assert_eq!(interaction.force(0.0), -1.0);
+            assert!(interaction.force(interaction.min_range * 0.5) < 0.0);
+
+            let middle = (interaction.min_range + interaction.max_range) / 2.0;
+            assert_eq!(interaction.force(middle), strength);
+            let band = interaction.force(interaction.min_range + 1.0);
+            assert_eq!(
+                band.signum(),
+                if strength == 0.0 {
+                    1.0
+                } else {
+                    strength.signum()
+                }
+            );
+
+            assert_eq!(interaction.force(interaction.max_range), 0.0);
+            assert_eq!(interaction.force(interaction.max_range * 2.0), 0.0);
+        }
+    }
+
+    /// Runs one step of particle life on the given particles and returns their accelerations
+    fn accelerations(matrix: InteractionMatrix, particles: Vec<ParticleBundle>) -> Vec<Vec2> {
+        let mut app = App::new();
+        app.insert_resource(SimSettings {
+            enable_particle_life: true,
+            interactions: matrix,
+            ..default()
+        })
+        .init_resource::<Time>()
+        .add_systems(Update, calc_particle_life_accel);
+        app.world_mut()
+            .resource_mut::<Time>()
+            .advance_by(Duration::from_secs_f32(0.01));
+
+        let entities: Vec<Entity> = particles
+            .into_iter()
+            .map(|particle| app.world_mut().spawn(particle).id())
+            .collect();
+        app.update();
+
+        entities
+            .iter()
+            .map(|entity| app.world().get::<Acceleration>(*entity).unwrap().0)
+            .collect()
+    }
+
+    #[test]
+    fn test_interactions_follow_matrix_sign() {
+        let mut matrix = InteractionMatrix::new(2);
+        matrix.friction = 0.0;
+        // 0 chases 1 and 1 runs away from 0
+        matrix.get_mut(0, 1).strength = 1.0;
+        matrix.get_mut(1, 0).strength = -1.0;
+
+        let accelerations = accelerations(
+            matrix,
+            vec![
+                ParticleBundle::new().species(0),
+                ParticleBundle::new()
+                    .species(1)
+                    .position(Vec2::new(20.0, 0.0)),
+            ],
+        );
+        assert!(accelerations[0].x > 0.0, "{}", accelerations[0]);
+        assert!(accelerations[1].x > 0.0, "{}", accelerations[1]);
+        assert_eq!(accelerations[0].y, 0.0);
+    }
+
+    #[test]
+    fn test_friction_damps_velocity() {
+        let mut matrix = InteractionMatrix::new(1);
+        matrix.friction = 2.0;
+
+        // 0.1 per step of 0.01 seconds
+        let accelerations = accelerations(
+            matrix,
+            vec![ParticleBundle::new().velocity(Vec2::new(0.1, 0.0))],
+        );
+        assert!(
+            accelerations[0].distance(Vec2::new(-20.0, 0.0)) < 1e-3,
+            "{}",
+            accelerations[0]
+        );
+    }
+}
@@ -0,0 +1,334 @@
+use std::f32::consts::PI;
+
+use crate::particle::{Mass, Particle, TestParticle};
+use crate::simulation::fft::{fft_2d, Complex};
+use crate::simulation::motion::Acceleration;
+use crate::simulation::periodic::PeriodicBox;
+use bevy::prelude::*;
+
+use super::SimSettings;
+
+#[derive(Clone, Copy, Debug)]
+pub struct ParticleMeshSettings {
+    /// Cells along each side of the grid, always a power of two
+    pub grid_size: usize,
+}
+
+impl Default for ParticleMeshSettings {
+    fn default() -> Self {
+        Self { grid_size: 128 }
+    }
+}
+
+/// Gravitational field on a square grid, found by depositing mass with cloud in cell assignment
+/// and convolving it with the 1/r potential using ffts.
+///
+/// With the periodic box enabled the grid covers the box and the potential is periodic,
+/// otherwise the grid is fitted around the particles and zero padded to twice its size so the
+/// convolution doesn't wrap around and the boundaries are isolated.
+pub struct ParticleMesh {
+    size: usize,
+    cell_size: f32,
+    /// Corner of the grid with the lowest coordinates
+    origin: Vec2,
+    periodic: bool,
+    /// Acceleration at the center of every cell, row major
+    field: Vec<Vec2>,
+}
+
+impl ParticleMesh {
+    /// Builds the field of `sources`, each a position and a mass. The grid is made big enough
+    /// to also cover every position in `targets` when the boundaries are isolated.
+    pub fn new(
+        sources: &[(Vec2, f32)],
+        targets: &[Vec2],
+        grid_size: usize,
+        periodic: &PeriodicBox,
+    ) -> Self {
+        // isolated grids spend four cells on padding, so anything smaller leaves none for the sources
+        let size = grid_size.next_power_of_two().max(8);
+
+        let (origin, cell_size) = if periodic.enabled {
+            (
+                Vec2::splat(-periodic.size / 2.0),
+                periodic.size / size as f32,
+            )
+        } else {
+            let mut min = Vec2::splat(f32::INFINITY);
+            let mut max = Vec2::splat(f32::NEG_INFINITY);
+            for position in sources.iter().map(|(position, _)| position).chain(targets) {
+                min = min.min(*position);
+                max = max.max(*position);
+            }
+            if !min.is_finite() {
+                min = Vec2::ZERO;
+                max = Vec2::ZERO;
+            }
+            // leave a cell free on every side so cloud in cell never falls off the grid
+            let extent = (max - min).max_element().max(1.0);
+            let cell_size = extent / (size - 4) as f32;
+            let center = (min + max) / 2.0;
+            (
+                center - Vec2::splat(cell_size * size as f32 / 2.0),
+                cell_size,
+            )
+        };
+
+        let mut mesh = Self {
+            size,
+            cell_size,
+            origin,
+            periodic: periodic.enabled,
+            field: Vec::new(),
+        };
+
+        let mut mass = vec![0.0; size * size];
+        for (position, source_mass) in sources {
+            for (index, weight) in mesh.cloud_in_cell(*position) {
+                mass[index] += source_mass * weight;
+            }
+        }
+
+        let potential = if mesh.periodic {
+            mesh.periodic_potential(&mass)
+        } else {
+            mesh.isolated_potential(&mass)
+        };
+        mesh.field = mesh.gradient(&potential);
+        mesh
+    }
+
+    /// Acceleration at `position`, interpolated from the grid with the same cloud in cell
+    /// weights used to deposit mass so particles don't accelerate themselves
+    pub fn accel_at(&self, position: Vec2) -> Vec2 {
+        self.cloud_in_cell(position)
+            .into_iter()
+            .map(|(index, weight)| self.field[index] * weight)
+            .sum()
+    }
+
+    /// The four cells around `position` and how much of it each one gets
+    fn cloud_in_cell(&self, position: Vec2) -> [(usize, f32); 4] {
+        let cell = (position - self.origin) / self.cell_size - Vec2::splat(0.5);
+        let base = cell.floor();
+        let fraction = cell - base;
+        let (x, y) = (base.x as i64, base.y as i64);
+
+        [
+            (self.index(x, y), (1.0 - fraction.x) * (1.0 - fraction.y)),
+            (self.index(x + 1, y), fraction.x * (1.0 - fraction.y)),
+            (self.index(x, y + 1), (1.0 - fraction.x) * fraction.y),
+            (self.index(x + 1, y + 1), fraction.x * fraction.y),
+        ]
+    }
+
+    /// Index of cell `(x, y)`, wrapped around when periodic and clamped to the edge otherwise
+    fn index(&self, x: i64, y: i64) -> usize {
+        let size = self.size as i64;
+        let (x, y) = if self.periodic {
+            (x.rem_euclid(size), y.rem_euclid(size))
+        } else {
+            (x.clamp(0, size - 1), y.clamp(0, size - 1))
+        };
+        (y * size + x) as usize
+    }
+
+    /// Convolve the mass with a softened `-1/r` on a grid twice the size, so mass on one side
+    /// can't reach around to the other
+    fn isolated_potential(&self, mass: &[f32]) -> Vec<f32> {
+        let size = self.size;
+        let padded = size * 2;
+        let softening_sq = self.cell_size * self.cell_size;
+
+        let mut density = vec![Complex::ZERO; padded * padded];
+        for y in 0..size {
+            for x in 0..size {
+                density[y * padded + x].re = mass[y * size + x];
+            }
+        }
+
+        let mut green = vec![Complex::ZERO; padded * padded];
+        for y in 0..padded {
+            for x in 0..padded {
+                let dx = x.min(padded - x) as f32 * self.cell_size;
+                let dy = y.min(padded - y) as f32 * self.cell_size;
+                green[y * padded + x].re = -1.0 / (dx * dx + dy * dy + softening_sq).sqrt();
+            }
+        }
+
+        fft_2d(&mut density, padded, false);
+        fft_2d(&mut green, padded, false);
+        for (value, green) in density.iter_mut().zip(&green) {
+            *value = *value * *green;
+        }
+        fft_2d(&mut density, padded, true);
+
+        let mut potential = vec![0.0; size * size];
+        for y in 0..size {
+            for x in 0..size {
+                potential[y * size + x] = density[y * padded + x].re;
+            }
+        }
+        potential
+    }
+
+    /// Solve `phi(k) = -2 pi rho(k) / |k|`, poisson's equation for the 1/r potential of a sheet
+    /// of mass, against a uniform background like the ewald sum
+    fn periodic_potential(&self, mass: &[f32]) -> Vec<f32> {
+        let size = self.size;
+        let cell_area = self.cell_size * self.cell_size;
+        let box_size = self.cell_size * size as f32;
+
+        let mut density: Vec<Complex> = mass
+            .iter()
+            .map(|mass| Complex::new(mass / cell_area, 0.0))
+            .collect();
+        fft_2d(&mut density, size, false);
+
+        let wave_number = |i: usize| {
+            let i = if i < size / 2 {
+                i as f32
+            } else {
+                i as f32 - size as f32
+            };
+            2.0 * PI * i / box_size
+        };
+        for y in 0..size {
+            for x in 0..size {
+                let k = Vec2::new(wave_number(x), wave_number(y)).length();
+                let value = &mut density[y * size + x];
+                *value = if k == 0.0 {
+                    Complex::ZERO
+                } else {
+                    value.scale(-2.0 * PI / k)
+                };
+            }
+        }
+        fft_2d(&mut density, size, true);
+
+        density.iter().map(|value| value.re).collect()
+    }
+
+    /// `-grad(phi)` by central differences, one sided at the edge of an isolated grid
+    fn gradient(&self, potential: &[f32]) -> Vec<Vec2> {
+        let size = self.size as i64;
+        let mut field = vec![Vec2::ZERO; potential.len()];
+
+        for y in 0..size {
+            for x in 0..size {
+                let difference = |(x_1, y_1): (i64, i64), (x_2, y_2): (i64, i64)| {
+                    let steps = if self.periodic {
+                        2.0
+                    } else {
+                        ((x_2.clamp(0, size - 1) - x_1.clamp(0, size - 1))
+                            + (y_2.clamp(0, size - 1) - y_1.clamp(0, size - 1)))
+                            as f32
+                    };
+                    (potential[self.index(x_2, y_2)] - potential[self.index(x_1, y_1)])
+                        / (steps * self.cell_size)
+                };
+                field[(y * size + x) as usize] = -Vec2::new(
+                    difference((x - 1, y), (x + 1, y)),
+                    difference((x, y - 1), (x, y + 1)),
+                );
+            }
+        }
+        field
+    }
+}
+
+pub fn calc_pm_accel(
+    mut particles: Query<(&mut Acceleration, &Transform, &Mass, Has<TestParticle>), With<Particle>>,
+    sim_settings: Res<SimSettings>,
+) {
+    if particles.is_empty() {
+        return;
+    }
+
+    // test particles are only there to be pushed around, so they don't add to the mesh
+    let mut sources = Vec::new();
+    let mut targets = Vec::new();
+    for (_, transform, Mass(mass), test_particle) in particles.iter() {
+        let position = transform.translation.truncate();
+        targets.push(position);
+        if !test_particle {
+            sources.push((position, mass * sim_settings.gravitational_constant));
+        }
+    }
+
+    let mesh = ParticleMesh::new(
+        &sources,
+        &targets,
+        sim_settings.particle_mesh.grid_size,
+        &sim_settings.periodic,
+    );
+
+    particles
+        .par_iter_mut()
+        .for_each(|(mut acceleration, transform, _, _)| {
+            acceleration.0 += mesh.accel_at(transform.translation.truncate());
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ParticleMesh;
+    use crate::simulation::periodic::PeriodicBox;
+    use bevy::prelude::*;
+
+    #[test]
+    fn test_isolated_mesh_matches_point_mass() {
+        let mass = 100.0;
+        let sources = [(Vec2::ZERO, mass)];
+        let targets = [Vec2::new(-100.0, -100.0), Vec2::new(100.0, 100.0)];
+        let mesh = ParticleMesh::new(&sources, &targets, 128, &PeriodicBox::default());
+
+        for position in [Vec2::new(30.0, 0.0), Vec2::new(-20.0, 45.0)] {
+            let exact = -position * mass / position.length().powi(3);
+            let accel = mesh.accel_at(position);
+            assert!(
+                (accel - exact).length() < 0.05 * exact.length(),
+                "mesh gives {accel} at {position}, expected {exact}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_tiny_grid_gives_finite_field() {
+        let sources = [(Vec2::ZERO, 100.0), (Vec2::new(10.0, 5.0), 50.0)];
+        for grid_size in [0, 1, 2, 4] {
+            let mesh = ParticleMesh::new(&sources, &[], grid_size, &PeriodicBox::default());
+            let accel = mesh.accel_at(Vec2::new(5.0, 0.0));
+            assert!(accel.is_finite(), "grid size {grid_size} gives {accel}");
+        }
+    }
+
+    #[test]
+    fn test_periodic_mesh_matches_ewald_sum() {
+        let mass = 100.0;
+        let periodic = PeriodicBox {
+            enabled: true,
+            size: 100.0,
+        };
+        let sources = [(Vec2::ZERO, mass)];
+        let mesh = ParticleMesh::new(&sources, &[], 128, &periodic);
+
+        // a source half a box away pulls equally from both sides
+        let halfway = mesh.accel_at(Vec2::new(50.0, 0.0));
+        assert!(halfway.x.abs() < 1e-3 * mass, "{halfway} half a box away");
+
+        for position in [
+            Vec2::new(20.0, 0.0),
+            Vec2::new(-15.0, 30.0),
+            Vec2::new(35.0, 35.0),
+        ] {
+            let delta = -position;
+            let exact = mass * (delta / delta.length().powi(3) + periodic.ewald_correction(delta));
+            let accel = mesh.accel_at(position);
+            assert!(
+                (accel - exact).length() < 0.05 * mass / delta.length_squared(),
+                "mesh gives {accel} at {position}, ewald sum gives {exact}"
+            );
+        }
+    }
+}
@@ -0,0 +1,230 @@
+use std::f32::consts::PI;
+
+use crate::particle::Particle;
+use crate::simulation::motion::OldPosition;
+use bevy::prelude::*;
+
+use super::SimSettings;
+
+/// Square box centered on the origin that particles wrap around, gravity from every periodic
+/// image of a particle is included with ewald summation
+#[derive(Clone, Copy, Debug)]
+pub struct PeriodicBox {
+    pub enabled: bool,
+    pub size: f32,
+}
+
+impl Default for PeriodicBox {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            size: 500.0,
+        }
+    }
+}
+
+/// Images of the box summed over directly in the real space part of the ewald sum, in each direction
+const EWALD_REAL_IMAGES: i32 = 1;
+/// Wave vectors summed over in the fourier space part of the ewald sum, in each direction
+const EWALD_WAVE_VECTORS: i32 = 3;
+/// Splitting between real and fourier space, in units of one over the box size
+const EWALD_ALPHA: f32 = 3.0;
+
+impl PeriodicBox {
+    /// Put a position back inside the box
+    pub fn wrap(&self, position: Vec2) -> Vec2 {
+        position - self.size * (position / self.size).round()
+    }
+
+    /// Shortest vector between two points when the box wraps around, `delta` is the
+    /// unwrapped difference between them. Does nothing if the box is disabled
+    pub fn minimum_image(&self, delta: Vec2) -> Vec2 {
+        if !self.enabled {
+            return delta;
+        }
+        delta - self.size * (delta / self.size).round()
+    }
+
+    /// Extra acceleration, per unit source mass, on a particle from all the periodic images of a
+    /// source at `delta` (the minimum image) that plain `delta / |delta|^3` gravity misses.
+    ///
+    /// This is the ewald sum for a 1/r potential with 2d periodicity (all particles in the z = 0
+    /// plane) against a uniform background, with the bare nearest image force taken away so the
+    /// usual softened force can still be used up close.
+    pub fn ewald_correction(&self, delta: Vec2) -> Vec2 {
+        let distance = delta.length();
+        if distance < 1e-10 {
+            return Vec2::ZERO;
+        }
+
+        let size = self.size;
+        let alpha = EWALD_ALPHA / size;
+        let mut accel = Vec2::ZERO;
+
+        for x in -EWALD_REAL_IMAGES..=EWALD_REAL_IMAGES {
+            for y in -EWALD_REAL_IMAGES..=EWALD_REAL_IMAGES {
+                let image = delta + Vec2::new(x as f32, y as f32) * size;
+                let image_distance = image.length();
+                let ad = alpha * image_distance;
+                let magnitude = erfc(ad) / (image_distance * image_distance)
+                    + 2.0 * alpha / PI.sqrt() * (-ad * ad).exp() / image_distance;
+                accel += image / image_distance * magnitude;
+            }
+        }
+
+        let area = size * size;
+        for x in -EWALD_WAVE_VECTORS..=EWALD_WAVE_VECTORS {
+            for y in -EWALD_WAVE_VECTORS..=EWALD_WAVE_VECTORS {
+                if x == 0 && y == 0 {
+                    continue;
+                }
+                let wave = Vec2::new(x as f32, y as f32) * 2.0 * PI / size;
+                let k = wave.length();
+                accel +=
+                    2.0 * PI / (area * k) * erfc(k / (2.0 * alpha)) * wave * wave.dot(delta).sin();
+            }
+        }
+
+        accel - delta / (distance * distance * distance)
+    }
+}
+
+/// Points along each side of an [EwaldTable]
+const EWALD_TABLE_POINTS: usize = 65;
+
+/// [PeriodicBox::ewald_correction] sampled on a grid across the box. The correction only
+/// depends on the box size, and working it out directly costs dozens of `erfc` calls, far too
+/// many to make for every pair every step
+#[derive(Default)]
+pub struct EwaldTable {
+    /// Box size the table was built for
+    size: f32,
+    /// Correction at every grid point, row major from the corner with the lowest coordinates
+    corrections: Vec<Vec2>,
+}
+
+impl EwaldTable {
+    pub fn new(periodic: &PeriodicBox) -> Self {
+        let spacing = periodic.size / (EWALD_TABLE_POINTS - 1) as f32;
+        let corner = Vec2::splat(-periodic.size / 2.0);
+        let corrections = (0..EWALD_TABLE_POINTS * EWALD_TABLE_POINTS)
+            .map(|index| {
+                let point = Vec2::new(
+                    (index % EWALD_TABLE_POINTS) as f32,
+                    (index / EWALD_TABLE_POINTS) as f32,
+                );
+                periodic.ewald_correction(corner + point * spacing)
+            })
+            .collect();
+        Self {
+            size: periodic.size,
+            corrections,
+        }
+    }
+
+    /// Rebuild the table if the box has changed size since it was built
+    pub fn update(&mut self, periodic: &PeriodicBox) {
+        if self.corrections.is_empty() || self.size != periodic.size {
+            *self = Self::new(periodic);
+        }
+    }
+
+    /// [PeriodicBox::ewald_correction] at `delta`, which has to be a minimum image, bilinearly
+    /// interpolated from the table
+    pub fn correction(&self, delta: Vec2) -> Vec2 {
+        let last = (EWALD_TABLE_POINTS - 1) as f32;
+        let point = ((delta / self.size + 0.5) * last).clamp(Vec2::ZERO, Vec2::splat(last));
+        let base = point.floor().min(Vec2::splat(last - 1.0));
+        let fraction = point - base;
+        let (x, y) = (base.x as usize, base.y as usize);
+        let at = |x: usize, y: usize| self.corrections[y * EWALD_TABLE_POINTS + x];
+
+        let bottom = at(x, y).lerp(at(x + 1, y), fraction.x);
+        let top = at(x, y + 1).lerp(at(x + 1, y + 1), fraction.x);
+        bottom.lerp(top, fraction.y)
+    }
+}
+
+/// Complementary error function, abramowitz and stegun 7.1.26, good to about 1e-7
+pub fn erfc(x: f32) -> f32 {
+    if x < 0.0 {
+        return 2.0 - erfc(-x);
+    }
+    let t = 1.0 / (1.0 + 0.3275911 * x);
+    let polynomial =
+        t * (0.2548296 + t * (-0.28449674 + t * (1.4214137 + t * (-1.453152 + t * 1.0614054))));
+    polynomial * (-x * x).exp()
+}
+
+/// Wraps particles that left the box back to the other side, moving their old position with them
+/// so their velocity stays the same
+pub fn wrap_particles(
+    mut particles: Query<(&mut Transform, &mut OldPosition), With<Particle>>,
+    sim_settings: Res<SimSettings>,
+) {
+    let periodic = sim_settings.periodic;
+    if !periodic.enabled {
+        return;
+    }
+
+    particles
+        .par_iter_mut()
+        .for_each(|(mut position, mut old_position)| {
+            let pos = position.translation.truncate();
+            let wrapped = periodic.wrap(pos);
+            if wrapped != pos {
+                let shift = (wrapped - pos).extend(0.0);
+                position.translation += shift;
+                old_position.0.translation += shift;
+            }
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EwaldTable, PeriodicBox};
+    use bevy::prelude::*;
+
+    #[test]
+    fn test_ewald_force_vanishes_halfway_across_box() {
+        let periodic = PeriodicBox {
+            enabled: true,
+            size: 100.0,
+        };
+
+        // a source half a box away is pulled on equally from both sides
+        for delta in [Vec2::new(50.0, 0.0), Vec2::new(50.0, 50.0)] {
+            let bare = delta / delta.length().powi(3);
+            let total = bare + periodic.ewald_correction(delta);
+            assert!(
+                total.length() < 1e-3 * bare.length(),
+                "total force {total} at {delta} should be zero"
+            );
+        }
+    }
+
+    #[test]
+    fn test_ewald_table_matches_direct_correction() {
+        let periodic = PeriodicBox {
+            enabled: true,
+            size: 100.0,
+        };
+        let table = EwaldTable::new(&periodic);
+
+        for delta in [
+            Vec2::new(3.7, -1.2),
+            Vec2::new(21.3, 8.9),
+            Vec2::new(-33.1, 40.6),
+            Vec2::new(49.9, -49.9),
+        ] {
+            let exact = periodic.ewald_correction(delta);
+            let bare = delta / delta.length().powi(3);
+            let error = (table.correction(delta) - exact).length();
+            // small next to the force it gets added to, or next to the correction itself far away
+            assert!(
+                error < 1e-2 * bare.length().max(exact.length()),
+                "table is off by {error} at {delta}, exact {exact}"
+            );
+        }
+    }
+}
@@ -0,0 +1,335 @@
+use std::collections::HashSet;
+use std::f64::consts::PI;
+
+use crate::particle::{Mass, Particle, Radius, TestParticle};
+use crate::simulation::motion::{Fixed, OldPosition, PreviousAcceleration};
+use crate::simulation::sph::NeighbourGrid;
+use bevy::math::DVec2;
+use bevy::prelude::*;
+
+use super::SimSettings;
+
+/// Runge-kutta steps per orbit of the regularized oscillator, in fictitious time
+const STEPS_PER_ORBIT: f64 = 128.0;
+/// A binary is let go once its members get this many capture distances apart
+const RELEASE_FACTOR: f32 = 2.0;
+
+#[derive(Clone, Copy, Debug)]
+pub struct RegularizationSettings {
+    pub enabled: bool,
+    /// Bound pairs closer than this are regularized
+    pub capture_distance: f32,
+}
+
+impl Default for RegularizationSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            capture_distance: 10.0,
+        }
+    }
+}
+
+/// Two particles whose relative orbit is integrated with levi-civita regularization instead of
+/// verlet, stored on its own entity like a [super::bonds::Bond]
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Binary {
+    pub a: Entity,
+    pub b: Entity,
+    /// Relative position and velocity of `b` from `a` at full precision, with the positions of
+    /// both that were written from them. Only used while nothing else has moved the particles
+    state: Option<(DVec2, DVec2, [Vec2; 2])>,
+}
+
+impl Binary {
+    pub fn new(a: Entity, b: Entity) -> Self {
+        Self { a, b, state: None }
+    }
+}
+
+fn mul(a: DVec2, b: DVec2) -> DVec2 {
+    a.rotate(b)
+}
+
+fn conj(a: DVec2) -> DVec2 {
+    DVec2::new(a.x, -a.y)
+}
+
+/// Principal square root of a complex number
+fn sqrt(z: DVec2) -> DVec2 {
+    let length = z.length();
+    let re = ((length + z.x) / 2.0).max(0.0).sqrt();
+    let im = ((length - z.x) / 2.0).max(0.0).sqrt();
+    DVec2::new(re, if z.y < 0.0 { -im } else { im })
+}
+
+/// `(u, u', energy, time)`, everything the regularized equations of motion carry
+type State = (DVec2, DVec2, f64, f64);
+
+/// Derivatives with respect to fictitious time `s`, where `dt = |u|^2 ds`
+fn derivatives((u, u_prime, energy, _): State, perturbation: DVec2) -> State {
+    let r = u.length_squared();
+    (
+        u_prime,
+        energy / 2.0 * u + r / 2.0 * mul(conj(u), perturbation),
+        2.0 * mul(u, u_prime).dot(perturbation),
+        r,
+    )
+}
+
+fn add((u, u_prime, energy, time): State, (du, du_prime, de, dt): State, scale: f64) -> State {
+    (
+        u + du * scale,
+        u_prime + du_prime * scale,
+        energy + de * scale,
+        time + dt * scale,
+    )
+}
+
+/// Moves a pair on its relative orbit for `dt`, `position` and `velocity` being those of one body
+/// relative to the other and `perturbation` the difference in the outside acceleration they feel.
+///
+/// With `z = u^2` and `dt = |z| ds` the kepler problem turns into a harmonic oscillator in `u`,
+/// `u'' = (h/2) u + (|u|^2/2) conj(u) P`, which has no singularity when the bodies meet, so even
+/// very eccentric orbits can be followed accurately with fourth order runge-kutta.
+pub fn regularized_step(
+    position: DVec2,
+    velocity: DVec2,
+    total_mass: f64,
+    perturbation: DVec2,
+    dt: f64,
+) -> (DVec2, DVec2) {
+    let distance = position.length();
+    if distance < 1e-12 || dt == 0.0 {
+        return (position + velocity * dt, velocity);
+    }
+
+    let u = sqrt(position);
+    let u_prime = mul(velocity, conj(u)) / 2.0;
+    let energy = velocity.length_squared() / 2.0 - total_mass / distance;
+    let mut state: State = (u, u_prime, energy, 0.0);
+
+    // bound orbits are oscillators with frequency sqrt(-h/2) in fictitious time, and one orbit
+    // of z = u^2 is half an oscillation of u
+    let max_ds = if energy < 0.0 {
+        PI / (-energy / 2.0).sqrt() / STEPS_PER_ORBIT
+    } else {
+        f64::INFINITY
+    };
+
+    for _ in 0..100_000 {
+        let remaining = dt - state.3;
+        if remaining.abs() <= 1e-12 * dt.abs() {
+            break;
+        }
+        let ds = (remaining / state.0.length_squared()).clamp(-max_ds, max_ds);
+
+        let k1 = derivatives(state, perturbation);
+        let k2 = derivatives(add(state, k1, ds / 2.0), perturbation);
+        let k3 = derivatives(add(state, k2, ds / 2.0), perturbation);
+        let k4 = derivatives(add(state, k3, ds), perturbation);
+        state = add(state, k1, ds / 6.0);
+        state = add(state, k2, ds / 3.0);
+        state = add(state, k3, ds / 3.0);
+        state = add(state, k4, ds / 6.0);
+    }
+
+    let (u, u_prime, _, _) = state;
+    let r = u.length_squared();
+    (mul(u, u), 2.0 * mul(u_prime, u) / r)
+}
+
+/// Steps every [Binary] with [regularized_step], overwriting where verlet put its members, then
+/// looks for new bound pairs closer than the capture distance.
+///
+/// Has to run straight after [super::motion::update_particle_positions], the outside acceleration
+/// on each member is what verlet just used minus the pull of its partner as the direct solver
+/// works it out.
+pub fn regularize_binaries(
+    mut commands: Commands,
+    mut binaries: Query<(Entity, &mut Binary)>,
+    mut particles: Query<
+        (
+            Entity,
+            &mut Transform,
+            &mut OldPosition,
+            &PreviousAcceleration,
+            &Mass,
+            &Radius,
+        ),
+        (With<Particle>, Without<Fixed>, Without<TestParticle>),
+    >,
+    sim_settings: Res<SimSettings>,
+    time: Res<Time>,
+) {
+    let settings = sim_settings.regularization;
+    let periodic = sim_settings.periodic;
+    // masses are only ever used as G * m or in ratios, so scale them all by G
+    let g = sim_settings.gravitational_constant;
+    let dt = time.delta_secs();
+    if dt == 0.0 {
+        return;
+    }
+
+    let mut members = HashSet::new();
+    for (entity, mut binary) in binaries.iter_mut() {
+        let Ok(
+            [(_, mut position_a, mut old_a, accel_a, Mass(mass_a), Radius(radius_a)), (_, mut position_b, mut old_b, accel_b, Mass(mass_b), Radius(radius_b))],
+        ) = particles.get_many_mut([binary.a, binary.b])
+        else {
+            commands.entity(entity).despawn();
+            continue;
+        };
+        let (mass_a, mass_b) = (mass_a * g, mass_b * g);
+        let total_mass = mass_a + mass_b;
+        if !settings.enabled || total_mass <= 0.0 {
+            commands.entity(entity).despawn();
+            continue;
+        }
+        members.insert(binary.a);
+        members.insert(binary.b);
+
+        // verlet has just moved both, so the old positions are where this step started
+        let start_a = old_a.0.translation.truncate();
+        let start_b = old_b.0.translation.truncate();
+        let verlet_a = position_a.translation.truncate();
+        // unwrap b next to a, in case the pair straddles the edge of a periodic box
+        let unwrapped_b = start_a + periodic.minimum_image(start_b - start_a);
+        let verlet_b =
+            unwrapped_b + periodic.minimum_image(position_b.translation.truncate() - unwrapped_b);
+
+        let (relative_position, relative_velocity) = match binary.state {
+            Some((position, velocity, written)) if written == [start_a, start_b] => {
+                (position, velocity)
+            }
+            _ => {
+                let velocity_a = (verlet_a - start_a) / dt - accel_a.0 * dt / 2.0;
+                let velocity_b = (verlet_b - unwrapped_b) / dt - accel_b.0 * dt / 2.0;
+                (
+                    (unwrapped_b - start_a).as_dvec2(),
+                    (velocity_b - velocity_a).as_dvec2(),
+                )
+            }
+        };
+
+        // take out the softened pull of the partner the gravity solver already added
+        let delta = relative_position.as_vec2();
+        let distance = delta.length().max(radius_a + radius_b).max(1e-10);
+        let pull = delta / (distance * distance * distance);
+        let outside_a = accel_a.0 - mass_b * pull;
+        let outside_b = accel_b.0 + mass_a * pull;
+
+        let (relative_position, relative_velocity) = regularized_step(
+            relative_position,
+            relative_velocity,
+            total_mass as f64,
+            (outside_b - outside_a).as_dvec2(),
+            dt as f64,
+        );
+
+        // the center of mass isn't affected by the pair's own pull, so verlet got it right
+        let start_center = (start_a * mass_a + unwrapped_b * mass_b) / total_mass;
+        let center = (verlet_a * mass_a + verlet_b * mass_b) / total_mass;
+        let center_velocity = (center - start_center) / dt;
+
+        let offset_a = -relative_position.as_vec2() * mass_b / total_mass;
+        let offset_b = relative_position.as_vec2() * mass_a / total_mass;
+        let new_a = center + offset_a;
+        let new_b = center + offset_b;
+        let velocity_a = center_velocity - relative_velocity.as_vec2() * mass_b / total_mass;
+        let velocity_b = center_velocity + relative_velocity.as_vec2() * mass_a / total_mass;
+
+        position_a.translation = new_a.extend(0.0);
+        position_b.translation = new_b.extend(0.0);
+        old_a.0.translation = (new_a - velocity_a * dt).extend(0.0);
+        old_b.0.translation = (new_b - velocity_b * dt).extend(0.0);
+        binary.state = Some((relative_position, relative_velocity, [new_a, new_b]));
+
+        let energy = relative_velocity.length_squared() / 2.0
+            - total_mass as f64 / relative_position.length();
+        if relative_position.length() as f32 > settings.capture_distance * RELEASE_FACTOR
+            || energy >= 0.0
+        {
+            commands.entity(entity).despawn();
+        }
+    }
+
+    if !settings.enabled {
+        return;
+    }
+
+    let candidates: Vec<(Entity, Vec2, Vec2, f32)> = particles
+        .iter()
+        .filter(|(entity, ..)| !members.contains(entity))
+        .map(|(entity, position, old_position, _, Mass(mass), _)| {
+            let position = position.translation.truncate();
+            let velocity = (position - old_position.0.translation.truncate()) / dt;
+            (entity, position, velocity, mass * g)
+        })
+        .collect();
+    let positions: Vec<Vec2> = candidates
+        .iter()
+        .map(|(_, position, ..)| *position)
+        .collect();
+    let grid = NeighbourGrid::new(&positions, settings.capture_distance, &periodic);
+
+    for (i, (entity_a, position_a, velocity_a, mass_a)) in candidates.iter().enumerate() {
+        if members.contains(entity_a) {
+            continue;
+        }
+        for j in grid.neighbours(*position_a) {
+            let (entity_b, position_b, velocity_b, mass_b) = candidates[j];
+            if j <= i || members.contains(&entity_b) || mass_a + mass_b <= 0.0 {
+                continue;
+            }
+            let distance = periodic.minimum_image(position_b - *position_a).length();
+            let energy =
+                (velocity_b - *velocity_a).length_squared() / 2.0 - (mass_a + mass_b) / distance;
+            if distance < settings.capture_distance && energy < 0.0 {
+                commands.spawn(Binary::new(*entity_a, entity_b));
+                members.insert(*entity_a);
+                members.insert(entity_b);
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::regularized_step;
+    use bevy::math::DVec2;
+    use std::f64::consts::PI;
+
+    #[test]
+    fn test_eccentric_binary_keeps_energy_with_large_steps() {
+        let total_mass = 100.0;
+        // released from apoapsis of an e = 0.95 orbit
+        let semi_major_axis: f64 = 10.0;
+        let eccentricity = 0.95;
+        let apoapsis = semi_major_axis * (1.0 + eccentricity);
+        let speed: f64 = (total_mass * (1.0 - eccentricity) / apoapsis).sqrt();
+        let period = 2.0 * PI * (semi_major_axis.powi(3) / total_mass).sqrt();
+
+        let start = DVec2::new(apoapsis, 0.0);
+        let mut position = start;
+        let mut velocity = DVec2::new(0.0, speed);
+        let energy = |position: DVec2, velocity: DVec2| {
+            velocity.length_squared() / 2.0 - total_mass / position.length()
+        };
+        let initial = energy(position, velocity);
+
+        // 10 steps per orbit, each one passing straight through periapsis at times
+        for _ in 0..1000 {
+            (position, velocity) =
+                regularized_step(position, velocity, total_mass, DVec2::ZERO, period / 10.0);
+        }
+
+        let drift = ((energy(position, velocity) - initial) / initial).abs();
+        assert!(drift < 1e-5, "energy drifted by {drift}");
+        assert!(
+            position.distance(start) < 1e-3 * apoapsis,
+            "ended at {position}, expected {start}"
+        );
+    }
+}
@@ -0,0 +1,168 @@
+use std::collections::HashSet;
+
+use crate::particle::{Mass, Particle, Radius, TestParticle};
+use crate::simulation::motion::{Fixed, OldPosition};
+use bevy::prelude::*;
+
+use super::SimSettings;
+
+/// A black hole or star that swallows every particle that comes within its accretion radius
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Sink {
+    pub accretion_radius: f32,
+    /// If true the sink radius grows with its mass, keeping the same density
+    pub grow_radius: bool,
+    /// Total mass this sink has swallowed
+    pub accreted_mass: f32,
+}
+
+impl Sink {
+    pub fn new(accretion_radius: f32) -> Self {
+        Self {
+            accretion_radius,
+            grow_radius: true,
+            accreted_mass: 0.0,
+        }
+    }
+}
+
+/// Total mass swallowed by every sink since the simulation started
+#[derive(Resource, Default)]
+pub struct AccretedMass(pub f32);
+
+pub fn accrete_particles(
+    mut commands: Commands,
+    mut sinks: Query<
+        (
+            &mut Sink,
+            &mut Mass,
+            &mut Radius,
+            &mut Transform,
+            &mut OldPosition,
+        ),
+        With<Particle>,
+    >,
+    particles: Query<
+        (Entity, &Transform, &OldPosition, &Mass, Has<TestParticle>),
+        (With<Particle>, Without<Sink>, Without<Fixed>),
+    >,
+    mut accreted_mass: ResMut<AccretedMass>,
+    sim_settings: Res<SimSettings>,
+) {
+    let mut swallowed = HashSet::new();
+
+    for (mut sink, mut sink_mass, mut sink_radius, mut sink_position, mut sink_old_position) in
+        sinks.iter_mut()
+    {
+        let position = sink_position.translation.truncate();
+        // velocity here is the distance moved per step, the same as everywhere else
+        let mut momentum =
+            sink_mass.0 * (sink_position.translation - sink_old_position.0.translation).truncate();
+        let mut mass = sink_mass.0;
+
+        for (entity, transform, old_position, Mass(particle_mass), test_particle) in
+            particles.iter()
+        {
+            let offset = sim_settings
+                .periodic
+                .minimum_image(transform.translation.truncate() - position);
+            if swallowed.contains(&entity) || offset.length() > sink.accretion_radius {
+                continue;
+            }
+
+            swallowed.insert(entity);
+            commands.entity(entity).despawn();
+
+            // test particles are massless, they just disappear
+            if test_particle {
+                continue;
+            }
+
+            let velocity = (transform.translation - old_position.0.translation).truncate();
+            momentum += particle_mass * velocity;
+            mass += particle_mass;
+            sink.accreted_mass += particle_mass;
+            accreted_mass.0 += particle_mass;
+        }
+
+        if mass == sink_mass.0 {
+            continue;
+        }
+
+        if sink.grow_radius && sink_mass.0 > 0.0 && mass > 0.0 {
+            sink_radius.0 *= (mass / sink_mass.0).sqrt();
+            sink_position.scale = Vec3::splat(sink_radius.0);
+        }
+
+        sink_mass.0 = mass;
+        if mass != 0.0 {
+            sink_old_position.0.translation =
+                sink_position.translation - (momentum / mass).extend(0.0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{accrete_particles, AccretedMass, Sink};
+    use crate::particle::{Mass, Particle, ParticleBundle, Radius};
+    use crate::simulation::motion::OldPosition;
+    use crate::simulation::SimSettings;
+    use bevy::prelude::*;
+
+    #[test]
+    fn test_accretion_conserves_mass_and_momentum() {
+        let mut app = App::new();
+        app.init_resource::<SimSettings>()
+            .init_resource::<AccretedMass>()
+            .add_systems(Update, accrete_particles);
+
+        let sink = app
+            .world_mut()
+            .spawn((
+                ParticleBundle::new()
+                    .velocity(Vec2::new(1.0, 0.0))
+                    .mass(100.0)
+                    .radius(2.0),
+                Sink::new(5.0),
+            ))
+            .id();
+        let swallowed = app
+            .world_mut()
+            .spawn(
+                ParticleBundle::new()
+                    .position(Vec2::new(3.0, 0.0))
+                    .velocity(Vec2::new(0.0, 2.0))
+                    .mass(10.0),
+            )
+            .id();
+        let far = app
+            .world_mut()
+            .spawn(ParticleBundle::new().position(Vec2::new(20.0, 0.0)))
+            .id();
+
+        app.update();
+
+        assert!(app.world().get_entity(swallowed).is_err());
+        assert!(app.world().get_entity(far).is_ok());
+
+        let world = app.world();
+        assert_eq!(world.get::<Mass>(sink).unwrap().0, 110.0);
+        assert_eq!(world.get::<Sink>(sink).unwrap().accreted_mass, 10.0);
+        assert_eq!(world.resource::<AccretedMass>().0, 10.0);
+        let radius = world.get::<Radius>(sink).unwrap().0;
+        assert!((radius - 2.0 * 1.1_f32.sqrt()).abs() < 1e-5);
+
+        let position = world.get::<Transform>(sink).unwrap().translation;
+        let old_position = world.get::<OldPosition>(sink).unwrap().0.translation;
+        let momentum = 110.0 * (position - old_position).truncate();
+        assert!(
+            momentum.distance(Vec2::new(100.0, 20.0)) < 1e-3,
+            "{momentum}"
+        );
+
+        let mut masses = app.world_mut().query_filtered::<&Mass, With<Particle>>();
+        let total: f32 = masses.iter(app.world()).map(|Mass(mass)| mass).sum();
+        assert_eq!(total, 111.0);
+    }
+}
@@ -0,0 +1,408 @@
+use std::collections::HashMap;
+use std::f32::consts::PI;
+
+use crate::particle::{Mass, Particle};
+use crate::simulation::motion::{Acceleration, OldPosition};
+use bevy::prelude::*;
+
+use super::periodic::PeriodicBox;
+use super::SimSettings;
+
+/// Marks a particle as gas, its hydrodynamic state is updated every step by [calc_sph_accel]
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Gas {
+    pub density: f32,
+    pub pressure: f32,
+    /// Specific internal energy, the temperature of the gas
+    pub internal_energy: f32,
+}
+
+impl Gas {
+    pub fn new(internal_energy: f32) -> Self {
+        Self {
+            density: 0.0,
+            pressure: 0.0,
+            internal_energy,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct SphSettings {
+    /// Kernel smoothing length, particles interact out to twice this distance
+    pub smoothing_length: f32,
+    /// Adiabatic index of the ideal gas equation of state
+    pub gamma: f32,
+    /// Linear artificial viscosity coefficient
+    pub viscosity_alpha: f32,
+    /// Quadratic artificial viscosity coefficient, stops particles passing through shocks
+    pub viscosity_beta: f32,
+}
+
+impl Default for SphSettings {
+    fn default() -> Self {
+        Self {
+            smoothing_length: 5.0,
+            gamma: 5.0 / 3.0,
+            viscosity_alpha: 1.0,
+            viscosity_beta: 2.0,
+        }
+    }
+}
+
+/// 2d cubic spline kernel
+pub fn kernel(distance: f32, h: f32) -> f32 {
+    let sigma = 10.0 / (7.0 * PI * h * h);
+    let q = distance / h;
+    if q < 1.0 {
+        sigma * (1.0 - 1.5 * q * q + 0.75 * q * q * q)
+    } else if q < 2.0 {
+        sigma * 0.25 * (2.0 - q).powi(3)
+    } else {
+        0.0
+    }
+}
+
+/// Derivative of [kernel] with respect to distance
+pub fn kernel_derivative(distance: f32, h: f32) -> f32 {
+    let sigma = 10.0 / (7.0 * PI * h * h);
+    let q = distance / h;
+    if q < 1.0 {
+        sigma / h * (-3.0 * q + 2.25 * q * q)
+    } else if q < 2.0 {
+        sigma / h * -0.75 * (2.0 - q).powi(2)
+    } else {
+        0.0
+    }
+}
+
+/// Uniform grid used to find every particle within a cell width of a point
+pub struct NeighbourGrid {
+    cell_size: f32,
+    cells: HashMap<IVec2, Vec<usize>>,
+    /// Cells per side of the periodic box, if the grid wraps around
+    wrap: Option<i32>,
+}
+
+impl NeighbourGrid {
+    pub fn new(positions: &[Vec2], cell_size: f32, periodic: &PeriodicBox) -> Self {
+        let mut grid = Self {
+            cell_size,
+            cells: HashMap::new(),
+            wrap: None,
+        };
+
+        if periodic.enabled {
+            // cells have to tile the box exactly, so they get a little bigger
+            let cells_per_side = (periodic.size / cell_size).floor().max(1.0);
+            grid.cell_size = periodic.size / cells_per_side;
+            grid.wrap = Some(cells_per_side as i32);
+        }
+
+        for (index, position) in positions.iter().enumerate() {
+            let cell = grid.cell(*position);
+            grid.cells.entry(cell).or_default().push(index);
+        }
+        grid
+    }
+
+    fn cell(&self, position: Vec2) -> IVec2 {
+        match self.wrap {
+            Some(cells_per_side) => {
+                let size = self.cell_size * cells_per_side as f32;
+                let cell = ((position + size / 2.0) / self.cell_size)
+                    .floor()
+                    .as_ivec2();
+                self.wrap_cell(cell, cells_per_side)
+            }
+            None => (position / self.cell_size).floor().as_ivec2(),
+        }
+    }
+
+    fn wrap_cell(&self, cell: IVec2, cells_per_side: i32) -> IVec2 {
+        IVec2::new(
+            cell.x.rem_euclid(cells_per_side),
+            cell.y.rem_euclid(cells_per_side),
+        )
+    }
+
+    /// Indices of all the particles in the 3x3 block of cells around `position`
+    pub fn neighbours(&self, position: Vec2) -> impl Iterator<Item = usize> + '_ {
+        let center = self.cell(position);
+        let mut cells: Vec<IVec2> = Vec::with_capacity(9);
+        for x in -1..=1 {
+            for y in -1..=1 {
+                let mut cell = center + IVec2::new(x, y);
+                if let Some(cells_per_side) = self.wrap {
+                    cell = self.wrap_cell(cell, cells_per_side);
+                }
+                // small periodic boxes wrap onto the same cell more than once
+                if !cells.contains(&cell) {
+                    cells.push(cell);
+                }
+            }
+        }
+
+        cells
+            .into_iter()
+            .filter_map(|cell| self.cells.get(&cell))
+            .flatten()
+            .copied()
+    }
+}
+
+pub fn calc_sph_accel(
+    mut query: Query<
+        (&mut Acceleration, &mut Gas, &Transform, &OldPosition, &Mass),
+        With<Particle>,
+    >,
+    sim_settings: Res<SimSettings>,
+    time: Res<Time>,
+) {
+    let dt = time.delta_secs();
+    if query.is_empty() || dt == 0.0 {
+        return;
+    }
+
+    let settings = sim_settings.sph;
+    let h = settings.smoothing_length.max(1e-3);
+    let gamma = settings.gamma;
+
+    let positions: Vec<Vec2> = query
+        .iter()
+        .map(|(_, _, transform, _, _)| transform.translation.truncate())
+        .collect();
+    let velocities: Vec<Vec2> = query
+        .iter()
+        .map(|(_, _, transform, old, _)| {
+            (transform.translation - old.0.translation).truncate() / dt
+        })
+        .collect();
+    let masses: Vec<f32> = query.iter().map(|(_, _, _, _, mass)| mass.0).collect();
+    let energies: Vec<f32> = query
+        .iter()
+        .map(|(_, gas, _, _, _)| gas.internal_energy.max(0.0))
+        .collect();
+
+    let periodic = sim_settings.periodic;
+    let grid = NeighbourGrid::new(&positions, 2.0 * h, &periodic);
+
+    let densities: Vec<f32> = positions
+        .iter()
+        .map(|position| {
+            grid.neighbours(*position)
+                .map(|j| {
+                    let delta = periodic.minimum_image(positions[j] - *position);
+                    masses[j] * kernel(delta.length(), h)
+                })
+                .sum::<f32>()
+        })
+        .collect();
+
+    let pressures: Vec<f32> = densities
+        .iter()
+        .zip(&energies)
+        .map(|(density, energy)| (gamma - 1.0) * density * energy)
+        .collect();
+
+    let sound_speeds: Vec<f32> = pressures
+        .iter()
+        .zip(&densities)
+        .map(|(pressure, density)| {
+            if *density > 0.0 {
+                (gamma * pressure / density).sqrt()
+            } else {
+                0.0
+            }
+        })
+        .collect();
+
+    for (i, (mut acceleration, mut gas, _, _, _)) in query.iter_mut().enumerate() {
+        let density_i = densities[i];
+        if density_i <= 0.0 {
+            continue;
+        }
+        let pressure_term_i = pressures[i] / (density_i * density_i);
+
+        let mut accel = Vec2::ZERO;
+        let mut energy_rate = 0.0;
+
+        for j in grid.neighbours(positions[i]) {
+            if i == j || densities[j] <= 0.0 {
+                continue;
+            }
+
+            let delta = periodic.minimum_image(positions[i] - positions[j]);
+            let distance = delta.length();
+            if distance < 1e-10 || distance >= 2.0 * h {
+                continue;
+            }
+
+            let relative_velocity = velocities[i] - velocities[j];
+            let approach = relative_velocity.dot(delta);
+
+            // monaghan artificial viscosity, only acts on particles moving towards each other
+            let mut viscosity = 0.0;
+            if approach < 0.0 {
+                let mu = h * approach / (distance * distance + 0.01 * h * h);
+                let mean_sound_speed = (sound_speeds[i] + sound_speeds[j]) / 2.0;
+                let mean_density = (density_i + densities[j]) / 2.0;
+                viscosity = (-settings.viscosity_alpha * mean_sound_speed * mu
+                    + settings.viscosity_beta * mu * mu)
+                    / mean_density;
+            }
+
+            let pressure_term_j = pressures[j] / (densities[j] * densities[j]);
+            let gradient = kernel_derivative(distance, h) * delta / distance;
+
+            accel -= masses[j] * (pressure_term_i + pressure_term_j + viscosity) * gradient;
+            energy_rate += 0.5
+                * masses[j]
+                * (pressure_term_i + pressure_term_j + viscosity)
+                * relative_velocity.dot(gradient);
+        }
+
+        acceleration.0 += accel;
+        gas.density = density_i;
+        gas.pressure = pressures[i];
+        gas.internal_energy = (energies[i] + energy_rate * dt).max(0.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{calc_sph_accel, kernel, Gas, NeighbourGrid};
+    use crate::particle::ParticleBundle;
+    use crate::simulation::motion::Acceleration;
+    use crate::simulation::periodic::PeriodicBox;
+    use crate::simulation::SimSettings;
+    use bevy::prelude::*;
+    use std::f32::consts::PI;
+    use std::time::Duration;
+
+    #[test]
+    fn test_kernel_is_normalised() {
+        let h = 3.0;
+        let steps = 10000;
+        let width = 2.0 * h / steps as f32;
+        let integral: f32 = (0..steps)
+            .map(|i| {
+                let r = (i as f32 + 0.5) * width;
+                kernel(r, h) * 2.0 * PI * r * width
+            })
+            .sum();
+        assert!(
+            (integral - 1.0).abs() < 1e-3,
+            "kernel integrates to {integral}"
+        );
+    }
+
+    #[test]
+    fn test_uniform_lattice_has_reference_density() {
+        let spacing = 4.0;
+        let mass = 2.0;
+        let side = 20;
+        let periodic = PeriodicBox {
+            enabled: true,
+            size: spacing * side as f32,
+        };
+
+        let mut app = App::new();
+        let mut settings = SimSettings {
+            periodic,
+            ..default()
+        };
+        settings.sph.smoothing_length = 1.2 * spacing;
+        app.insert_resource(settings)
+            .init_resource::<Time>()
+            .add_systems(Update, calc_sph_accel);
+        app.world_mut()
+            .resource_mut::<Time>()
+            .advance_by(Duration::from_secs_f32(0.01));
+
+        // fills the box exactly, so there are no edges to thin out the density
+        let mut entities = Vec::new();
+        for x in 0..side {
+            for y in 0..side {
+                let position =
+                    (Vec2::new(x as f32, y as f32) + 0.5) * spacing - periodic.size / 2.0;
+                let particle = ParticleBundle::new().position(position).mass(mass);
+                entities.push(app.world_mut().spawn((particle, Gas::new(1.0))).id());
+            }
+        }
+        app.update();
+
+        let reference = mass / (spacing * spacing);
+        for entity in entities {
+            let density = app.world().get::<Gas>(entity).unwrap().density;
+            assert!(
+                (density - reference).abs() < 0.02 * reference,
+                "density {density}, expected {reference}"
+            );
+            // pressure is the same everywhere, so it pushes nothing around
+            let acceleration = app.world().get::<Acceleration>(entity).unwrap().0;
+            assert!(
+                acceleration.length() < 1e-3,
+                "accelerated by {acceleration}"
+            );
+        }
+    }
+
+    /// Acceleration of the left particle of a gas pair 4 apart, closing at `closing_speed`
+    fn pair_acceleration(closing_speed: f32) -> Vec2 {
+        let mut app = App::new();
+        app.init_resource::<SimSettings>()
+            .init_resource::<Time>()
+            .add_systems(Update, calc_sph_accel);
+        let dt = 0.01;
+        app.world_mut()
+            .resource_mut::<Time>()
+            .advance_by(Duration::from_secs_f32(dt));
+
+        let velocity = Vec2::new(closing_speed / 2.0 * dt, 0.0);
+        let left = ParticleBundle::new().velocity(velocity);
+        let right = ParticleBundle::new()
+            .position(Vec2::new(4.0, 0.0))
+            .velocity(-velocity);
+        let left = app.world_mut().spawn((left, Gas::new(1.0))).id();
+        app.world_mut().spawn((right, Gas::new(1.0)));
+        app.update();
+
+        app.world().get::<Acceleration>(left).unwrap().0
+    }
+
+    #[test]
+    fn test_pressure_and_viscosity_push_pair_apart() {
+        let at_rest = pair_acceleration(0.0);
+        assert!(at_rest.x < 0.0, "pressure gave {at_rest}");
+        assert_eq!(at_rest.y, 0.0);
+
+        // viscosity only adds to it when they are moving towards each other
+        let approaching = pair_acceleration(10.0);
+        assert!(approaching.x < at_rest.x, "{approaching} vs {at_rest}");
+        let separating = pair_acceleration(-10.0);
+        assert_eq!(separating, at_rest);
+    }
+
+    #[test]
+    fn test_neighbour_grid_wraps_around_periodic_box() {
+        let periodic = PeriodicBox {
+            enabled: true,
+            size: 100.0,
+        };
+        let positions = [
+            Vec2::new(-49.0, 0.0),
+            Vec2::new(49.0, 0.0),
+            Vec2::new(0.0, 0.0),
+        ];
+        let grid = NeighbourGrid::new(&positions, 10.0, &periodic);
+
+        let neighbours: Vec<usize> = grid.neighbours(positions[0]).collect();
+        assert!(neighbours.contains(&1));
+        assert!(!neighbours.contains(&2));
+
+        // without the box they are too far apart
+        let grid = NeighbourGrid::new(&positions, 10.0, &PeriodicBox::default());
+        assert!(!grid.neighbours(positions[0]).any(|index| index == 1));
+    }
+}
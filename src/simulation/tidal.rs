@@ -0,0 +1,254 @@
+use std::f32::consts::PI;
+
+use crate::particle::{Mass, Particle, ParticleBundle, Radius, Species, TestParticle};
+use crate::simulation::motion::{Fixed, OldPosition};
+use crate::simulation::sinks::Sink;
+use crate::simulation::sph::Gas;
+use bevy::prelude::*;
+
+use super::SimSettings;
+
+#[derive(Clone, Copy, Debug)]
+pub struct TidalSettings {
+    pub enabled: bool,
+    /// How many pieces a disrupted particle breaks into
+    pub fragments: u32,
+    /// Mass per area of the fragments, this sets their radius
+    pub fragment_density: f32,
+    /// A body has to be this many times heavier than a particle to tear it apart
+    pub mass_ratio: f32,
+    /// Particles whose fragments would be lighter than this are left alone
+    pub min_fragment_mass: f32,
+}
+
+impl Default for TidalSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            fragments: 4,
+            fragment_density: 1.0,
+            mass_ratio: 100.0,
+            min_fragment_mass: 0.01,
+        }
+    }
+}
+
+/// Sent every time a particle is torn apart inside the roche limit of a heavier body
+#[derive(Event, Clone, Copy, Debug)]
+pub struct TidalDisruption {
+    pub particle: Entity,
+    pub primary: Entity,
+    pub position: Vec2,
+    pub mass: f32,
+    pub fragments: u32,
+}
+
+/// A piece of a disrupted particle. Fragments are never torn apart again, as they are usually
+/// still well inside their own roche limit and would otherwise keep splitting until they hit
+/// [TidalSettings::min_fragment_mass]
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct TidalFragment;
+
+/// Rigid body roche limit of a particle with `radius` and `mass` around a `primary_mass`
+pub fn roche_limit(radius: f32, mass: f32, primary_mass: f32) -> f32 {
+    radius * (2.0 * primary_mass / mass).cbrt()
+}
+
+pub fn tidal_disruption(
+    mut commands: Commands,
+    particles: Query<
+        (
+            Entity,
+            &Transform,
+            &OldPosition,
+            &Mass,
+            &Radius,
+            &Species,
+            Has<Fixed>,
+        ),
+        (
+            With<Particle>,
+            Without<TestParticle>,
+            Without<Gas>,
+            Without<Sink>,
+            Without<TidalFragment>,
+        ),
+    >,
+    primaries: Query<(Entity, &Transform, &Mass), (With<Particle>, Without<TestParticle>)>,
+    sim_settings: Res<SimSettings>,
+    mut disruptions: EventWriter<TidalDisruption>,
+) {
+    let settings = sim_settings.tidal;
+    if !settings.enabled || settings.fragments < 2 {
+        return;
+    }
+
+    for (entity, transform, old_position, Mass(mass), Radius(radius), species, fixed) in
+        particles.iter()
+    {
+        let fragment_mass = mass / settings.fragments as f32;
+        if fixed || *mass <= 0.0 || fragment_mass < settings.min_fragment_mass {
+            continue;
+        }
+
+        let position = transform.translation.truncate();
+
+        // offset from the nearest image of the primary, which may be across the periodic box
+        let primary = primaries
+            .iter()
+            .filter(|(_, _, Mass(primary_mass))| *primary_mass >= mass * settings.mass_ratio)
+            .find_map(|(primary, primary_transform, Mass(primary_mass))| {
+                let offset = sim_settings
+                    .periodic
+                    .minimum_image(position - primary_transform.translation.truncate());
+                (offset.length() < roche_limit(*radius, *mass, *primary_mass))
+                    .then_some((primary, offset))
+            });
+
+        let Some((primary, offset)) = primary else {
+            continue;
+        };
+
+        // every fragment keeps the parents velocity and they sit evenly around its center, so
+        // mass, momentum and center of mass all stay the same. The first one points away from
+        // the primary, along the direction the tide stretches the particle
+        let velocity = (transform.translation - old_position.0.translation).truncate();
+        let fragment_radius = (fragment_mass / (PI * settings.fragment_density.max(1e-6))).sqrt();
+        let spread = (radius - fragment_radius).max(fragment_radius);
+        let start_angle = offset.to_angle();
+
+        for i in 0..settings.fragments {
+            let angle = start_angle + 2.0 * PI * i as f32 / settings.fragments as f32;
+            let fragment = ParticleBundle::new()
+                .position(position + Vec2::from_angle(angle) * spread)
+                .velocity(velocity)
+                .mass(fragment_mass)
+                .radius(fragment_radius)
+                .species(species.0)
+                .spawn(&mut commands);
+            commands.entity(fragment).insert(TidalFragment);
+        }
+
+        commands.entity(entity).despawn();
+
+        disruptions.write(TidalDisruption {
+            particle: entity,
+            primary,
+            position,
+            mass: *mass,
+            fragments: settings.fragments,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{tidal_disruption, TidalDisruption, TidalFragment};
+    use crate::particle::{Mass, Particle, ParticleBundle};
+    use crate::simulation::sinks::Sink;
+    use crate::simulation::SimSettings;
+    use bevy::prelude::*;
+
+    #[test]
+    fn test_only_particles_inside_roche_limit_fragment() {
+        let mut settings = SimSettings::default();
+        settings.tidal.enabled = true;
+        settings.periodic.enabled = true;
+        settings.periodic.size = 200.0;
+
+        let mut app = App::new();
+        app.insert_resource(settings)
+            .add_event::<TidalDisruption>()
+            .add_systems(Update, tidal_disruption);
+
+        let particle = |x: f32, y: f32, mass: f32, radius: f32| {
+            ParticleBundle::new()
+                .position(Vec2::new(x, y))
+                .mass(mass)
+                .radius(radius)
+        };
+        // the roche limit of the small particles is about 25 away from the primary
+        let primary = app.world_mut().spawn(particle(90.0, 0.0, 1000.0, 5.0)).id();
+        // 15 away once the box wraps around
+        let inside = app.world_mut().spawn(particle(-95.0, 0.0, 1.0, 2.0)).id();
+        let outside = app.world_mut().spawn(particle(90.0, 40.0, 1.0, 2.0)).id();
+
+        app.update();
+
+        assert!(app.world().get_entity(inside).is_err());
+        assert!(app.world().get_entity(outside).is_ok());
+
+        let mut masses = app.world_mut().query_filtered::<&Mass, With<Particle>>();
+        let masses: Vec<f32> = masses.iter(app.world()).map(|Mass(mass)| *mass).collect();
+        assert_eq!(masses.len(), 2 + 4);
+        assert!((masses.iter().sum::<f32>() - 1002.0).abs() < 1e-3);
+
+        let events = app.world().resource::<Events<TidalDisruption>>();
+        let disruptions: Vec<_> = events.iter_current_update_events().collect();
+        assert_eq!(disruptions.len(), 1);
+        assert_eq!(disruptions[0].particle, inside);
+        assert_eq!(disruptions[0].primary, primary);
+        assert_eq!(disruptions[0].fragments, 4);
+    }
+
+    #[test]
+    fn test_fragments_are_not_disrupted_again() {
+        let mut settings = SimSettings::default();
+        settings.tidal.enabled = true;
+
+        let mut app = App::new();
+        app.insert_resource(settings)
+            .add_event::<TidalDisruption>()
+            .add_systems(Update, tidal_disruption);
+
+        // a default particle just inside its roche limit of about 5.8, its fragments come out
+        // bigger and each well inside their own
+        app.world_mut()
+            .spawn(ParticleBundle::new().mass(10000.0).radius(5.0));
+        app.world_mut().spawn(
+            ParticleBundle::new()
+                .position(Vec2::new(5.0, 0.0))
+                .mass(100.0),
+        );
+
+        for _ in 0..6 {
+            app.update();
+        }
+
+        let mut fragments = app
+            .world_mut()
+            .query_filtered::<(), (With<Particle>, With<TidalFragment>)>();
+        assert_eq!(fragments.iter(app.world()).count(), 4);
+        let mut particles = app.world_mut().query_filtered::<(), With<Particle>>();
+        assert_eq!(particles.iter(app.world()).count(), 5);
+    }
+
+    #[test]
+    fn test_sinks_are_not_disrupted() {
+        let mut settings = SimSettings::default();
+        settings.tidal.enabled = true;
+
+        let mut app = App::new();
+        app.insert_resource(settings)
+            .add_event::<TidalDisruption>()
+            .add_systems(Update, tidal_disruption);
+
+        app.world_mut()
+            .spawn(ParticleBundle::new().mass(10000.0).radius(5.0));
+        let sink = app
+            .world_mut()
+            .spawn((
+                ParticleBundle::new()
+                    .position(Vec2::new(5.0, 0.0))
+                    .mass(100.0),
+                Sink::new(1.0),
+            ))
+            .id();
+
+        app.update();
+
+        assert!(app.world().get::<Sink>(sink).is_some());
+        let events = app.world().resource::<Events<TidalDisruption>>();
+        assert_eq!(events.iter_current_update_events().count(), 0);
+    }
+}
@@ -0,0 +1,145 @@
+use bevy::prelude::*;
+
+use super::SimSettings;
+
+/// Gauss's gravitational constant, the square root of G in AU, solar masses and days
+pub const GAUSSIAN_GRAVITATIONAL_CONSTANT: f64 = 0.01720209895;
+
+/// What one unit of mass, length and time stands for. A second of simulation is one unit of
+/// time, and G follows from the rest. Changing it doesn't convert particles that are already
+/// there, only G and how numbers are labelled.
+#[derive(Resource, PartialEq, Debug, Copy, Clone, Default)]
+pub enum UnitSystem {
+    /// Plain numbers with G = 1
+    #[default]
+    Dimensionless,
+    /// Metres, kilograms and seconds
+    Si,
+    /// AU, solar masses and julian years
+    Astronomical,
+    /// AU, solar masses and days, short enough steps to follow moons
+    Planetary,
+    /// Kiloparsecs, 10^10 solar masses and gigayears
+    Galactic,
+}
+
+/// Kinds of value the gui puts unit labels on
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum Quantity {
+    Mass,
+    Length,
+    Time,
+    Velocity,
+}
+
+impl UnitSystem {
+    pub const ALL: [UnitSystem; 5] = [
+        UnitSystem::Dimensionless,
+        UnitSystem::Si,
+        UnitSystem::Astronomical,
+        UnitSystem::Planetary,
+        UnitSystem::Galactic,
+    ];
+
+    /// Newton's constant in these units
+    pub fn gravitational_constant(&self) -> f64 {
+        match self {
+            UnitSystem::Dimensionless => 1.0,
+            UnitSystem::Si => 6.674_30e-11,
+            UnitSystem::Astronomical => {
+                (GAUSSIAN_GRAVITATIONAL_CONSTANT * 365.25)
+                    * (GAUSSIAN_GRAVITATIONAL_CONSTANT * 365.25)
+            }
+            UnitSystem::Planetary => {
+                GAUSSIAN_GRAVITATIONAL_CONSTANT * GAUSSIAN_GRAVITATIONAL_CONSTANT
+            }
+            // 4.300917e-6 kpc (km/s)^2 per solar mass, with a km/s being 1.0227 kpc per Gyr
+            UnitSystem::Galactic => 4.498_502e4,
+        }
+    }
+
+    /// Length of the time unit in SI seconds, none when time has no physical meaning
+    pub fn seconds_per_time_unit(&self) -> Option<f64> {
+        match self {
+            UnitSystem::Dimensionless => None,
+            UnitSystem::Si => Some(1.0),
+            UnitSystem::Astronomical => Some(365.25 * 86400.0),
+            UnitSystem::Planetary => Some(86400.0),
+            UnitSystem::Galactic => Some(365.25 * 86400.0 * 1e9),
+        }
+    }
+
+    /// Symbol for the unit of `quantity`, empty when dimensionless
+    pub fn unit(&self, quantity: Quantity) -> &'static str {
+        let (mass, length, time, velocity) = match self {
+            UnitSystem::Dimensionless => ("", "", "", ""),
+            UnitSystem::Si => ("kg", "m", "s", "m/s"),
+            UnitSystem::Astronomical => ("Msun", "AU", "yr", "AU/yr"),
+            UnitSystem::Planetary => ("Msun", "AU", "day", "AU/day"),
+            UnitSystem::Galactic => ("1e10 Msun", "kpc", "Gyr", "kpc/Gyr"),
+        };
+        match quantity {
+            Quantity::Mass => mass,
+            Quantity::Length => length,
+            Quantity::Time => time,
+            Quantity::Velocity => velocity,
+        }
+    }
+
+    /// `name` with the unit of `quantity` after it in brackets
+    pub fn label(&self, name: &str, quantity: Quantity) -> String {
+        match self.unit(quantity) {
+            "" => name.to_string(),
+            unit => format!("{name} ({unit})"),
+        }
+    }
+
+    /// `value` followed by the unit of `quantity`
+    pub fn format(&self, value: f64, quantity: Quantity) -> String {
+        format!("{value:.1} {}", self.unit(quantity))
+            .trim_end()
+            .to_string()
+    }
+}
+
+impl std::fmt::Display for UnitSystem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UnitSystem::Dimensionless => write!(f, "dimensionless"),
+            UnitSystem::Si => write!(f, "SI (m, kg, s)"),
+            UnitSystem::Astronomical => write!(f, "astronomical (AU, Msun, yr)"),
+            UnitSystem::Planetary => write!(f, "planetary (AU, Msun, day)"),
+            UnitSystem::Galactic => write!(f, "galactic (kpc, 1e10 Msun, Gyr)"),
+        }
+    }
+}
+
+/// Keeps G in the simulation settings in step with the chosen units
+pub fn apply_unit_system(units: Res<UnitSystem>, mut settings: ResMut<SimSettings>) {
+    settings.gravitational_constant = units.gravitational_constant() as f32;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::UnitSystem;
+
+    #[test]
+    fn test_gravitational_constants_agree_with_si() {
+        // in SI, a solar mass is 1.988_47e30 kg, an AU is 1.495_978_707e11 m and a kpc is
+        // 3.085_677_58e19 m
+        let si = UnitSystem::Si.gravitational_constant();
+        for (units, mass, length) in [
+            (UnitSystem::Astronomical, 1.988_47e30, 1.495_978_707e11),
+            (UnitSystem::Planetary, 1.988_47e30, 1.495_978_707e11),
+            (UnitSystem::Galactic, 1.988_47e40, 3.085_677_58e19),
+        ] {
+            let seconds = units.seconds_per_time_unit().unwrap();
+            let expected = si * mass * seconds * seconds / (length * length * length);
+            let g = units.gravitational_constant();
+            assert!(
+                (g - expected).abs() < 1e-3 * expected,
+                "{units} has G = {g}, expected {expected}"
+            );
+        }
+    }
+}
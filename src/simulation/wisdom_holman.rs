@@ -0,0 +1,508 @@
+use std::collections::HashMap;
+
+use crate::particle::{Mass, Particle, Radius, TestParticle};
+use crate::simulation::bonds::Bond;
+use crate::simulation::colliders::Collider;
+use crate::simulation::motion::{Acceleration, Fixed, OldPosition, PreviousAcceleration};
+use crate::simulation::sph::Gas;
+use bevy::math::DVec2;
+use bevy::prelude::*;
+
+use super::SimSettings;
+
+/// How particles are moved forward each step
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum Integrator {
+    /// Position verlet with whichever gravity solver is selected
+    Verlet,
+    /// Kepler orbits around the most massive particle with kicks from everything else, see
+    /// [wisdom_holman_step]
+    WisdomHolman,
+}
+
+impl std::fmt::Display for Integrator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Integrator::Verlet => write!(f, "verlet"),
+            Integrator::WisdomHolman => write!(f, "wisdom-holman"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct WisdomHolmanSettings {
+    /// Two bodies closer than this many of their mutual hill radii count as a close encounter,
+    /// and verlet takes over until they separate
+    pub encounter_hill_radii: f32,
+}
+
+impl Default for WisdomHolmanSettings {
+    fn default() -> Self {
+        Self {
+            encounter_hill_radii: 3.0,
+        }
+    }
+}
+
+/// What the hybrid integrator did last step
+#[derive(Resource, Default)]
+pub struct HybridState {
+    /// True when the last step was handed to verlet, because of a close encounter or because
+    /// something is turned on that the kepler drift can't handle
+    pub fallback: bool,
+    /// Velocities from the last step, kept at full precision along with the positions they were
+    /// written with so they are only used if nothing has moved the particle since
+    velocities: HashMap<Entity, (Vec2, Vec2, DVec2)>,
+}
+
+/// Run condition that is true when the chosen integrator is [Integrator::WisdomHolman]
+pub fn wisdom_holman_enabled(settings: Res<SimSettings>) -> bool {
+    settings.integrator == Integrator::WisdomHolman && !settings.enable_particle_life
+}
+
+/// Run condition that is true when particles should be moved by verlet this step
+pub fn verlet_active(settings: Res<SimSettings>, state: Res<HybridState>) -> bool {
+    settings.integrator == Integrator::Verlet || settings.enable_particle_life || state.fallback
+}
+
+/// A particle as the integrator sees it, in double precision
+#[derive(Clone, Copy, Debug)]
+pub struct Body {
+    pub position: DVec2,
+    pub velocity: DVec2,
+    pub mass: f64,
+    pub radius: f64,
+}
+
+/// Stumpff functions `(c2(z), c3(z))`
+fn stumpff(z: f64) -> (f64, f64) {
+    if z > 1e-6 {
+        let root = z.sqrt();
+        ((1.0 - root.cos()) / z, (root - root.sin()) / (root * z))
+    } else if z < -1e-6 {
+        let root = (-z).sqrt();
+        ((root.cosh() - 1.0) / -z, (root.sinh() - root) / (root * -z))
+    } else {
+        (
+            0.5 - z / 24.0 + z * z / 720.0,
+            1.0 / 6.0 - z / 120.0 + z * z / 5040.0,
+        )
+    }
+}
+
+/// Moves a body along its two body orbit around a mass `mu` at the origin for `dt`, works for
+/// any eccentricity. Uses the universal variable formulation with f and g functions
+pub fn kepler_drift(position: DVec2, velocity: DVec2, mu: f64, dt: f64) -> (DVec2, DVec2) {
+    let r0 = position.length();
+    if r0 < 1e-12 || mu <= 0.0 {
+        return (position + velocity * dt, velocity);
+    }
+    let sqrt_mu = mu.sqrt();
+    let radial_velocity = position.dot(velocity) / r0;
+    // reciprocal of the semi major axis, negative for hyperbolic orbits
+    let alpha = 2.0 / r0 - velocity.length_squared() / mu;
+
+    // newton iteration for the universal anomaly
+    let mut chi = if alpha > 1e-12 {
+        sqrt_mu * alpha * dt
+    } else {
+        sqrt_mu * dt / r0
+    };
+    for _ in 0..50 {
+        let z = alpha * chi * chi;
+        let (c, s) = stumpff(z);
+        let f = r0 * radial_velocity / sqrt_mu * chi * chi * c
+            + (1.0 - alpha * r0) * chi * chi * chi * s
+            + r0 * chi
+            - sqrt_mu * dt;
+        let derivative = r0 * radial_velocity / sqrt_mu * chi * (1.0 - z * s)
+            + (1.0 - alpha * r0) * chi * chi * c
+            + r0;
+        let change = f / derivative;
+        chi -= change;
+        if change.abs() < 1e-12 * chi.abs().max(1.0) {
+            break;
+        }
+    }
+
+    let z = alpha * chi * chi;
+    let (c, s) = stumpff(z);
+    let f = 1.0 - chi * chi / r0 * c;
+    let g = dt - chi * chi * chi * s / sqrt_mu;
+    let new_position = f * position + g * velocity;
+
+    let r = new_position.length();
+    let f_dot = sqrt_mu / (r * r0) * (z * s - 1.0) * chi;
+    let g_dot = 1.0 - chi * chi / r * c;
+    let new_velocity = f_dot * position + g_dot * velocity;
+
+    (new_position, new_velocity)
+}
+
+/// Pulls every body except the primary towards every other one, positions are heliocentric
+fn interaction_kick(positions: &[DVec2], velocities: &mut [DVec2], masses: &[f64], dt: f64) {
+    for i in 0..positions.len() {
+        for j in i + 1..positions.len() {
+            let delta = positions[j] - positions[i];
+            let distance_sq = delta.length_squared();
+            if distance_sq < 1e-20 {
+                continue;
+            }
+            let pull = delta / (distance_sq * distance_sq.sqrt()) * dt;
+            velocities[i] += masses[j] * pull;
+            velocities[j] -= masses[i] * pull;
+        }
+    }
+}
+
+/// Shifts heliocentric positions by the momentum of the primary's reflex motion
+fn jump(positions: &mut [DVec2], velocities: &[DVec2], masses: &[f64], primary_mass: f64, dt: f64) {
+    let momentum: DVec2 = velocities
+        .iter()
+        .zip(masses)
+        .map(|(velocity, mass)| *velocity * *mass)
+        .sum();
+    for position in positions.iter_mut() {
+        *position += momentum / primary_mass * dt;
+    }
+}
+
+/// One step of the wisdom-holman map in democratic heliocentric coordinates (duncan, levison and
+/// lee 1998). Every body other than `primary` follows its kepler orbit around the primary, with
+/// half step kicks from the other bodies either side, so the only error comes from the
+/// interactions between them and is symplectic
+pub fn step(bodies: &mut [Body], primary: usize, dt: f64) {
+    let primary_mass = bodies[primary].mass;
+    let total_mass: f64 = bodies.iter().map(|body| body.mass).sum();
+    if primary_mass <= 0.0 {
+        return;
+    }
+
+    let mut center_of_mass = DVec2::ZERO;
+    let mut center_of_mass_velocity = DVec2::ZERO;
+    for body in bodies.iter() {
+        center_of_mass += body.position * body.mass / total_mass;
+        center_of_mass_velocity += body.velocity * body.mass / total_mass;
+    }
+
+    let others: Vec<usize> = (0..bodies.len()).filter(|i| *i != primary).collect();
+    let masses: Vec<f64> = others.iter().map(|i| bodies[*i].mass).collect();
+    let mut positions: Vec<DVec2> = others
+        .iter()
+        .map(|i| bodies[*i].position - bodies[primary].position)
+        .collect();
+    let mut velocities: Vec<DVec2> = others
+        .iter()
+        .map(|i| bodies[*i].velocity - center_of_mass_velocity)
+        .collect();
+
+    interaction_kick(&positions, &mut velocities, &masses, dt / 2.0);
+    jump(&mut positions, &velocities, &masses, primary_mass, dt / 2.0);
+    for (position, velocity) in positions.iter_mut().zip(velocities.iter_mut()) {
+        (*position, *velocity) = kepler_drift(*position, *velocity, primary_mass, dt);
+    }
+    jump(&mut positions, &velocities, &masses, primary_mass, dt / 2.0);
+    interaction_kick(&positions, &mut velocities, &masses, dt / 2.0);
+
+    center_of_mass += center_of_mass_velocity * dt;
+    let mut primary_position = center_of_mass;
+    let mut primary_velocity = center_of_mass_velocity;
+    for ((position, velocity), mass) in positions.iter().zip(&velocities).zip(&masses) {
+        primary_position -= *position * *mass / total_mass;
+        primary_velocity -= *velocity * *mass / primary_mass;
+    }
+
+    bodies[primary].position = primary_position;
+    bodies[primary].velocity = primary_velocity;
+    for (n, i) in others.iter().enumerate() {
+        bodies[*i].position = positions[n] + primary_position;
+        bodies[*i].velocity = velocities[n] + center_of_mass_velocity;
+    }
+}
+
+/// True if any two bodies are within `hill_radii` of their mutual hill radius, or any body is
+/// close to touching the primary
+pub fn close_encounter(bodies: &[Body], primary: usize, hill_radii: f64) -> bool {
+    let center = bodies[primary];
+    for (i, body) in bodies.iter().enumerate() {
+        if i == primary {
+            continue;
+        }
+        if body.position.distance(center.position) < 2.0 * (body.radius + center.radius) {
+            return true;
+        }
+        for (j, other) in bodies.iter().enumerate().skip(i + 1) {
+            if j == primary {
+                continue;
+            }
+            let mean_distance = (body.position.distance(center.position)
+                + other.position.distance(center.position))
+                / 2.0;
+            let hill_radius =
+                ((body.mass + other.mass) / (3.0 * center.mass)).cbrt() * mean_distance;
+            let distance = body.position.distance(other.position);
+            if distance < hill_radii * hill_radius || distance < body.radius + other.radius {
+                return true;
+            }
+        }
+    }
+    false
+}
+
+/// Plain newtonian acceleration of every body, left in [Acceleration] so verlet can carry on
+/// from where this step ended if the next one falls back to it
+fn accelerations(bodies: &[Body]) -> Vec<DVec2> {
+    let mut accelerations = vec![DVec2::ZERO; bodies.len()];
+    for i in 0..bodies.len() {
+        for j in i + 1..bodies.len() {
+            let delta = bodies[j].position - bodies[i].position;
+            let distance = delta
+                .length()
+                .max(bodies[i].radius + bodies[j].radius)
+                .max(1e-10);
+            let pull = delta / (distance * distance * distance);
+            accelerations[i] += bodies[j].mass * pull;
+            accelerations[j] -= bodies[i].mass * pull;
+        }
+    }
+    accelerations
+}
+
+/// Runs a wisdom-holman step around the most massive particle in place of verlet and gravity.
+/// Only gravity is integrated, and anything it can't handle (fixed particles, gas, bonds,
+/// colliders, collisions, particle life, periodic boundaries, cosmology, post-newtonian terms,
+/// close encounters) makes it hand the step to verlet
+#[allow(clippy::too_many_arguments)]
+pub fn wisdom_holman_step(
+    mut particles: Query<
+        (
+            Entity,
+            &mut Transform,
+            &mut OldPosition,
+            &mut Acceleration,
+            &mut PreviousAcceleration,
+            &Mass,
+            &Radius,
+            Has<TestParticle>,
+        ),
+        With<Particle>,
+    >,
+    fixed: Query<(), (With<Particle>, With<Fixed>)>,
+    gas: Query<(), (With<Particle>, With<Gas>)>,
+    bonds: Query<(), With<Bond>>,
+    colliders: Query<(), With<Collider>>,
+    mut state: ResMut<HybridState>,
+    sim_settings: Res<SimSettings>,
+    time: Res<Time>,
+) {
+    let dt = time.delta_secs();
+    if dt == 0.0 {
+        return;
+    }
+
+    // all of these add to the acceleration, which the kepler drift never looks at
+    let unsupported = !fixed.is_empty()
+        || !gas.is_empty()
+        || !bonds.is_empty()
+        || !colliders.is_empty()
+        || sim_settings.enable_collisions
+        || sim_settings.enable_particle_life
+        || sim_settings.periodic.enabled
+        || sim_settings.cosmology.enabled
+        || sim_settings.post_newtonian.enabled();
+
+    let mut entities = Vec::new();
+    let mut bodies = Vec::new();
+    for (
+        entity,
+        transform,
+        old_position,
+        acceleration,
+        _,
+        Mass(mass),
+        Radius(radius),
+        test_particle,
+    ) in particles.iter()
+    {
+        let position = transform.translation.truncate();
+        let old_position = old_position.0.translation.truncate();
+        // verlet only knows the velocity half a step back, so the acceleration is used to bring
+        // it up to date
+        let velocity = match state.velocities.get(&entity) {
+            Some((written, written_old, velocity))
+                if *written == position && *written_old == old_position =>
+            {
+                *velocity
+            }
+            _ => ((position - old_position) / dt + acceleration.0 * dt / 2.0).as_dvec2(),
+        };
+        entities.push(entity);
+        bodies.push(Body {
+            position: position.as_dvec2(),
+            velocity,
+            // scaled by G so everything below can treat it as 1
+            mass: if test_particle {
+                0.0
+            } else {
+                (mass * sim_settings.gravitational_constant) as f64
+            },
+            radius: *radius as f64,
+        });
+    }
+
+    let primary = (0..bodies.len()).max_by(|a, b| bodies[*a].mass.total_cmp(&bodies[*b].mass));
+    let Some(primary) = primary.filter(|primary| {
+        !unsupported
+            && bodies[*primary].mass > 0.0
+            && !close_encounter(
+                &bodies,
+                *primary,
+                sim_settings.wisdom_holman.encounter_hill_radii as f64,
+            )
+    }) else {
+        state.fallback = true;
+        state.velocities.clear();
+        return;
+    };
+
+    step(&mut bodies, primary, dt as f64);
+    let accelerations = accelerations(&bodies);
+
+    state.fallback = false;
+    state.velocities.clear();
+    for (n, entity) in entities.iter().enumerate() {
+        let Ok((
+            _,
+            mut transform,
+            mut old_position,
+            mut acceleration,
+            mut previous_acceleration,
+            ..,
+        )) = particles.get_mut(*entity)
+        else {
+            continue;
+        };
+        let body = bodies[n];
+        let accel = accelerations[n];
+        let position = body.position.as_vec2();
+        let old =
+            (body.position - body.velocity * dt as f64 + accel * (dt * dt) as f64 / 2.0).as_vec2();
+
+        transform.translation = position.extend(0.0);
+        old_position.0.translation = old.extend(0.0);
+        previous_acceleration.0 = accel.as_vec2();
+        acceleration.0 = accel.as_vec2();
+        state
+            .velocities
+            .insert(*entity, (position, old, body.velocity));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        kepler_drift, step, verlet_active, wisdom_holman_enabled, wisdom_holman_step, Body,
+        HybridState, Integrator,
+    };
+    use crate::particle::ParticleBundle;
+    use crate::simulation::bonds::{calc_bond_forces, Bond};
+    use crate::simulation::motion::update_particle_positions;
+    use crate::simulation::SimSettings;
+    use bevy::math::DVec2;
+    use bevy::prelude::*;
+    use std::f64::consts::PI;
+    use std::time::Duration;
+
+    fn energy(bodies: &[Body]) -> f64 {
+        let mut energy = 0.0;
+        for (i, body) in bodies.iter().enumerate() {
+            energy += 0.5 * body.mass * body.velocity.length_squared();
+            for other in &bodies[i + 1..] {
+                energy -= body.mass * other.mass / body.position.distance(other.position);
+            }
+        }
+        energy
+    }
+
+    #[test]
+    fn test_kepler_drift_returns_after_one_period() {
+        let mu = 1000.0;
+        let position = DVec2::new(50.0, 0.0);
+        // eccentric orbit, slower than circular
+        let velocity = DVec2::new(0.0, 0.8 * (mu / 50.0_f64).sqrt());
+        let semi_major_axis = 1.0 / (2.0 / 50.0 - velocity.length_squared() / mu);
+        let period = 2.0 * PI * (semi_major_axis.powi(3) / mu).sqrt();
+
+        let (end, end_velocity) = kepler_drift(position, velocity, mu, period);
+        assert!(end.distance(position) < 1e-6, "ended at {end}");
+        assert!(end_velocity.distance(velocity) < 1e-6);
+    }
+
+    #[test]
+    fn test_planetary_system_conserves_energy() {
+        let body = |mass: f64, distance: f64| Body {
+            position: DVec2::new(distance, 0.0),
+            velocity: DVec2::new(0.0, (1000.0 / distance).sqrt()),
+            mass,
+            radius: 1.0,
+        };
+        let mut bodies = vec![
+            Body {
+                position: DVec2::ZERO,
+                velocity: DVec2::ZERO,
+                mass: 1000.0,
+                radius: 5.0,
+            },
+            body(1.0, 50.0),
+            body(0.5, 80.0),
+        ];
+
+        // about 35 steps per orbit of the inner planet, for 100 orbits
+        let initial = energy(&bodies);
+        for _ in 0..3500 {
+            step(&mut bodies, 0, 2.0);
+        }
+        let drift = ((energy(&bodies) - initial) / initial).abs();
+        assert!(drift < 1e-5, "energy drifted by {drift}");
+    }
+
+    #[test]
+    fn test_bonded_pair_feels_spring_under_wisdom_holman() {
+        let mut app = App::new();
+        app.insert_resource(SimSettings {
+            integrator: Integrator::WisdomHolman,
+            ..default()
+        })
+        .init_resource::<HybridState>()
+        .init_resource::<Time>()
+        .add_systems(
+            Update,
+            (
+                wisdom_holman_step.run_if(wisdom_holman_enabled),
+                update_particle_positions.run_if(verlet_active),
+                calc_bond_forces,
+            )
+                .chain(),
+        );
+
+        let a = app.world_mut().spawn(ParticleBundle::new()).id();
+        let b = app
+            .world_mut()
+            .spawn(ParticleBundle::new().position(Vec2::new(20.0, 0.0)))
+            .id();
+        app.world_mut().spawn(Bond::new(a, b, 10.0));
+
+        // a third of a period of the spring, gravity alone would barely move them
+        for _ in 0..30 {
+            app.world_mut()
+                .resource_mut::<Time>()
+                .advance_by(Duration::from_secs_f32(0.01));
+            app.update();
+        }
+
+        let position = |entity| app.world().get::<Transform>(entity).unwrap().translation;
+        let length = position(a).distance(position(b));
+        assert!(app.world().resource::<HybridState>().fallback);
+        assert!(length < 15.0, "bond is still {length} long");
+    }
+}